@@ -0,0 +1,1054 @@
+use std::path::{Path, PathBuf};
+
+use embedded_graphics::prelude::Point;
+
+use crate::{
+    completion::{self, CompletionKind},
+    config::{CONFIG_FILE, ChannelConfig, Config, ConfigError, validate_channels},
+    gfx::renderers::widgets::{self, Action, Region},
+    history::History,
+    irc::{
+        command::{Command, Input, Moderation},
+        mode::{ban_mask, prefix_at_least},
+        server::IrcServer,
+        transport::Transport,
+        types::{IrcChannelType, strip_prefix},
+    },
+    profile::{PROFILES_DIR, Profile, validate_profile_name},
+    state::{Dirty, Panel, State},
+    theme::{THEMES_DIR, Theme},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PromptKind {
+    Text,
+    Multiline,
+    Number,
+}
+
+/// checks prompt input, an error makes the user try again
+pub type Validator = fn(&str) -> Result<(), String>;
+
+/// a request for some text from the user, on the 3ds this is the software keyboard
+pub struct Prompt {
+    pub hint: String,
+    pub initial_text: String,
+    pub kind: PromptKind,
+    /// if false the user has no way to back out
+    pub cancellable: bool,
+    /// checked before the text is accepted
+    pub validate: Option<Validator>,
+}
+
+impl Prompt {
+    pub fn new(hint: &str) -> Self {
+        Prompt {
+            hint: hint.to_string(),
+            initial_text: String::new(),
+            kind: PromptKind::Text,
+            cancellable: true,
+            validate: None,
+        }
+    }
+
+    pub fn kind(mut self, kind: PromptKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn initial_text(mut self, text: &str) -> Self {
+        self.initial_text = text.to_string();
+        self
+    }
+
+    pub fn required(mut self) -> Self {
+        self.cancellable = false;
+        self
+    }
+
+    pub fn validate(mut self, validate: Validator) -> Self {
+        self.validate = Some(validate);
+        self
+    }
+}
+
+pub trait Prompter {
+    /// returns none if the user cancelled
+    fn prompt(&mut self, prompt: &Prompt) -> Option<String>;
+}
+
+/// the buttons grairc cares about, frontends map their own input onto these
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Key {
+    Start,
+    Up,
+    Down,
+    A,
+    X,
+    Select,
+}
+
+/// a stylus press thats still on the touchscreen
+struct TouchDrag {
+    start: Point,
+    last: Point,
+    dragged: bool,
+}
+
+/// everything grairc does in response to input, independent of what its running on
+pub struct App {
+    pub state: State,
+    pub running: bool,
+    touch: Option<TouchDrag>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        App {
+            state: State::default(),
+            running: true,
+            touch: None,
+        }
+    }
+}
+
+impl App {
+    /// finds the profiles for the picker, moving an old single config into one first
+    pub fn load_profiles(&mut self) {
+        match Profile::load_all() {
+            Ok(profiles) => self.state.set_profiles(profiles),
+            Err(e) => {
+                println!("Failed to load profiles: {}", e);
+                self.state
+                    .push_system_message(format!("couldnt load the profiles: {}", e));
+            }
+        }
+        self.state.push_system_message(
+            "pick a profile on the bottom screen, or make a new one".to_string(),
+        );
+        self.state.set_panel(Panel::Profiles);
+        self.state.redraw_all();
+    }
+
+    /// where the config is saved, the open profiles or the old single config without one
+    pub fn config_path(&self) -> PathBuf {
+        match &self.state.profile {
+            Some(profile) => profile.config_path(),
+            None => PathBuf::from(CONFIG_FILE),
+        }
+    }
+
+    /// loads the config, running the setup wizard if there isnt one yet
+    ///
+    /// returns false if theres one that couldnt be loaded. the error goes to the system channel
+    /// and its back to the profile picker, so it can be read before anything else happens
+    pub fn load_config(&mut self, prompter: &mut dyn Prompter) -> bool {
+        println!("Loading configuration...");
+        let path = self.config_path();
+        let config = match Config::load(&path) {
+            Ok(config) => {
+                println!("Configuration loaded successfully");
+                config
+            }
+            Err(ConfigError::Missing) => {
+                println!("No configuration found, launching setup wizard...");
+                self.new_config(prompter)
+            }
+            Err(e) => {
+                println!("Failed to load configuration: {}", e);
+                self.state
+                    .push_system_message(format!("couldnt load {}: {}", path.display(), e));
+                match e {
+                    // made by a newer grairc, this one doesnt get to touch it
+                    ConfigError::TooNew(_) => self.state.push_system_message(
+                        "it was left as it is, open the profile with a newer grairc".to_string(),
+                    ),
+                    _ => match Config::backup(&path) {
+                        Ok(backup) => self.state.push_system_message(format!(
+                            "it was moved to {}, move it back once its fixed or open the profile again to start over",
+                            backup.display()
+                        )),
+                        Err(e) => self
+                            .state
+                            .push_system_message(format!("couldnt move the old config: {}", e)),
+                    },
+                }
+                self.close_profile();
+                return false;
+            }
+        };
+        self.state.config = Some(config);
+        self.load_themes();
+        self.state.redraw_all();
+        true
+    }
+
+    /// back to the profile picker without a profile open
+    fn close_profile(&mut self) {
+        self.state.profile = None;
+        self.state.log_dir = None;
+        self.state.history = History::default();
+        self.state.set_panel(Panel::Profiles);
+        self.state.redraw_all();
+    }
+
+    /// the open profiles history, theres nothing to load without one
+    pub fn load_history(&mut self) {
+        let Some(profile) = &self.state.profile else {
+            return;
+        };
+        match History::load(&profile.history_path()) {
+            Ok(history) => self.state.history = history,
+            Err(e) => self.state.push_system_message(e),
+        }
+    }
+
+    fn save_history(&mut self) {
+        let Some(profile) = &self.state.profile else {
+            return;
+        };
+        if let Err(e) = self.state.history.save(&profile.history_path()) {
+            self.state
+                .push_system_message(format!("failed to save the history: {}", e));
+        }
+    }
+
+    fn new_config(&mut self, prompter: &mut dyn Prompter) -> Config {
+        let config = Config::default().setup_wizard(prompter);
+        match config.save(&self.config_path()) {
+            Ok(()) => println!("Configuration saved successfully"),
+            Err(e) => self
+                .state
+                .push_system_message(format!("failed to save the config: {}", e)),
+        }
+        config
+    }
+
+    /// the built in themes plus whatever is in `THEMES_DIR`, then switches to the configured one
+    fn load_themes(&mut self) {
+        let mut themes = Theme::builtin();
+        for theme in Theme::load_custom(Path::new(THEMES_DIR)) {
+            match theme {
+                Ok(theme) => {
+                    // custom themes with a builtin name replace the builtin one
+                    themes.retain(|existing| existing.name != theme.name);
+                    themes.push(theme);
+                }
+                Err(e) => self.state.push_system_message(e),
+            }
+        }
+        self.state.themes = themes;
+
+        let Some(name) = self
+            .state
+            .config
+            .as_ref()
+            .map(|config| config.theme.clone())
+        else {
+            return;
+        };
+        if !self.state.set_theme(&name) {
+            self.state
+                .push_system_message(format!("theme {} not found, using the default", name));
+        }
+    }
+
+    pub fn key_down<T: Transport>(
+        &mut self,
+        key: Key,
+        irc_server: &mut IrcServer<T>,
+        prompter: &mut dyn Prompter,
+    ) {
+        match key {
+            Key::Start => self.running = false,
+            // the d-pad picks a completion while the picker is open
+            Key::Down if self.state.panel == Panel::Compose => {
+                self.state.move_completion_selection(1)
+            }
+            Key::Up if self.state.panel == Panel::Compose => {
+                self.state.move_completion_selection(-1)
+            }
+            Key::Down if self.state.panel == Panel::Directory => self
+                .state
+                .update_directory(|directory| directory.move_selection(1)),
+            Key::Up if self.state.panel == Panel::Directory => self
+                .state
+                .update_directory(|directory| directory.move_selection(-1)),
+            Key::A if self.state.panel == Panel::Directory => {
+                self.perform(Action::JoinListed, irc_server, prompter)
+            }
+            Key::Down if self.state.panel == Panel::ModeList => self.state.move_mask_selection(1),
+            Key::Up if self.state.panel == Panel::ModeList => self.state.move_mask_selection(-1),
+            Key::Down if self.state.panel == Panel::User => self.state.scroll_user_list(1),
+            Key::Up if self.state.panel == Panel::User => self.state.scroll_user_list(-1),
+            Key::Down if self.state.panel == Panel::Settings => {
+                widgets::scroll_settings(&mut self.state, 1)
+            }
+            Key::Up if self.state.panel == Panel::Settings => {
+                widgets::scroll_settings(&mut self.state, -1)
+            }
+            Key::Down => {
+                self.state.switch_channels(1);
+                widgets::scroll_to_selected(&mut self.state);
+            }
+            Key::Up => {
+                self.state.switch_channels(-1);
+                widgets::scroll_to_selected(&mut self.state);
+            }
+            Key::A => self.perform(Action::Send, irc_server, prompter),
+            Key::X => self.perform(Action::Raw, irc_server, prompter),
+            Key::Select => self.perform(Action::Settings, irc_server, prompter),
+        }
+    }
+
+    /// d-pad and buttons while the profile picker is up, before theres a server to talk to
+    pub fn picker_key_down(&mut self, key: Key, prompter: &mut dyn Prompter) {
+        match key {
+            Key::Start => self.running = false,
+            Key::Down => self.state.move_profile_selection(1),
+            Key::Up => self.state.move_profile_selection(-1),
+            Key::A => self.perform_picker(Action::OpenProfile, prompter),
+            Key::X | Key::Select => {}
+        }
+    }
+
+    pub fn picker_touch_up(&mut self, prompter: &mut dyn Prompter) {
+        if let Some(action) = self.take_tap() {
+            self.perform_picker(action, prompter);
+        }
+    }
+
+    fn perform_picker(&mut self, action: Action, prompter: &mut dyn Prompter) {
+        let root = Path::new(PROFILES_DIR);
+        let selected = self
+            .state
+            .profiles
+            .get(self.state.profile_selection)
+            .cloned();
+
+        match action {
+            Action::SelectProfile(index) => {
+                // tapping the highlighted one again opens it
+                if index == self.state.profile_selection {
+                    self.perform_picker(Action::OpenProfile, prompter);
+                } else {
+                    self.state.select_profile(index);
+                }
+            }
+            Action::OpenProfile => {
+                let Some(profile) = selected else {
+                    return;
+                };
+                if let Err(e) = std::fs::create_dir_all(profile.logs_dir()) {
+                    self.state
+                        .push_system_message(format!("couldnt create the logs folder: {}", e));
+                }
+                self.state
+                    .push_system_message(format!("using profile {}", profile.name));
+                self.state.log_dir = Some(profile.logs_dir());
+                self.state.profile = Some(profile);
+                self.load_history();
+                self.state.set_panel(Panel::Info);
+                self.state.redraw_all();
+            }
+            Action::NewProfile => {
+                let name = prompter.prompt(
+                    &Prompt::new("enter a name for the new profile")
+                        .validate(validate_profile_name),
+                );
+                self.state.redraw_all();
+                let Some(name) = name else {
+                    return;
+                };
+                let result = Profile::create(root, name.trim());
+                self.profile_changed(result.map(|profile| profile.name));
+            }
+            Action::DuplicateProfile => {
+                let Some(profile) = selected else {
+                    return;
+                };
+                let name = prompter.prompt(
+                    &Prompt::new(&format!("enter a name for the copy of {}", profile.name))
+                        .initial_text(&format!("{} copy", profile.name))
+                        .validate(validate_profile_name),
+                );
+                self.state.redraw_all();
+                let Some(name) = name else {
+                    return;
+                };
+                let result = profile.duplicate(name.trim());
+                self.profile_changed(result.map(|profile| profile.name));
+            }
+            Action::DeleteProfile => {
+                let Some(profile) = selected else {
+                    return;
+                };
+                let answer = prompter.prompt(&Prompt::new(&format!(
+                    "type yes to delete {} and its logs",
+                    profile.name
+                )));
+                self.state.redraw_all();
+                if answer.is_none_or(|answer| answer.trim() != "yes") {
+                    return;
+                }
+                let result = profile.delete();
+                self.profile_changed(result.map(|()| String::new()));
+            }
+            _ => {}
+        }
+    }
+
+    /// rereads the profiles after one was made or deleted and highlights `result`s profile
+    fn profile_changed(&mut self, result: Result<String, std::io::Error>) {
+        match result {
+            Ok(name) => {
+                self.state
+                    .set_profiles(Profile::list(Path::new(PROFILES_DIR)));
+                if let Some(index) = self.state.profiles.iter().position(|p| p.name == name) {
+                    self.state.select_profile(index);
+                }
+            }
+            Err(e) => self.state.push_system_message(e.to_string()),
+        }
+    }
+
+    pub fn touch_down(&mut self, point: Point) {
+        self.touch = Some(TouchDrag {
+            start: point,
+            last: point,
+            dragged: false,
+        });
+    }
+
+    pub fn touch_held(&mut self, point: Point) {
+        let Some(drag) = &mut self.touch else {
+            return;
+        };
+
+        if (point.y - drag.start.y).abs() > widgets::DRAG_THRESHOLD {
+            drag.dragged = true;
+        }
+        if drag.dragged && widgets::in_region(&self.state, Region::Channels, drag.start) {
+            widgets::scroll_channels(&mut self.state, drag.last.y - point.y);
+        }
+        drag.last = point;
+    }
+
+    /// taps fire when the stylus is lifted so that drags dont also count as taps
+    pub fn touch_up<T: Transport>(
+        &mut self,
+        irc_server: &mut IrcServer<T>,
+        prompter: &mut dyn Prompter,
+    ) {
+        if let Some(action) = self.take_tap() {
+            self.perform(action, irc_server, prompter);
+        }
+    }
+
+    /// the action under the stylus if it was lifted without dragging
+    fn take_tap(&mut self) -> Option<Action> {
+        let drag = self.touch.take()?;
+        if drag.dragged {
+            return None;
+        }
+        widgets::hit(&self.state, drag.start)
+    }
+
+    pub fn perform<T: Transport>(
+        &mut self,
+        action: Action,
+        irc_server: &mut IrcServer<T>,
+        prompter: &mut dyn Prompter,
+    ) {
+        match action {
+            Action::SwitchChannel(index) => {
+                self.state.select_channel(index);
+                widgets::scroll_to_selected(&mut self.state);
+            }
+            // a second press sends whatever is highlighted, or nothing
+            Action::Send if self.state.panel == Panel::Compose => {
+                let text = self.state.completion_selection.and_then(|index| {
+                    completion::completions(&self.state, self.state.completion_kind)
+                        .get(index)
+                        .map(|completion| completion.text.clone())
+                });
+                self.compose(&text.unwrap_or_default(), irc_server, prompter);
+            }
+            Action::Send => {
+                // theres nobody to talk to in the system channel, only commands
+                let kind = match self.state.current_channel_static().channel_type {
+                    IrcChannelType::System => CompletionKind::Commands,
+                    _ => CompletionKind::Nicks,
+                };
+                self.state.set_completion_kind(kind);
+                self.state.set_panel(Panel::Compose);
+            }
+            Action::Complete(index) => {
+                let completions = completion::completions(&self.state, self.state.completion_kind);
+                let Some(completion) = completions.get(index) else {
+                    return;
+                };
+                self.compose(&completion.text, irc_server, prompter);
+            }
+            Action::CompletionKind(kind) => self.state.set_completion_kind(kind),
+            Action::Raw
+                if self.state.panel == Panel::Compose
+                    && self.state.completion_kind == CompletionKind::RawHistory =>
+            {
+                self.perform(Action::Send, irc_server, prompter);
+            }
+            Action::Raw => {
+                self.state.set_completion_kind(CompletionKind::RawHistory);
+                self.state.set_panel(Panel::Compose);
+            }
+            Action::Join => {
+                let prompt = Prompt::new("channel to join (add the key after a space)")
+                    .validate(validate_channels);
+                let input = prompter.prompt(&prompt);
+                self.state.redraw_all();
+                let Some(input) = input else {
+                    return;
+                };
+                let mut words = input.split_whitespace();
+                let Some(channel) = words.next() else {
+                    return;
+                };
+                self.join(channel, words.next(), irc_server);
+            }
+            Action::Part => {
+                let selected_channel = self.state.current_channel_static();
+                if selected_channel.channel_type != IrcChannelType::Channel {
+                    return;
+                }
+
+                let name = selected_channel.name.clone();
+                self.part(&name, None, irc_server);
+            }
+            Action::MoveChannel(change) => {
+                let Some(index) = self.state.channels.iter().position(|ch| ch.selected) else {
+                    return;
+                };
+                if !self.state.move_channel(index, change) {
+                    return;
+                }
+                widgets::scroll_to_selected(&mut self.state);
+                // autojoin in the same order next time
+                let order: Vec<String> = self
+                    .state
+                    .channels
+                    .iter()
+                    .map(|ch| ch.name.to_lowercase())
+                    .collect();
+                if let Some(config) = &mut self.state.config {
+                    config.channels.sort_by_key(|channel| {
+                        order
+                            .iter()
+                            .position(|name| *name == channel.name.to_lowercase())
+                            .unwrap_or(usize::MAX)
+                    });
+                }
+                self.save_config();
+            }
+            Action::Settings => {
+                let panel = match self.state.panel {
+                    Panel::Info => Panel::Settings,
+                    Panel::Settings => Panel::Info,
+                    // back to the rest of the settings
+                    Panel::Channel => Panel::Settings,
+                    Panel::Compose | Panel::User | Panel::Directory => Panel::Info,
+                    Panel::Moderation => Panel::Channel,
+                    Panel::ModeList => Panel::Moderation,
+                    Panel::Profiles => return,
+                };
+                self.state.set_panel(panel);
+            }
+            Action::ChannelSettings => {
+                if self.state.current_channel_static().channel_type == IrcChannelType::Channel {
+                    self.state.set_panel(Panel::Channel);
+                }
+            }
+            Action::Users => {
+                self.state.select_user(None);
+                self.state.set_panel(Panel::User);
+            }
+            Action::SelectUser(index) => {
+                let Some(user) = self.state.current_channel_static().users.get(index) else {
+                    return;
+                };
+                let nick = strip_prefix(user).to_string();
+                self.whois(&nick, irc_server);
+            }
+            Action::Directory => self.browse(None, irc_server),
+            Action::DirectorySort(sort) => self
+                .state
+                .update_directory(|directory| directory.set_sort(sort)),
+            Action::DirectoryFilter => {
+                let prompt = Prompt::new("channels to look for, empty shows all")
+                    .initial_text(&self.state.directory.filter);
+                let input = prompter.prompt(&prompt);
+                self.state.redraw_all();
+                let Some(input) = input else {
+                    return;
+                };
+                self.state
+                    .update_directory(|directory| directory.set_filter(&input));
+            }
+            Action::SelectListed(index) if self.state.directory.is_selected(index) => {
+                self.perform(Action::JoinListed, irc_server, prompter);
+            }
+            Action::SelectListed(index) => self
+                .state
+                .update_directory(|directory| directory.select(index)),
+            Action::JoinListed => {
+                let Some(channel) = self.state.directory.selected() else {
+                    return;
+                };
+                self.state.set_panel(Panel::Info);
+                self.join(&channel, None, irc_server);
+            }
+            Action::Moderation => {
+                let channel = self.state.current_channel_static();
+                if channel.channel_type != IrcChannelType::Channel {
+                    return;
+                }
+                // the reply (324) fills in the modes
+                let line = format!("MODE {}", channel.name);
+                self.state.set_panel(Panel::Moderation);
+                if let Err(e) = irc_server.irc_raw_send(&line) {
+                    self.send_failed(e);
+                }
+            }
+            Action::ToggleMode(mode) => {
+                let channel = self.state.current_channel_static();
+                if !prefix_at_least(self.state.our_prefix(), '@') {
+                    return;
+                }
+                let sign = if channel.modes.contains_key(&mode) {
+                    '-'
+                } else {
+                    '+'
+                };
+                let line = format!("MODE {} {}{}", channel.name, sign, mode);
+                if let Err(e) = irc_server.irc_raw_send(&line) {
+                    self.send_failed(e);
+                }
+            }
+            Action::SetTopic => {
+                let name = self.state.current_channel_static().name.clone();
+                let prompt = Prompt::new(&format!("new topic for {}, empty keeps it", name));
+                let input = prompter.prompt(&prompt);
+                self.state.redraw_all();
+                // an empty TOPIC would clear it, thats never what an empty keyboard means
+                let Some(topic) = input.filter(|topic| !topic.trim().is_empty()) else {
+                    return;
+                };
+                let line = Command::Topic(Some(topic)).line(Some(&name));
+                if let Ok(line) = line
+                    && let Err(e) = irc_server.irc_raw_send(&line)
+                {
+                    self.send_failed(e);
+                }
+            }
+            Action::ModeList(mode) => {
+                let name = self.state.current_channel_static().name.clone();
+                self.state.open_mode_list(mode);
+                if let Err(e) = irc_server.irc_raw_send(&format!("MODE {} +{}", name, mode)) {
+                    self.send_failed(e);
+                }
+            }
+            // the list is shown read only, theres nothing to remove
+            Action::SelectMask(_) if !self.state.can_edit_mode_list() => {}
+            Action::SelectMask(index) if self.state.mask_selection == Some(index) => {
+                let Some(mask) = self.state.mode_list_masks().get(index).cloned() else {
+                    return;
+                };
+                let name = self.state.current_channel_static().name.clone();
+                let line = format!("MODE {} -{} {}", name, self.state.mode_list, mask);
+                self.state.mask_selection = None;
+                if let Err(e) = irc_server.irc_raw_send(&line) {
+                    self.send_failed(e);
+                }
+            }
+            Action::SelectMask(index) => {
+                self.state.mask_selection = Some(index);
+                self.state.mark_dirty(Dirty::INFO);
+            }
+            Action::Moderate(moderation) => {
+                let Some(nick) = self.state.selected_user.clone() else {
+                    return;
+                };
+                let channel = self.state.current_channel_static();
+                if !channel.has_user(&nick)
+                    || !Moderation::available(self.state.our_prefix(), channel.prefixes_of(&nick))
+                        .contains(&moderation)
+                {
+                    return;
+                }
+                let name = channel.name.clone();
+                let host = self
+                    .state
+                    .get_user_info(&nick)
+                    .and_then(|info| info.host.as_deref());
+                let mask = ban_mask(&nick, host);
+
+                let reason = match moderation {
+                    Moderation::Kick | Moderation::KickBan => {
+                        let prompt = Prompt::new(&format!("why is {} being kicked", nick));
+                        let input = prompter.prompt(&prompt);
+                        self.state.redraw_all();
+                        // cancelling doesnt kick, leaving it empty kicks without a reason
+                        let Some(reason) = input else {
+                            return;
+                        };
+                        Some(reason).filter(|reason| !reason.trim().is_empty())
+                    }
+                    _ => None,
+                };
+                for line in moderation.lines(&name, &nick, &mask, reason.as_deref()) {
+                    if let Err(e) = irc_server.irc_raw_send(&line) {
+                        self.send_failed(e);
+                        return;
+                    }
+                }
+            }
+            Action::ChannelOption(option) => {
+                let channel = self.state.current_channel_static();
+                if channel.channel_type != IrcChannelType::Channel {
+                    return;
+                }
+                let name = channel.name.clone();
+                let Some(mut config) = self.state.config.clone() else {
+                    return;
+                };
+                // channels joined by hand get added to the config, so theyre joined next time too
+                if config.channel(&name).is_none() {
+                    config.channels.push(ChannelConfig::new(&name));
+                }
+                let Some(channel) = config.channel_mut(&name) else {
+                    return;
+                };
+
+                if !option.toggle(channel) {
+                    let Some(prompt) = option.prompt(channel) else {
+                        return;
+                    };
+                    let input = prompter.prompt(&prompt);
+                    self.state.redraw_all();
+                    let Some(input) = input else {
+                        return;
+                    };
+                    option.set(channel, &input);
+                }
+                self.apply_config(config, irc_server);
+            }
+            Action::SelectTheme(index) => {
+                let Some(theme) = self.state.themes.get(index) else {
+                    return;
+                };
+                let name = theme.name.clone();
+                self.state.set_theme(&name);
+                if let Some(config) = &mut self.state.config {
+                    config.theme = name;
+                }
+                self.save_config();
+            }
+            Action::EditSetting(setting) => {
+                let Some(mut config) = self.state.config.clone() else {
+                    return;
+                };
+                if !setting.toggle(&mut config) {
+                    let input = prompter.prompt(&setting.prompt(&config));
+                    self.state.redraw_all();
+                    let Some(input) = input else {
+                        return;
+                    };
+                    setting.set(&mut config, &input);
+                }
+                self.apply_config(config, irc_server);
+            }
+            // the profile picker is done before theres a server, see `perform_picker`
+            Action::SelectProfile(_)
+            | Action::OpenProfile
+            | Action::NewProfile
+            | Action::DuplicateProfile
+            | Action::DeleteProfile => {}
+        }
+    }
+
+    /// opens the keyboard starting with `initial_text`, then sends whatever was typed to the
+    /// channel on screen, or runs it if its a command
+    fn compose<T: Transport>(
+        &mut self,
+        initial_text: &str,
+        irc_server: &mut IrcServer<T>,
+        prompter: &mut dyn Prompter,
+    ) {
+        self.state.set_panel(Panel::Info);
+        let raw = self.state.completion_kind == CompletionKind::RawHistory;
+        let hint = if raw {
+            "raw irc command"
+        } else {
+            "message or /command"
+        };
+        let text = prompter.prompt(&Prompt::new(hint).initial_text(initial_text));
+        self.state.redraw_all();
+        let Some(text) = text else {
+            return;
+        };
+
+        let channel = self.state.current_channel_static().clone();
+        if raw {
+            self.state.history.push_raw(&text);
+        } else {
+            self.state.history.push(&channel.name, &text);
+        }
+        self.save_history();
+        if raw {
+            if let Err(e) = irc_server.irc_raw_send(&text) {
+                self.send_failed(e);
+            }
+            return;
+        }
+
+        let result = match Input::parse(&text) {
+            Ok(Input::Message(_)) if channel.channel_type == IrcChannelType::System => {
+                self.state.push_system_message(
+                    "theres nobody to talk to here, try /join #channel".to_string(),
+                );
+                return;
+            }
+            Ok(Input::Message(message)) => {
+                irc_server.irc_send(&message, &channel.name, &mut self.state)
+            }
+            Ok(Input::Command(Command::Msg { target, text })) => {
+                irc_server.irc_send(&text, &target, &mut self.state)
+            }
+            Ok(Input::Command(Command::Whois(nick))) => {
+                self.whois(&nick, irc_server);
+                return;
+            }
+            Ok(Input::Command(Command::List(filter))) => {
+                self.browse(filter.as_deref(), irc_server);
+                return;
+            }
+            Ok(Input::Command(Command::Join { channel, key })) => {
+                self.join(&channel, key.as_deref(), irc_server);
+                return;
+            }
+            Ok(Input::Command(Command::Part {
+                channel: Some(target),
+                reason,
+            })) => {
+                self.part(&target, reason.as_deref(), irc_server);
+                return;
+            }
+            Ok(Input::Command(Command::Part {
+                channel: None,
+                reason,
+            })) if channel.channel_type == IrcChannelType::Channel => {
+                self.part(&channel.name, reason.as_deref(), irc_server);
+                return;
+            }
+            Ok(Input::Command(command)) => {
+                let channel_name = (channel.channel_type == IrcChannelType::Channel)
+                    .then_some(channel.name.as_str());
+                match command.line(channel_name) {
+                    Ok(line) => irc_server.irc_raw_send(&line),
+                    Err(e) => {
+                        self.state.push_system_message(e);
+                        return;
+                    }
+                }
+            }
+            Err(e) => {
+                self.state.push_system_message(e);
+                return;
+            }
+        };
+        if let Err(e) = result {
+            self.send_failed(e);
+        }
+    }
+
+    /// asks the server about `nick` and shows the answer in the user panel as it comes in
+    fn whois<T: Transport>(&mut self, nick: &str, irc_server: &mut IrcServer<T>) {
+        self.state.start_lookup(nick);
+        self.state.select_user(Some(nick));
+        self.state.set_panel(Panel::User);
+        let line = Command::Whois(nick.to_string()).line(None);
+        if let Ok(line) = line
+            && let Err(e) = irc_server.irc_raw_send(&line)
+        {
+            self.send_failed(e);
+        }
+    }
+
+    /// joins `channel` and switches to it once the server says were in, adding it to the
+    /// autojoin channels if the config says to
+    fn join<T: Transport>(
+        &mut self,
+        channel: &str,
+        key: Option<&str>,
+        irc_server: &mut IrcServer<T>,
+    ) {
+        if let Some(index) = self
+            .state
+            .channels
+            .iter()
+            .position(|ch| ch.joined && ch.name.eq_ignore_ascii_case(channel))
+        {
+            self.state.select_channel(index);
+            widgets::scroll_to_selected(&mut self.state);
+            return;
+        }
+        self.state.focus_on_join = Some(channel.to_string());
+
+        if let Some(config) = &mut self.state.config
+            && config.remember_channels
+            && config.channel(channel).is_none()
+        {
+            config.channels.push(ChannelConfig {
+                key: key.map(|key| key.to_string()),
+                ..ChannelConfig::new(channel)
+            });
+            // new autojoin channels get joined right away
+            let channels = config.channels.clone();
+            if let Err(e) = irc_server.set_channels(&channels) {
+                self.send_failed(e);
+            }
+            self.save_config();
+            return;
+        }
+
+        let line = Command::Join {
+            channel: channel.to_string(),
+            key: key.map(|key| key.to_string()),
+        }
+        .line(None);
+        if let Ok(line) = line
+            && let Err(e) = irc_server.irc_raw_send(&line)
+        {
+            self.send_failed(e);
+        }
+    }
+
+    /// leaves `channel`, its closed once the server confirms. channels we were kicked from are
+    /// just closed
+    fn part<T: Transport>(
+        &mut self,
+        channel: &str,
+        reason: Option<&str>,
+        irc_server: &mut IrcServer<T>,
+    ) {
+        if let Some(config) = &mut self.state.config
+            && config.remember_channels
+            && config.channel(channel).is_some()
+        {
+            config
+                .channels
+                .retain(|configured| !configured.name.eq_ignore_ascii_case(channel));
+            let channels = config.channels.clone();
+            if let Err(e) = irc_server.set_channels(&channels) {
+                self.send_failed(e);
+            }
+            self.save_config();
+        }
+
+        let kicked_from = self
+            .state
+            .channels
+            .iter()
+            .find(|ch| !ch.joined && ch.name.eq_ignore_ascii_case(channel))
+            .map(|ch| ch.name.clone());
+        if let Some(name) = kicked_from {
+            self.state.close_channel(&name);
+            return;
+        }
+
+        let line = Command::Part {
+            channel: Some(channel.to_string()),
+            reason: reason.map(|reason| reason.to_string()),
+        }
+        .line(None);
+        if let Ok(line) = line
+            && let Err(e) = irc_server.irc_raw_send(&line)
+        {
+            self.send_failed(e);
+        }
+    }
+
+    /// opens the channel browser and fills it with a fresh LIST, `filter` replaces the last one
+    /// if theres one
+    fn browse<T: Transport>(&mut self, filter: Option<&str>, irc_server: &mut IrcServer<T>) {
+        self.state.update_directory(|directory| {
+            directory.start();
+            if let Some(filter) = filter {
+                directory.set_filter(filter);
+            }
+        });
+        self.state.set_panel(Panel::Directory);
+        let line = Command::List(None).line(None);
+        if let Ok(line) = line
+            && let Err(e) = irc_server.irc_raw_send(&line)
+        {
+            self.state.update_directory(|directory| directory.finish());
+            self.send_failed(e);
+        }
+    }
+
+    /// switches to `config`, telling the server about whatever changed, then saves it
+    fn apply_config<T: Transport>(&mut self, config: Config, irc_server: &mut IrcServer<T>) {
+        let Some(old) = self.state.config.replace(config.clone()) else {
+            return;
+        };
+        // channel settings change how messages are drawn
+        self.state.mark_dirty(Dirty::INFO.union(Dirty::MESSAGES));
+
+        // the nick and channels are only stored while disconnected, registering sends them
+        if config.server_hostname != old.server_hostname || config.server_port != old.server_port {
+            irc_server.set_server(&config.server_hostname, config.server_port, &mut self.state);
+        }
+        if config.send_burst != old.send_burst || config.send_interval_ms != old.send_interval_ms {
+            irc_server.set_flood_control(config.send_burst, config.send_interval());
+        }
+        if config.ping_interval_secs != old.ping_interval_secs
+            || config.ping_timeout_secs != old.ping_timeout_secs
+        {
+            irc_server.set_ping_timing(config.ping_interval(), config.ping_timeout());
+        }
+        if config.nickname != old.nickname
+            && let Err(e) = irc_server.set_nick(&config.nickname)
+        {
+            self.send_failed(e);
+        }
+        if config.channels != old.channels
+            && let Err(e) = irc_server.set_channels(&config.channels)
+        {
+            self.send_failed(e);
+        }
+
+        if self.save_config() {
+            self.state.push_system_message("settings saved".to_string());
+        }
+    }
+
+    /// returns false if saving failed, the error is shown in the system channel
+    fn save_config(&mut self) -> bool {
+        let Some(config) = &self.state.config else {
+            return false;
+        };
+        match config.save(&self.config_path()) {
+            Ok(()) => true,
+            Err(e) => {
+                self.state
+                    .push_system_message(format!("failed to save the config: {}", e));
+                false
+            }
+        }
+    }
+
+    fn send_failed(&mut self, error: std::io::Error) {
+        self.state
+            .push_system_message(format!("failed to send to the server: {}", error));
+    }
+}
@@ -0,0 +1,182 @@
+//! runs grairc on a pc, drawing into in-memory framebuffers that get written out as pngs
+//!
+//! usage: `grairc-sim <server> <port> <nick> [channels...]`
+//!
+//! input comes from stdin, one command per line:
+//! - `up` / `down` switch channels like the d-pad, or pick a completion while the picker is open
+//! - `a` opens the completion picker, `a <text>` skips it and sends a message or /command
+//! - `x` opens the raw command history, `x <raw>` skips it and sends a raw irc command
+//! - `tap <x> <y> [text]` taps the bottom screen, the text answers any prompt it opens
+//! - `select` opens and closes the settings
+//! - `quit` exits
+//!
+//! the screens are written to `sim-out/top.png` and `sim-out/bottom.png` whenever they change,
+//! settings, history and logs go in `sim-out/profiles/simulator`
+
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufWriter},
+    path::Path,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use anyhow::Error;
+use embedded_graphics::prelude::Point;
+use grairc::{
+    app::{App, Key, Prompt, Prompter},
+    config::{ChannelConfig, Config},
+    gfx::{
+        memory::{MemoryDisplay, MemoryTargets},
+        renderers::Renderer,
+    },
+    irc::server::IrcServer,
+    profile::Profile,
+    state::{Dirty, Panel},
+};
+
+const OUTPUT_DIR: &str = "sim-out";
+const FRAME_TIME: Duration = Duration::from_millis(100);
+
+/// answers prompts with whatever text came along with the command, theres no keyboard to pop
+/// up so anything else just gets cancelled
+#[derive(Default)]
+struct SimPrompter {
+    pending: Option<String>,
+}
+
+impl Prompter for SimPrompter {
+    fn prompt(&mut self, prompt: &Prompt) -> Option<String> {
+        let text = self.pending.take()?;
+        if let Some(validate) = prompt.validate
+            && let Err(e) = validate(&text)
+        {
+            eprintln!("{}: {}", prompt.hint, e);
+            return None;
+        }
+        Some(text)
+    }
+}
+
+fn read_stdin() -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+fn write_png<const W: u32, const H: u32>(
+    display: &MemoryDisplay<W, H>,
+    path: &Path,
+) -> Result<(), Error> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), W, H);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()?
+        .write_image_data(&display.to_rgb_bytes())?;
+    Ok(())
+}
+
+fn handle_command(
+    line: &str,
+    app: &mut App,
+    irc_server: &mut IrcServer,
+    prompter: &mut SimPrompter,
+) {
+    let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+    match command {
+        "up" => app.key_down(Key::Up, irc_server, prompter),
+        "down" => app.key_down(Key::Down, irc_server, prompter),
+        "a" | "x" => {
+            prompter.pending = (!rest.is_empty()).then(|| rest.to_string());
+            let key = if command == "a" { Key::A } else { Key::X };
+            app.key_down(key, irc_server, prompter);
+            // the first press only opens the picker
+            if app.state.panel == Panel::Compose && prompter.pending.is_some() {
+                app.key_down(key, irc_server, prompter);
+            }
+        }
+        "tap" => {
+            let mut parts = rest.splitn(3, ' ');
+            let (Some(Ok(x)), Some(Ok(y))) =
+                (parts.next().map(str::parse), parts.next().map(str::parse))
+            else {
+                eprintln!("usage: tap <x> <y> [text]");
+                return;
+            };
+            prompter.pending = parts.next().map(|text| text.to_string());
+            app.touch_down(Point::new(x, y));
+            app.touch_up(irc_server, prompter);
+        }
+        "select" => app.key_down(Key::Select, irc_server, prompter),
+        "quit" | "start" => app.key_down(Key::Start, irc_server, prompter),
+        "" => {}
+        _ => eprintln!("unknown command: {}", command),
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [server_hostname, server_port, nickname, channels @ ..] = &args[..] else {
+        eprintln!("usage: grairc-sim <server> <port> <nick> [channels...]");
+        std::process::exit(1);
+    };
+
+    let mut app = App::default();
+    app.state.config = Some(Config {
+        nickname: nickname.clone(),
+        server_hostname: server_hostname.clone(),
+        server_port: server_port.parse()?,
+        channels: channels
+            .iter()
+            .map(|name| ChannelConfig::new(name))
+            .collect(),
+        ..Config::default()
+    });
+    // keeps saved settings and logs out of the real profiles
+    let profile = Profile::new(&Path::new(OUTPUT_DIR).join("profiles"), "simulator");
+    fs::create_dir_all(profile.logs_dir())?;
+    app.state.log_dir = Some(profile.logs_dir());
+    app.state.profile = Some(profile);
+    app.load_history();
+    let config = app.state.config.as_ref().unwrap();
+
+    let mut irc_server = IrcServer::new(&config.server_hostname, &config.server_port);
+    irc_server.set_flood_control(config.send_burst, config.send_interval());
+    irc_server.set_ping_timing(config.ping_interval(), config.ping_timeout());
+    irc_server.irc_ident(&config.nickname, &config.channels)?;
+
+    let mut targets = MemoryTargets::default();
+    let mut renderer = Renderer::default();
+    let mut prompter = SimPrompter::default();
+    let commands = read_stdin();
+
+    while app.running {
+        let changed = app.state.dirty != Dirty::NONE;
+        renderer.render(&mut targets, &mut app.state)?;
+        if changed {
+            write_png(&targets.top, &Path::new(OUTPUT_DIR).join("top.png"))?;
+            write_png(&targets.bottom, &Path::new(OUTPUT_DIR).join("bottom.png"))?;
+        }
+
+        irc_server.handler(&mut app.state);
+
+        while let Ok(line) = commands.try_recv() {
+            handle_command(line.trim(), &mut app, &mut irc_server, &mut prompter);
+        }
+
+        thread::sleep(FRAME_TIME);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use crate::{
+    irc::{
+        command::COMMANDS,
+        types::{IrcChannelType, strip_prefix},
+    },
+    state::State,
+};
+
+/// the tabs of the completion picker shown before the message keyboard
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CompletionKind {
+    Nicks,
+    Channels,
+    Commands,
+    /// lines sent in the channel on screen
+    History,
+    /// the picker for the X button, whatever is picked gets sent as is
+    RawHistory,
+}
+
+impl CompletionKind {
+    /// the tabs of the message picker, the raw one only has its history
+    pub const MESSAGE: [CompletionKind; 4] = [
+        CompletionKind::Nicks,
+        CompletionKind::Channels,
+        CompletionKind::Commands,
+        CompletionKind::History,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CompletionKind::Nicks => "nicks",
+            CompletionKind::Channels => "chans",
+            CompletionKind::Commands => "cmds",
+            CompletionKind::History => "sent",
+            CompletionKind::RawHistory => "sent raw commands",
+        }
+    }
+}
+
+/// something to start a message with, typing nicks on the 3ds keyboard is no fun
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Completion {
+    /// whats shown in the picker
+    pub label: String,
+    /// what the keyboard starts out with
+    pub text: String,
+}
+
+/// sent lines go back into the keyboard unchanged
+fn history(lines: &[String]) -> Vec<Completion> {
+    lines
+        .iter()
+        .map(|line| Completion {
+            label: line.clone(),
+            text: line.clone(),
+        })
+        .collect()
+}
+
+/// the choices for `kind` in the channel on screen
+///
+/// nicks are whoever spoke most recently first, then everyone else alphabetically
+pub fn completions(state: &State, kind: CompletionKind) -> Vec<Completion> {
+    let channel = state.current_channel_static();
+    match kind {
+        CompletionKind::Nicks => {
+            let own_nick = state.nickname.as_deref().unwrap_or_default();
+
+            let mut speakers: Vec<(&String, _)> = channel.last_spoke.iter().collect();
+            speakers.sort_by(|(_, a), (_, b)| b.cmp(a));
+            let mut nicks: Vec<&str> = speakers.iter().map(|(nick, _)| nick.as_str()).collect();
+
+            let mut users: Vec<&str> = channel
+                .users
+                .iter()
+                .map(|user| strip_prefix(user))
+                .collect();
+            users.sort_by_key(|user| user.to_lowercase());
+            for user in users {
+                if !nicks.contains(&user) {
+                    nicks.push(user);
+                }
+            }
+
+            nicks
+                .into_iter()
+                .filter(|nick| !nick.eq_ignore_ascii_case(own_nick))
+                .map(|nick| Completion {
+                    label: nick.to_string(),
+                    text: format!("{}: ", nick),
+                })
+                .collect()
+        }
+        CompletionKind::Channels => state
+            .channels
+            .iter()
+            .filter(|channel| channel.channel_type == IrcChannelType::Channel)
+            .map(|channel| Completion {
+                label: channel.name.clone(),
+                text: format!("{} ", channel.name),
+            })
+            .collect(),
+        CompletionKind::Commands => COMMANDS
+            .iter()
+            .map(|(command, usage)| Completion {
+                label: usage.to_string(),
+                text: format!("/{} ", command),
+            })
+            .collect(),
+        CompletionKind::History => history(state.history.lines(&channel.name)),
+        CompletionKind::RawHistory => history(&state.history.raw),
+    }
+}
@@ -1,117 +1,768 @@
-use std::io::Write;
+use std::fmt;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use anyhow::Error;
-use ctru::applets::swkbd::{ButtonConfig, CallbackResult, Features, Kind, SoftwareKeyboard};
 use serde::{Deserialize, Serialize};
 
-use crate::grairc::Grairc;
+use crate::app::{Prompt, PromptKind, Prompter};
+use crate::irc::queue::{DEFAULT_BURST, DEFAULT_INTERVAL};
+use crate::irc::server::{DEFAULT_PING_INTERVAL, DEFAULT_PING_TIMEOUT};
+use crate::theme::Theme;
 
+/// where the config lived before profiles, see `profile::Profile::migrate_legacy`
 pub const CONFIG_FILE: &str = "/3ds/grairc/config.toml";
 
-#[derive(Serialize, Deserialize, Debug)]
+/// bump this and add a step to `migrate` whenever old configs need rewriting to load
+pub const CONFIG_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub struct Config {
+    /// which version of this struct the file was written for, 0 is anything from before versions
+    /// were added
+    #[serde(default)]
+    pub config_version: u32,
+
     /// nickname to use in IRC
     /// if taken a number will be appended to it
     pub nickname: String,
 
     /// irc server info
     pub server_hostname: String,
+    #[serde(default = "default_port")]
     pub server_port: u16,
 
-    /// list of autojoin channels
-    pub autojoin_channels: Vec<String>,
+    /// the channels to join, with their settings
+    #[serde(default)]
+    pub channels: Vec<ChannelConfig>,
+    /// channels joined and parted from the ui are added to and removed from `channels`
+    #[serde(default = "default_remember_channels")]
+    pub remember_channels: bool,
+    /// the smart filter for channels that arent in `channels`, like the ones joined from the ui
+    /// when theyre not remembered, on by default like it is for new channels
+    #[serde(default = "default_smart_filter")]
+    pub smart_filter: bool,
+
+    /// name of the theme to use, see `theme::THEMES_DIR` for custom ones
+    #[serde(default = "default_theme")]
+    pub theme: String,
+
+    /// flood control, how many lines can be sent back to back
+    #[serde(default = "default_send_burst")]
+    pub send_burst: u32,
+    /// and how many milliseconds until another one can go after that
+    #[serde(default = "default_send_interval_ms")]
+    pub send_interval_ms: u64,
+
+    /// how often to check the lag, in seconds
+    #[serde(default = "default_ping_interval_secs")]
+    pub ping_interval_secs: u64,
+    /// seconds without anything from the server before reconnecting
+    #[serde(default = "default_ping_timeout_secs")]
+    pub ping_timeout_secs: u64,
+}
+
+fn default_port() -> u16 {
+    6667
+}
+
+fn default_remember_channels() -> bool {
+    true
+}
+
+fn default_send_burst() -> u32 {
+    DEFAULT_BURST
+}
+
+fn default_send_interval_ms() -> u64 {
+    DEFAULT_INTERVAL.as_millis() as u64
+}
+
+fn default_ping_interval_secs() -> u64 {
+    DEFAULT_PING_INTERVAL.as_secs()
+}
+
+fn default_ping_timeout_secs() -> u64 {
+    DEFAULT_PING_TIMEOUT.as_secs()
+}
+
+/// how many characters wide the nick column is when a channel doesnt say
+pub const DEFAULT_NICK_WIDTH: usize = 12;
+
+/// a channel thats joined on connect and how its shown
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ChannelConfig {
+    pub name: String,
+    /// for channels with mode +k, sent along with the JOIN
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// leaves out joins, parts and quits
+    #[serde(default)]
+    pub hide_joins_parts: bool,
+    /// only shows joins, parts and quits from people who spoke in the last few minutes
+    #[serde(default = "default_smart_filter")]
+    pub smart_filter: bool,
+    /// mentions of our nick are shown like any other message
+    #[serde(default)]
+    pub mute_highlights: bool,
+    /// characters in the nick column, `DEFAULT_NICK_WIDTH` if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nick_width: Option<usize>,
+    /// whether messages are written to the profiles logs
+    #[serde(default = "default_log")]
+    pub log: bool,
+}
+
+fn default_log() -> bool {
+    true
+}
+
+fn default_smart_filter() -> bool {
+    true
+}
+
+impl ChannelConfig {
+    pub fn new(name: &str) -> Self {
+        ChannelConfig {
+            name: name.to_string(),
+            key: None,
+            hide_joins_parts: false,
+            smart_filter: default_smart_filter(),
+            mute_highlights: false,
+            nick_width: None,
+            log: default_log(),
+        }
+    }
+
+    pub fn nick_width(&self) -> usize {
+        self.nick_width.unwrap_or(DEFAULT_NICK_WIDTH)
+    }
+}
+
+fn default_theme() -> String {
+    Theme::dark().name
+}
+
+/// why the config couldnt be loaded
+#[derive(Debug)]
+pub enum ConfigError {
+    /// theres no config yet, this is the first start
+    Missing,
+    Unreadable(std::io::Error),
+    /// the toml is broken or doesnt match `Config`, `position` is the line and column (from 1)
+    Invalid {
+        message: String,
+        position: Option<(usize, usize)>,
+    },
+    /// written by a newer grairc, loading it could lose settings
+    TooNew(u32),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Missing => write!(f, "theres no config file"),
+            ConfigError::Unreadable(e) => write!(f, "couldnt read the config file: {}", e),
+            ConfigError::Invalid {
+                message,
+                position: Some((line, column)),
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+            ConfigError::Invalid {
+                message,
+                position: None,
+            } => write!(f, "{}", message),
+            ConfigError::TooNew(version) => write!(
+                f,
+                "the config is version {} but this grairc only knows up to {}, update grairc",
+                version, CONFIG_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl ConfigError {
+    fn invalid(error: toml::de::Error, contents: &str) -> Self {
+        // missing fields come with an empty span at the start of the file, which isnt much help
+        let span = error.span().filter(|span| *span != (0..0));
+        let position = span.map(|span| {
+            let before = &contents[..span.start.min(contents.len())];
+            let line = before.matches('\n').count() + 1;
+            let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+            (line, column)
+        });
+        ConfigError::Invalid {
+            message: error.message().trim().to_string(),
+            position,
+        }
+    }
+}
+
+/// upgrades a config written for `version` to `CONFIG_VERSION`, one version at a time
+fn migrate(table: &mut toml::Table, version: u32) {
+    for from in version..CONFIG_VERSION {
+        match from {
+            // before versions there was nothing that needs changing, the fields that got added
+            // since all have defaults
+            0 => {}
+            // the autojoin list turned into a table per channel
+            1 => {
+                let channels: toml::value::Array = match table.remove("autojoin_channels") {
+                    Some(toml::Value::Array(names)) => names
+                        .into_iter()
+                        .filter_map(|name| {
+                            let mut channel = toml::Table::new();
+                            channel.insert(
+                                "name".to_string(),
+                                toml::Value::String(name.as_str()?.to_string()),
+                            );
+                            Some(toml::Value::Table(channel))
+                        })
+                        .collect(),
+                    _ => vec![],
+                };
+                table.insert("channels".to_string(), toml::Value::Array(channels));
+            }
+            _ => unreachable!("no migration from config version {}", from),
+        }
+    }
+    table.insert(
+        "config_version".to_string(),
+        toml::Value::Integer(CONFIG_VERSION.into()),
+    );
+}
+
+/// the config fields that can be edited from the settings panel, the theme has its own list
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Setting {
+    Nickname,
+    ServerHostname,
+    ServerPort,
+    Channels,
+    SendBurst,
+    SendInterval,
+    PingInterval,
+    PingTimeout,
+    RememberChannels,
+    SmartFilter,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 10] = [
+        Setting::Nickname,
+        Setting::ServerHostname,
+        Setting::ServerPort,
+        Setting::Channels,
+        Setting::SendBurst,
+        Setting::SendInterval,
+        Setting::PingInterval,
+        Setting::PingTimeout,
+        Setting::RememberChannels,
+        Setting::SmartFilter,
+    ];
+
+    /// what the setup wizard asks for, everything else has a default thats fine to start with
+    pub const SETUP: [Setting; 4] = [
+        Setting::Nickname,
+        Setting::ServerHostname,
+        Setting::ServerPort,
+        Setting::Channels,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Setting::Nickname => "nickname",
+            Setting::ServerHostname => "server",
+            Setting::ServerPort => "port",
+            Setting::Channels => "channels",
+            Setting::SendBurst => "send burst",
+            Setting::SendInterval => "send interval",
+            Setting::PingInterval => "ping interval",
+            Setting::PingTimeout => "ping timeout",
+            Setting::RememberChannels => "remember channels",
+            Setting::SmartFilter => "default smart filter",
+        }
+    }
+
+    /// the current value, short enough to fit in the settings panel
+    pub fn value(&self, config: &Config) -> String {
+        match self {
+            Setting::Nickname => config.nickname.clone(),
+            Setting::ServerHostname => config.server_hostname.clone(),
+            Setting::ServerPort => config.server_port.to_string(),
+            Setting::Channels => config
+                .channels
+                .iter()
+                .map(|channel| channel.name.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+            Setting::SendBurst => format!("{} lines", config.send_burst),
+            Setting::SendInterval => format!("{} ms", config.send_interval_ms),
+            Setting::PingInterval => format!("{} s", config.ping_interval_secs),
+            Setting::PingTimeout => format!("{} s", config.ping_timeout_secs),
+            Setting::RememberChannels => yes_no(config.remember_channels),
+            Setting::SmartFilter => yes_no(config.smart_filter),
+        }
+    }
+
+    /// the keyboard prompt for editing this setting, starting from its current value
+    pub fn prompt(&self, config: &Config) -> Prompt {
+        match self {
+            Setting::Nickname => Prompt::new("enter irc nickname")
+                .initial_text(&config.nickname)
+                .validate(validate_nickname),
+            Setting::ServerHostname => {
+                Prompt::new("enter irc server hostname (no port, e.g. irc.example.com)")
+                    .initial_text(&config.server_hostname)
+                    .validate(validate_hostname)
+            }
+            Setting::ServerPort => Prompt::new("enter irc server port (e.g. 6667)")
+                .kind(PromptKind::Number)
+                .initial_text(&config.server_port.to_string())
+                .validate(validate_port),
+            Setting::Channels => Prompt::new(
+                "enter autojoin channels (one per line, e.g. #general, add the key after a space)",
+            )
+            .kind(PromptKind::Multiline)
+            .initial_text(
+                &config
+                    .channels
+                    .iter()
+                    .map(|channel| match &channel.key {
+                        Some(key) => format!("{} {}", channel.name, key),
+                        None => channel.name.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )
+            .validate(validate_channels),
+            Setting::SendBurst => Prompt::new("enter how many lines can be sent at once (1-20)")
+                .kind(PromptKind::Number)
+                .initial_text(&config.send_burst.to_string())
+                .validate(validate_send_burst),
+            Setting::SendInterval => Prompt::new(
+                "enter the milliseconds between lines once the burst is used up (0-10000)",
+            )
+            .kind(PromptKind::Number)
+            .initial_text(&config.send_interval_ms.to_string())
+            .validate(validate_send_interval),
+            Setting::PingInterval => {
+                Prompt::new("enter how many seconds between lag checks (5-600)")
+                    .kind(PromptKind::Number)
+                    .initial_text(&config.ping_interval_secs.to_string())
+                    .validate(validate_ping_interval)
+            }
+            Setting::PingTimeout => {
+                Prompt::new("enter how many seconds without a reply before reconnecting (30-3600)")
+                    .kind(PromptKind::Number)
+                    .initial_text(&config.ping_timeout_secs.to_string())
+                    .validate(validate_ping_timeout)
+            }
+            Setting::RememberChannels => {
+                Prompt::new("keep channels joined and left from the ui in the config (yes or no)")
+                    .initial_text(&yes_no(config.remember_channels))
+                    .validate(validate_yes_no)
+            }
+            Setting::SmartFilter => Prompt::new(
+                "only show joins and parts from people who spoke recently in channels without \
+                 their own settings (yes or no, default yes)",
+            )
+            .initial_text(&yes_no(config.smart_filter))
+            .validate(validate_yes_no),
+        }
+    }
+
+    /// stores already validated input from `prompt`
+    pub fn set(&self, config: &mut Config, input: &str) {
+        match self {
+            Setting::Nickname => config.nickname = input.trim().to_string(),
+            Setting::ServerHostname => config.server_hostname = input.trim().to_string(),
+            Setting::ServerPort => config.server_port = input.trim().parse().unwrap_or(6667),
+            Setting::Channels => {
+                // channels that stay on the list keep their settings
+                config.channels = parse_channels(input)
+                    .into_iter()
+                    .map(|(name, key)| {
+                        let mut channel = config
+                            .channel(name)
+                            .cloned()
+                            .unwrap_or_else(|| ChannelConfig::new(name));
+                        channel.name = name.to_string();
+                        channel.key = key.map(|key| key.to_string());
+                        channel
+                    })
+                    .collect();
+            }
+            Setting::SendBurst => {
+                config.send_burst = input
+                    .trim()
+                    .parse()
+                    .unwrap_or_else(|_| default_send_burst());
+            }
+            Setting::SendInterval => {
+                config.send_interval_ms = input
+                    .trim()
+                    .parse()
+                    .unwrap_or_else(|_| default_send_interval_ms());
+            }
+            Setting::PingInterval => {
+                config.ping_interval_secs = input
+                    .trim()
+                    .parse()
+                    .unwrap_or_else(|_| default_ping_interval_secs());
+            }
+            Setting::PingTimeout => {
+                config.ping_timeout_secs = input
+                    .trim()
+                    .parse()
+                    .unwrap_or_else(|_| default_ping_timeout_secs());
+            }
+            Setting::RememberChannels => {
+                config.remember_channels = input.trim().eq_ignore_ascii_case("yes");
+            }
+            Setting::SmartFilter => {
+                config.smart_filter = input.trim().eq_ignore_ascii_case("yes");
+            }
+        }
+    }
+
+    /// flips yes/no settings, returns false for the ones that need typing instead
+    pub fn toggle(&self, config: &mut Config) -> bool {
+        match self {
+            Setting::RememberChannels => config.remember_channels = !config.remember_channels,
+            Setting::SmartFilter => config.smart_filter = !config.smart_filter,
+            _ => return false,
+        }
+        true
+    }
+}
+
+fn yes_no(value: bool) -> String {
+    if value { "yes" } else { "no" }.to_string()
+}
+
+/// see https://modern.ircdocs.horse/#clients, servers are usually less strict than this
+pub fn validate_nickname(input: &str) -> Result<(), String> {
+    let nick = input.trim();
+    if nick.is_empty() {
+        return Err("the nickname cant be empty".to_string());
+    }
+    if nick.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '#' || c == ':') {
+        return Err("the nickname cant start with a number, -, # or :".to_string());
+    }
+    if nick.contains(|c: char| c.is_whitespace() || c == ',' || c == '*' || c == '!' || c == '@') {
+        return Err("the nickname cant contain spaces or , * ! @".to_string());
+    }
+    Ok(())
+}
+
+pub fn validate_hostname(input: &str) -> Result<(), String> {
+    let hostname = input.trim();
+    if hostname.is_empty() || hostname.contains(char::is_whitespace) {
+        return Err("enter a hostname like irc.example.com".to_string());
+    }
+    if hostname.contains(':') && !hostname.contains("::") {
+        return Err("leave the port out of the hostname".to_string());
+    }
+    Ok(())
+}
+
+pub fn validate_port(input: &str) -> Result<(), String> {
+    match input.trim().parse::<u16>() {
+        Ok(port) if port > 0 => Ok(()),
+        _ => Err("Please enter a valid port number".to_string()),
+    }
+}
+
+pub fn validate_channels(input: &str) -> Result<(), String> {
+    for line in input.lines().filter(|line| !line.trim().is_empty()) {
+        if line.split_whitespace().count() > 2 {
+            return Err(format!(
+                "{} has too many spaces, use one line per channel",
+                line.trim()
+            ));
+        }
+    }
+    for (channel, key) in parse_channels(input) {
+        if !channel.starts_with(['#', '&']) {
+            return Err(format!("{} should start with #", channel));
+        }
+        if channel.contains(',') || key.is_some_and(|key| key.contains(',')) {
+            return Err(format!("{} cant contain commas", channel));
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_send_burst(input: &str) -> Result<(), String> {
+    match input.trim().parse::<u32>() {
+        Ok(1..=20) => Ok(()),
+        _ => Err("enter a number of lines from 1 to 20".to_string()),
+    }
+}
+
+pub fn validate_send_interval(input: &str) -> Result<(), String> {
+    match input.trim().parse::<u64>() {
+        Ok(0..=10000) => Ok(()),
+        _ => Err("enter a number of milliseconds from 0 to 10000".to_string()),
+    }
+}
+
+pub fn validate_ping_interval(input: &str) -> Result<(), String> {
+    match input.trim().parse::<u64>() {
+        Ok(5..=600) => Ok(()),
+        _ => Err("enter a number of seconds from 5 to 600".to_string()),
+    }
+}
+
+pub fn validate_ping_timeout(input: &str) -> Result<(), String> {
+    match input.trim().parse::<u64>() {
+        Ok(30..=3600) => Ok(()),
+        _ => Err("enter a number of seconds from 30 to 3600".to_string()),
+    }
+}
+
+pub fn validate_yes_no(input: &str) -> Result<(), String> {
+    let input = input.trim();
+    if input.eq_ignore_ascii_case("yes") || input.eq_ignore_ascii_case("no") {
+        Ok(())
+    } else {
+        Err("enter yes or no".to_string())
+    }
+}
+
+/// one channel per line with an optional key after it, blank lines are skipped
+fn parse_channels(input: &str) -> Vec<(&str, Option<&str>)> {
+    input
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            Some((words.next()?, words.next()))
+        })
+        .collect()
+}
+
+/// the per channel settings, edited from the channel settings panel
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChannelOption {
+    HideJoinsParts,
+    SmartFilter,
+    MuteHighlights,
+    NickWidth,
+    Log,
+    Key,
+}
+
+impl ChannelOption {
+    pub const ALL: [ChannelOption; 6] = [
+        ChannelOption::HideJoinsParts,
+        ChannelOption::SmartFilter,
+        ChannelOption::MuteHighlights,
+        ChannelOption::NickWidth,
+        ChannelOption::Log,
+        ChannelOption::Key,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChannelOption::HideJoinsParts => "hide joins/parts",
+            ChannelOption::SmartFilter => "smart filter",
+            ChannelOption::MuteHighlights => "mute highlights",
+            ChannelOption::NickWidth => "nick width",
+            ChannelOption::Log => "log",
+            ChannelOption::Key => "key",
+        }
+    }
+
+    pub fn value(&self, channel: &ChannelConfig) -> String {
+        match self {
+            ChannelOption::HideJoinsParts => yes_no(channel.hide_joins_parts),
+            ChannelOption::SmartFilter => yes_no(channel.smart_filter),
+            ChannelOption::MuteHighlights => yes_no(channel.mute_highlights),
+            ChannelOption::NickWidth => channel.nick_width().to_string(),
+            ChannelOption::Log => yes_no(channel.log),
+            ChannelOption::Key => channel.key.clone().unwrap_or_else(|| "none".to_string()),
+        }
+    }
+
+    /// flips yes/no options, returns false for the ones that need typing instead
+    pub fn toggle(&self, channel: &mut ChannelConfig) -> bool {
+        match self {
+            ChannelOption::HideJoinsParts => channel.hide_joins_parts = !channel.hide_joins_parts,
+            ChannelOption::SmartFilter => channel.smart_filter = !channel.smart_filter,
+            ChannelOption::MuteHighlights => channel.mute_highlights = !channel.mute_highlights,
+            ChannelOption::Log => channel.log = !channel.log,
+            ChannelOption::NickWidth | ChannelOption::Key => return false,
+        }
+        true
+    }
+
+    /// the prompt for options that arent yes/no
+    pub fn prompt(&self, channel: &ChannelConfig) -> Option<Prompt> {
+        match self {
+            ChannelOption::NickWidth => Some(
+                Prompt::new("enter the nick column width (1-30)")
+                    .kind(PromptKind::Number)
+                    .initial_text(&channel.nick_width().to_string())
+                    .validate(validate_nick_width),
+            ),
+            ChannelOption::Key => Some(
+                Prompt::new("enter the channel key, leave empty for none")
+                    .initial_text(channel.key.as_deref().unwrap_or(""))
+                    .validate(validate_key),
+            ),
+            _ => None,
+        }
+    }
+
+    /// stores already validated input from `prompt`
+    pub fn set(&self, channel: &mut ChannelConfig, input: &str) {
+        let input = input.trim();
+        match self {
+            ChannelOption::NickWidth => {
+                channel.nick_width = input
+                    .parse()
+                    .ok()
+                    .filter(|width| *width != DEFAULT_NICK_WIDTH);
+            }
+            ChannelOption::Key => {
+                channel.key = Some(input.to_string()).filter(|key| !key.is_empty());
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn validate_nick_width(input: &str) -> Result<(), String> {
+    match input.trim().parse::<usize>() {
+        Ok(1..=30) => Ok(()),
+        _ => Err("enter a width from 1 to 30".to_string()),
+    }
+}
+
+pub fn validate_key(input: &str) -> Result<(), String> {
+    if input
+        .trim()
+        .contains(|c: char| c.is_whitespace() || c == ',')
+    {
+        return Err("the key cant contain spaces or commas".to_string());
+    }
+    Ok(())
 }
 
 impl Config {
     /// this config wont work for connecting, just a placeholder
     pub fn default() -> Self {
         Config {
+            config_version: CONFIG_VERSION,
             nickname: "null".to_string(),
             server_hostname: "null".to_string(),
             server_port: 0,
-            autojoin_channels: vec!["#null".to_string()],
+            channels: vec![ChannelConfig::new("#null")],
+            remember_channels: default_remember_channels(),
+            smart_filter: default_smart_filter(),
+            theme: default_theme(),
+            send_burst: default_send_burst(),
+            send_interval_ms: default_send_interval_ms(),
+            ping_interval_secs: default_ping_interval_secs(),
+            ping_timeout_secs: default_ping_timeout_secs(),
         }
     }
 
-    fn keyboard(
-        &self,
-        hint_text: &str,
-        mut keyboard: SoftwareKeyboard,
-        features: Option<Features>,
-        grairc: &mut Grairc,
-    ) -> Result<String, Error> {
-        keyboard.set_hint_text(Some(hint_text));
-        if let Some(f) = features {
-            keyboard.set_features(f);
+    pub fn send_interval(&self) -> Duration {
+        Duration::from_millis(self.send_interval_ms)
+    }
+
+    pub fn ping_interval(&self) -> Duration {
+        Duration::from_secs(self.ping_interval_secs)
+    }
+
+    pub fn ping_timeout(&self) -> Duration {
+        Duration::from_secs(self.ping_timeout_secs)
+    }
+
+    pub fn setup_wizard(&self, prompter: &mut dyn Prompter) -> Self {
+        let mut config = Config {
+            theme: self.theme.clone(),
+            ..Config::default()
+        };
+        for setting in Setting::SETUP {
+            let prompt = Prompt {
+                initial_text: String::new(),
+                cancellable: false,
+                ..setting.prompt(&config)
+            };
+            let input = prompter
+                .prompt(&prompt)
+                .unwrap_or_else(|| panic!("Failed to get {} input", setting.label()));
+            setting.set(&mut config, &input);
         }
-        let result = keyboard.launch(grairc.apt, grairc.gfx)?;
-        Ok(result.0)
+        config
     }
 
-    pub fn setup_wizard(&self, grairc: &mut Grairc) -> Self {
-        let nickname = self
-            .keyboard(
-                "enter irc nickname",
-                SoftwareKeyboard::new(Kind::Normal, ButtonConfig::Right),
-                None,
-                grairc,
-            )
-            .expect("Failed to get nickname input");
-
-        let server_hostname = self
-            .keyboard(
-                "enter irc server hostname (no port, e.g. irc.example.com)",
-                SoftwareKeyboard::new(Kind::Normal, ButtonConfig::Right),
-                None,
-                grairc,
-            )
-            .expect("Failed to get server hostname input");
-
-        let mut server_port_keyboard = SoftwareKeyboard::new(Kind::Numpad, ButtonConfig::Right);
-        server_port_keyboard.set_filter_callback(Some(Box::new(move |input| {
-            if input.parse::<u16>().is_ok() {
-                CallbackResult::Ok
-            } else {
-                CallbackResult::Retry("Please enter a valid port number".into())
-            }
-        })));
-        let server_port = self
-            .keyboard(
-                "enter irc server port (e.g. 6667)",
-                server_port_keyboard,
-                None,
-                grairc,
-            )
-            .expect("Failed to get server port input")
-            .parse::<u16>()
-            .expect("Failed to parse server port input");
-
-        let autojoin_channels = self
-            .keyboard(
-                "enter autojoin channels (one per line, e.g. #general)",
-                SoftwareKeyboard::new(Kind::Normal, ButtonConfig::Right),
-                Some(Features::MULTILINE),
-                grairc,
-            )
-            .expect("Failed to get autojoin channels input")
-            .lines()
-            .map(|s| s.to_string())
-            .collect::<Vec<String>>();
+    /// the settings for `name`, channel names arent case sensitive
+    pub fn channel(&self, name: &str) -> Option<&ChannelConfig> {
+        self.channels
+            .iter()
+            .find(|channel| channel.name.eq_ignore_ascii_case(name))
+    }
 
-        Config {
-            nickname,
-            server_hostname,
-            server_port,
-            autojoin_channels,
+    pub fn channel_mut(&mut self, name: &str) -> Option<&mut ChannelConfig> {
+        self.channels
+            .iter_mut()
+            .find(|channel| channel.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| match e.kind() {
+            ErrorKind::NotFound => ConfigError::Missing,
+            _ => ConfigError::Unreadable(e),
+        })?;
+        Config::parse(&contents)
+    }
+
+    /// parses a config file, migrating it first if its from an older version
+    pub fn parse(contents: &str) -> Result<Self, ConfigError> {
+        let mut table: toml::Table =
+            toml::from_str(contents).map_err(|e| ConfigError::invalid(e, contents))?;
+
+        let version = match table.get("config_version") {
+            None => 0,
+            Some(toml::Value::Integer(version)) => u32::try_from(*version).unwrap_or(u32::MAX),
+            Some(_) => {
+                return Err(ConfigError::Invalid {
+                    message: "config_version should be a number".to_string(),
+                    position: None,
+                });
+            }
+        };
+        if version > CONFIG_VERSION {
+            return Err(ConfigError::TooNew(version));
         }
+        if version == CONFIG_VERSION {
+            // straight from the text so errors point at the right line
+            return toml::from_str(contents).map_err(|e| ConfigError::invalid(e, contents));
+        }
+
+        migrate(&mut table, version);
+        toml::Value::Table(table)
+            .try_into()
+            .map_err(|e: toml::de::Error| ConfigError::Invalid {
+                message: e.message().trim().to_string(),
+                position: None,
+            })
     }
 
-    pub fn load() -> Option<Self> {
-        toml::from_str(&std::fs::read_to_string(CONFIG_FILE).ok()?).ok()
+    /// moves a config that failed to load out of the way so the next start runs the setup
+    /// wizard instead of failing again, returns where it went
+    pub fn backup(path: &Path) -> Result<PathBuf, std::io::Error> {
+        let backup = path.with_extension("toml.bak");
+        std::fs::rename(path, &backup)?;
+        Ok(backup)
     }
 
-    pub fn save(&self) -> Result<(), anyhow::Error> {
-        let path = std::path::Path::new(CONFIG_FILE);
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
@@ -0,0 +1,161 @@
+use std::cmp::Ordering;
+
+/// how the channel browser orders whats in it
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum DirectorySort {
+    /// busiest first
+    #[default]
+    Users,
+    Name,
+}
+
+impl DirectorySort {
+    pub const ALL: [DirectorySort; 2] = [DirectorySort::Users, DirectorySort::Name];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DirectorySort::Users => "by users",
+            DirectorySort::Name => "by name",
+        }
+    }
+}
+
+/// one RPL_LIST line
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DirectoryEntry {
+    pub name: String,
+    pub users: usize,
+    pub topic: String,
+}
+
+/// the channels on the server from the last LIST, for finding ones to join
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Directory {
+    /// in the order they came in, so an index keeps pointing at the same channel while the
+    /// rest of the LIST streams in
+    pub entries: Vec<DirectoryEntry>,
+    /// a LIST went out and its end hasnt come back yet
+    pub listing: bool,
+    pub sort: DirectorySort,
+    /// only channels with this in their name or topic are shown, ignoring case. kept in
+    /// lowercase so its not lowercased again for every entry
+    pub filter: String,
+    /// the name of the highlighted channel
+    pub selection: Option<String>,
+    /// indexes into `entries` of whats shown, filtered and sorted. while listing new entries are
+    /// only added to the end and its sorted once at the end, big networks list tens of thousands
+    /// of channels
+    visible: Vec<usize>,
+}
+
+/// compares names ignoring case without making lowercase copies of them
+fn name_order(a: &str, b: &str) -> Ordering {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .cmp(b.chars().flat_map(char::to_lowercase))
+}
+
+impl DirectorySort {
+    fn order(&self, a: &DirectoryEntry, b: &DirectoryEntry) -> Ordering {
+        match self {
+            DirectorySort::Users => b
+                .users
+                .cmp(&a.users)
+                .then_with(|| name_order(&a.name, &b.name)),
+            DirectorySort::Name => name_order(&a.name, &b.name),
+        }
+    }
+}
+
+impl Directory {
+    /// forgets the last LIST, the entries of the next one come in through `push`
+    pub fn start(&mut self) {
+        self.entries.clear();
+        self.visible.clear();
+        self.listing = true;
+        self.selection = None;
+    }
+
+    /// adds an entry below the others, theyre sorted when the LIST is finished
+    pub fn push(&mut self, entry: DirectoryEntry) {
+        if self.matches(&entry) {
+            self.visible.push(self.entries.len());
+        }
+        self.entries.push(entry);
+    }
+
+    pub fn finish(&mut self) {
+        self.listing = false;
+        self.set_sort(self.sort);
+    }
+
+    pub fn set_sort(&mut self, sort: DirectorySort) {
+        self.sort = sort;
+        let entries = &self.entries;
+        self.visible
+            .sort_by(|a, b| sort.order(&entries[*a], &entries[*b]));
+    }
+
+    pub fn set_filter(&mut self, filter: &str) {
+        self.filter = filter.trim().to_lowercase();
+        self.visible = (0..self.entries.len())
+            .filter(|index| self.matches(&self.entries[*index]))
+            .collect();
+        self.set_sort(self.sort);
+        // the highlight goes if its channel isnt shown anymore
+        if self.selected_position().is_none() {
+            self.selection = None;
+        }
+    }
+
+    fn matches(&self, entry: &DirectoryEntry) -> bool {
+        if self.filter.is_empty() {
+            return true;
+        }
+        entry.name.to_lowercase().contains(&self.filter)
+            || entry.topic.to_lowercase().contains(&self.filter)
+    }
+
+    /// whats shown in the browser with their index into `entries`, filtered and sorted
+    pub fn visible(&self) -> impl ExactSizeIterator<Item = (usize, &DirectoryEntry)> {
+        self.visible
+            .iter()
+            .map(|index| (*index, &self.entries[*index]))
+    }
+
+    /// where the highlighted channel is in `visible`
+    pub fn selected_position(&self) -> Option<usize> {
+        let selection = self.selection.as_deref()?;
+        self.visible()
+            .position(|(_, entry)| entry.name == selection)
+    }
+
+    /// highlights the channel at `index` in `entries`
+    pub fn select(&mut self, index: usize) {
+        self.selection = self.entries.get(index).map(|entry| entry.name.clone());
+    }
+
+    pub fn is_selected(&self, index: usize) -> bool {
+        self.selection.is_some()
+            && self.selection.as_deref() == self.entries.get(index).map(|entry| entry.name.as_str())
+    }
+
+    /// moves the highlight, it stops at either end
+    pub fn move_selection(&mut self, change: isize) {
+        let len = self.visible.len();
+        if len == 0 {
+            self.selection = None;
+            return;
+        }
+        let position = match self.selected_position() {
+            Some(position) => position.saturating_add_signed(change).min(len - 1),
+            None => 0,
+        };
+        self.selection = Some(self.entries[self.visible[position]].name.clone());
+    }
+
+    /// the name of the highlighted channel
+    pub fn selected(&self) -> Option<String> {
+        self.selection.clone()
+    }
+}
@@ -0,0 +1,307 @@
+//! gpu backend on top of citro2d, which ships with devkitpro's 3ds-dev (libcitro2d/libcitro3d)
+//!
+//! theres no rust wrapper for citro2d so the few functions we need are declared by hand below.
+//! the `static inline` helpers from the headers (C2D_SceneBegin, C2D_Color32, etc) are
+//! reimplemented in rust since they dont exist in the library
+
+use std::ffi::{CString, c_char, c_void};
+
+use anyhow::{Error, bail};
+use ctru::prelude::Gfx;
+use ctru::services::cfgu::Cfgu;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+
+use grairc::gfx::backend::{Backend, Canvas, Screen};
+use grairc::gfx::font::text_width;
+use grairc::gfx::layout::{LINE_HEIGHT, Node, Span};
+use grairc::gfx::renderers::widgets::{BOTTOM_SCREEN, TOP_SCREEN};
+
+#[allow(non_camel_case_types)]
+type C3D_RenderTarget = c_void;
+#[allow(non_camel_case_types)]
+type C2D_TextBuf = *mut c_void;
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct C2D_Text {
+    buf: C2D_TextBuf,
+    begin: usize,
+    end: usize,
+    width: f32,
+    lines: u32,
+    words: u32,
+    font: *mut c_void,
+}
+
+const C3D_DEFAULT_CMDBUF_SIZE: usize = 0x40000;
+const C3D_FRAME_SYNCDRAW: u8 = 1 << 0;
+const C2D_DEFAULT_MAX_OBJECTS: usize = 4096;
+const C2D_WITH_COLOR: u32 = 1 << 1;
+const GFX_TOP: u32 = 0;
+const GFX_BOTTOM: u32 = 1;
+const GFX_LEFT: u32 = 0;
+
+/// max glyphs per frame, a full top screen of text is about 2000
+const TEXT_BUF_GLYPHS: usize = 4096;
+/// the system font is way bigger than the u8g2 fonts, scale it down to roughly match line heights
+const TEXT_SCALE: f32 = 0.5;
+
+#[link(name = "citro2d")]
+#[link(name = "citro3d")]
+unsafe extern "C" {
+    fn C3D_Init(cmd_buf_size: usize) -> bool;
+    fn C3D_Fini();
+    fn C3D_FrameBegin(flags: u8) -> bool;
+    fn C3D_FrameEnd(flags: u8);
+    fn C3D_FrameDrawOn(target: *mut C3D_RenderTarget) -> bool;
+
+    fn C2D_Init(max_objects: usize) -> bool;
+    fn C2D_Fini();
+    fn C2D_Prepare();
+    fn C2D_Flush();
+    fn C2D_SceneSize(width: u32, height: u32, tilt: bool);
+    fn C2D_CreateScreenTarget(screen: u32, side: u32) -> *mut C3D_RenderTarget;
+    fn C2D_TargetClear(target: *mut C3D_RenderTarget, color: u32);
+    #[allow(clippy::too_many_arguments)]
+    fn C2D_DrawRectangle(
+        x: f32,
+        y: f32,
+        z: f32,
+        w: f32,
+        h: f32,
+        clr0: u32,
+        clr1: u32,
+        clr2: u32,
+        clr3: u32,
+    ) -> bool;
+
+    fn C2D_TextBufNew(max_glyphs: usize) -> C2D_TextBuf;
+    fn C2D_TextBufDelete(buf: C2D_TextBuf);
+    fn C2D_TextBufClear(buf: C2D_TextBuf);
+    fn C2D_TextParse(text: *mut C2D_Text, buf: C2D_TextBuf, str: *const c_char) -> *const c_char;
+    fn C2D_TextOptimize(text: *const C2D_Text);
+    fn C2D_DrawText(
+        text: *const C2D_Text,
+        flags: u32,
+        x: f32,
+        y: f32,
+        z: f32,
+        scale_x: f32,
+        scale_y: f32,
+        ...
+    );
+}
+
+fn color32(color: Rgb888) -> u32 {
+    color.r() as u32 | (color.g() as u32) << 8 | (color.b() as u32) << 16 | 0xff << 24
+}
+
+/// draws everything through the gpu, the cpu only builds the draw lists
+pub struct Citro2dBackend {
+    top: *mut C3D_RenderTarget,
+    bottom: *mut C3D_RenderTarget,
+    text_buf: C2D_TextBuf,
+    frame_started: bool,
+    /// the system font needs the system region, which needs cfg:u
+    _cfgu: Cfgu,
+}
+
+impl Citro2dBackend {
+    /// `_gfx` has to be initialized before citro3d is, taking it here makes sure it is
+    pub fn new(_gfx: &Gfx) -> Result<Self, Error> {
+        let cfgu = Cfgu::new()?;
+
+        unsafe {
+            if !C3D_Init(C3D_DEFAULT_CMDBUF_SIZE) {
+                bail!("Failed to initialize citro3d");
+            }
+            if !C2D_Init(C2D_DEFAULT_MAX_OBJECTS) {
+                C3D_Fini();
+                bail!("Failed to initialize citro2d");
+            }
+            C2D_Prepare();
+
+            let top = C2D_CreateScreenTarget(GFX_TOP, GFX_LEFT);
+            let bottom = C2D_CreateScreenTarget(GFX_BOTTOM, GFX_LEFT);
+            let text_buf = C2D_TextBufNew(TEXT_BUF_GLYPHS);
+            if top.is_null() || bottom.is_null() || text_buf.is_null() {
+                C2D_Fini();
+                C3D_Fini();
+                bail!("Failed to create citro2d render targets");
+            }
+
+            Ok(Citro2dBackend {
+                top,
+                bottom,
+                text_buf,
+                frame_started: false,
+                _cfgu: cfgu,
+            })
+        }
+    }
+
+    /// false when the gpu couldnt start a frame, theres nothing to draw into then
+    fn begin_frame(&mut self) -> bool {
+        if self.frame_started {
+            return true;
+        }
+
+        unsafe {
+            if !C3D_FrameBegin(C3D_FRAME_SYNCDRAW) {
+                return false;
+            }
+            C2D_TargetClear(self.top, color32(Rgb888::BLACK));
+            C2D_TargetClear(self.bottom, color32(Rgb888::BLACK));
+            C2D_TextBufClear(self.text_buf);
+        }
+        self.frame_started = true;
+        true
+    }
+}
+
+impl Drop for Citro2dBackend {
+    fn drop(&mut self) {
+        unsafe {
+            if self.frame_started {
+                C3D_FrameEnd(0);
+            }
+            C2D_TextBufDelete(self.text_buf);
+            C2D_Fini();
+            C3D_Fini();
+        }
+    }
+}
+
+impl Backend for Citro2dBackend {
+    fn draw(&mut self, screen: Screen, node: &Node) -> Result<(), Error> {
+        // skip the frame, the next one redraws everything anyway
+        if !self.begin_frame() {
+            return Ok(());
+        }
+
+        let (target, size) = match screen {
+            Screen::Top => (self.top, TOP_SCREEN),
+            Screen::Bottom => (self.bottom, BOTTOM_SCREEN),
+        };
+
+        // C2D_SceneBegin, inlined
+        unsafe {
+            C2D_Flush();
+            C3D_FrameDrawOn(target);
+            // the screens are mounted sideways, so citro2d wants them rotated
+            C2D_SceneSize(size.height, size.width, true);
+        }
+
+        node.draw(&mut Citro2dCanvas {
+            size,
+            text_buf: self.text_buf,
+            depth: 0.0,
+        })
+    }
+
+    fn present(&mut self) -> Result<(), Error> {
+        if self.frame_started {
+            unsafe { C3D_FrameEnd(0) };
+            self.frame_started = false;
+        }
+        Ok(())
+    }
+
+    fn retains_frames(&self) -> bool {
+        false
+    }
+}
+
+struct Citro2dCanvas {
+    size: Size,
+    text_buf: C2D_TextBuf,
+    /// everything is drawn in order, so nudge each primitive forward to keep that order
+    depth: f32,
+}
+
+impl Citro2dCanvas {
+    fn next_depth(&mut self) -> f32 {
+        self.depth = (self.depth + 0.0001).min(1.0);
+        self.depth
+    }
+}
+
+impl Canvas for Citro2dCanvas {
+    fn bounds(&self) -> Rectangle {
+        Rectangle::new(Point::zero(), self.size)
+    }
+
+    fn fill_rect(&mut self, area: Rectangle, color: Rgb888) -> Result<(), Error> {
+        let color = color32(color);
+        let depth = self.next_depth();
+        unsafe {
+            C2D_DrawRectangle(
+                area.top_left.x as f32,
+                area.top_left.y as f32,
+                depth,
+                area.size.width as f32,
+                area.size.height as f32,
+                color,
+                color,
+                color,
+                color,
+            );
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, span: &Span, position: Point, clip: &Rectangle) -> Result<Point, Error> {
+        let next = position + Point::new(text_width(&span.text), 0);
+
+        // theres no cheap per-pixel clipping on the gpu, so lines that dont fully fit vertically
+        // are skipped and characters past the right edge are cut off
+        if position.y < clip.top_left.y
+            || position.y + LINE_HEIGHT as i32 > clip.top_left.y + clip.size.height as i32
+        {
+            return Ok(next);
+        }
+        let right = clip.top_left.x + clip.size.width as i32;
+        let visible: String = span
+            .text
+            .char_indices()
+            .take_while(|(index, c)| {
+                let end = position.x + text_width(&span.text[..index + c.len_utf8()]);
+                end <= right
+            })
+            .map(|(_, c)| c)
+            .collect();
+
+        if let Some(background) = span.background {
+            let width = text_width(&span.text).min(right - position.x).max(0) as u32;
+            self.fill_rect(
+                Rectangle::new(position, Size::new(width, LINE_HEIGHT)),
+                background,
+            )?;
+        }
+        if visible.is_empty() {
+            return Ok(next);
+        }
+
+        let c_text = CString::new(visible.replace('\0', ""))?;
+        let depth = self.next_depth();
+        unsafe {
+            let mut text = std::mem::zeroed::<C2D_Text>();
+            C2D_TextParse(&mut text, self.text_buf, c_text.as_ptr());
+            C2D_TextOptimize(&text);
+            C2D_DrawText(
+                &text,
+                C2D_WITH_COLOR,
+                position.x as f32,
+                position.y as f32,
+                depth,
+                TEXT_SCALE,
+                TEXT_SCALE,
+                color32(span.color),
+            );
+        }
+
+        Ok(next)
+    }
+}
@@ -18,6 +18,10 @@ use ctru::{
         gspgpu::FramebufferFormat,
     },
 };
+use grairc::gfx::{
+    backend::{self, Backend, SoftwareCanvas},
+    layout::Node,
+};
 use std::cell::RefCell;
 
 pub mod eg;
@@ -42,6 +46,23 @@ impl<'a> DisplayTargets<'a> {
     }
 }
 
+impl Backend for DisplayTargets<'_> {
+    fn draw(&mut self, screen: backend::Screen, node: &Node) -> anyhow::Result<()> {
+        match screen {
+            backend::Screen::Top => node.draw(&mut SoftwareCanvas(&mut self.top)),
+            backend::Screen::Bottom => node.draw(&mut SoftwareCanvas(&mut self.bottom)),
+        }
+    }
+
+    fn present(&mut self) -> anyhow::Result<()> {
+        self.flush()
+    }
+
+    fn retains_frames(&self) -> bool {
+        true
+    }
+}
+
 /// N.B. `W` and `H` are going by a horizontal measurement such that W > H (i.e. not rotated 90deg)
 pub struct Display<'a, C: ConsoleScreen, const W: u32, const H: u32> {
     pub framebuffer: &'a mut [u8],
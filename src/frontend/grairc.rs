@@ -0,0 +1,183 @@
+use ctru::{
+    prelude::*,
+    services::ptm::user::{BatteryLevel, PTMUser},
+};
+
+use embedded_graphics::prelude::Point;
+use grairc::{
+    app::{App, Key},
+    gfx::{backend::Backend, renderers::Renderer},
+    irc::server::IrcServer,
+    state::BatteryState,
+};
+
+use crate::frontend::keyboard::Keyboard;
+
+/// which 3ds buttons map to which [`Key`], earlier entries win when several are pressed
+const KEYS: [(KeyPad, Key); 6] = [
+    (KeyPad::START, Key::Start),
+    (KeyPad::DPAD_DOWN, Key::Down),
+    (KeyPad::DPAD_UP, Key::Up),
+    (KeyPad::A, Key::A),
+    (KeyPad::X, Key::X),
+    (KeyPad::SELECT, Key::Select),
+];
+
+pub struct Grairc<'a> {
+    pub apt: &'a mut Apt,
+    pub hid: &'a mut Hid,
+    pub gfx: &'a Gfx,
+    pub soc: &'a mut Soc,
+    pub ptmu: &'a PTMUser,
+    pub backend: Box<dyn Backend + 'a>,
+    pub renderer: Renderer,
+    pub app: App,
+}
+
+impl<'a> Grairc<'a> {
+    pub fn new(
+        apt: &'a mut Apt,
+        hid: &'a mut Hid,
+        gfx: &'a Gfx,
+        soc: &'a mut Soc,
+        ptmu: &'a PTMUser,
+    ) -> Self {
+        Grairc {
+            apt,
+            hid,
+            gfx,
+            soc,
+            ptmu,
+            backend: Self::backend(gfx),
+            renderer: Renderer::default(),
+            app: App::default(),
+        }
+    }
+
+    #[cfg(not(feature = "citro2d"))]
+    fn backend(gfx: &'a Gfx) -> Box<dyn Backend + 'a> {
+        Box::new(
+            crate::frontend::eg::DisplayTargets::new(gfx)
+                .expect("Failed to create display targets"),
+        )
+    }
+
+    #[cfg(feature = "citro2d")]
+    fn backend(gfx: &'a Gfx) -> Box<dyn Backend + 'a> {
+        Box::new(
+            crate::frontend::citro2d::Citro2dBackend::new(gfx)
+                .expect("Failed to initialize citro2d"),
+        )
+    }
+
+    /// shows the profile picker until one is opened or the user quits
+    fn pick_profile(&mut self) {
+        let keyboard = &mut Keyboard {
+            apt: self.apt,
+            gfx: self.gfx,
+        };
+        while self.app.state.profile.is_none() && self.app.running && keyboard.apt.main_loop() {
+            self.renderer
+                .render(self.backend.as_mut(), &mut self.app.state)
+                .expect("Render failed");
+
+            self.hid.scan_input();
+            let keys_down = self.hid.keys_down();
+            if let Some((_, key)) = KEYS.iter().find(|(pad, _)| keys_down.contains(*pad)) {
+                self.app.picker_key_down(*key, keyboard);
+            }
+
+            let (touch_x, touch_y) = self.hid.touch_position();
+            let touch_point = Point::new(touch_x as i32, touch_y as i32);
+            if keys_down.contains(KeyPad::TOUCH) {
+                self.app.touch_down(touch_point);
+            } else if self.hid.keys_held().contains(KeyPad::TOUCH) {
+                self.app.touch_held(touch_point);
+            } else {
+                self.app.picker_touch_up(keyboard);
+            }
+
+            self.gfx.wait_for_vblank();
+        }
+    }
+
+    pub fn run(&mut self) {
+        self.soc.redirect_to_3dslink(true, true).ok();
+        self.apt.set_sleep_allowed(false);
+
+        println!("\x1b[30;47mgrairc v{}\x1b[0m", env!("CARGO_PKG_VERSION"));
+
+        self.app.load_profiles();
+        // a profile whose config doesnt load goes back to the picker with the error on screen
+        loop {
+            self.pick_profile();
+            if !self.app.running {
+                return;
+            }
+            let keyboard = &mut Keyboard {
+                apt: self.apt,
+                gfx: self.gfx,
+            };
+            if self.app.load_config(keyboard) {
+                break;
+            }
+        }
+
+        let mut keyboard = Keyboard {
+            apt: self.apt,
+            gfx: self.gfx,
+        };
+
+        println!("Initializing IRC server connection...");
+        let config = self.app.state.config.as_ref().unwrap();
+        let mut irc_server = IrcServer::new(&config.server_hostname, &config.server_port);
+        irc_server.set_flood_control(config.send_burst, config.send_interval());
+        irc_server.set_ping_timing(config.ping_interval(), config.ping_timeout());
+
+        println!("Identifying to IRC server...");
+        irc_server
+            .irc_ident(&config.nickname, &config.channels)
+            .expect("Failed to identify to IRC server");
+
+        println!("Entering main loop...");
+        while self.app.running && keyboard.apt.main_loop() {
+            self.renderer
+                .render(self.backend.as_mut(), &mut self.app.state)
+                .expect("Render failed");
+
+            irc_server.handler(&mut self.app.state);
+
+            self.hid.scan_input();
+            let keys_down = self.hid.keys_down();
+            if let Some((_, key)) = KEYS.iter().find(|(pad, _)| keys_down.contains(*pad)) {
+                self.app.key_down(*key, &mut irc_server, &mut keyboard);
+            }
+
+            let (touch_x, touch_y) = self.hid.touch_position();
+            let touch_point = Point::new(touch_x as i32, touch_y as i32);
+            if keys_down.contains(KeyPad::TOUCH) {
+                self.app.touch_down(touch_point);
+            } else if self.hid.keys_held().contains(KeyPad::TOUCH) {
+                self.app.touch_held(touch_point);
+            } else {
+                self.app.touch_up(&mut irc_server, &mut keyboard);
+            }
+
+            let battery_state = match (
+                self.ptmu.battery_level().unwrap(),
+                self.ptmu.is_charging().unwrap(),
+            ) {
+                (_, true) => BatteryState::Charging,
+                (BatteryLevel::Critical, false) => BatteryState::Critical,
+                (BatteryLevel::Drained, false) => BatteryState::Drained,
+                (BatteryLevel::VeryLow, false) => BatteryState::VeryLow,
+                (BatteryLevel::Low, false) => BatteryState::Low,
+                (BatteryLevel::Medium, false) => BatteryState::Medium,
+                (BatteryLevel::High, false) => BatteryState::High,
+            };
+            self.app.state.set_battery_state(battery_state);
+
+            self.gfx.wait_for_vblank();
+        }
+    }
+}
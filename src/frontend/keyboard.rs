@@ -0,0 +1,49 @@
+use ctru::{
+    applets::swkbd::{Button, ButtonConfig, CallbackResult, Features, Kind, SoftwareKeyboard},
+    prelude::{Apt, Gfx},
+};
+use grairc::app::{Prompt, PromptKind, Prompter};
+
+/// answers prompts with the system software keyboard
+pub struct Keyboard<'a> {
+    pub apt: &'a Apt,
+    pub gfx: &'a Gfx,
+}
+
+impl Prompter for Keyboard<'_> {
+    fn prompt(&mut self, prompt: &Prompt) -> Option<String> {
+        let kind = match prompt.kind {
+            PromptKind::Number => Kind::Numpad,
+            PromptKind::Text | PromptKind::Multiline => Kind::Normal,
+        };
+        let buttons = if prompt.cancellable {
+            ButtonConfig::LeftRight
+        } else {
+            ButtonConfig::Right
+        };
+
+        let mut keyboard = SoftwareKeyboard::new(kind, buttons);
+        keyboard.set_hint_text(Some(&prompt.hint));
+        if !prompt.initial_text.is_empty() {
+            keyboard.set_initial_text(Some(prompt.initial_text.clone().into()));
+        }
+        match prompt.kind {
+            PromptKind::Text => keyboard.set_features(Features::PREDICTIVE_INPUT),
+            PromptKind::Multiline => keyboard.set_features(Features::MULTILINE),
+            PromptKind::Number => {}
+        }
+        if let Some(validate) = prompt.validate {
+            keyboard.set_filter_callback(Some(Box::new(move |input| match validate(input) {
+                Ok(()) => CallbackResult::Ok,
+                Err(e) => CallbackResult::Retry(e.into()),
+            })));
+        }
+
+        match keyboard.launch(self.apt, self.gfx) {
+            Ok((text, Button::Right)) => Some(text),
+            Ok((_, Button::Left)) => None,
+            Ok((_, Button::Middle)) => None, // impossible to press
+            Err(e) => panic!("Software keyboard failed: {e}"),
+        }
+    }
+}
@@ -0,0 +1,7 @@
+//! the 3ds side of grairc, everything in here talks to ctru
+
+#[cfg(feature = "citro2d")]
+pub mod citro2d;
+pub mod eg;
+pub mod grairc;
+pub mod keyboard;
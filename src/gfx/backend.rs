@@ -0,0 +1,143 @@
+use anyhow::Error;
+use embedded_graphics::image::Image;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+use tinytga::Tga;
+
+use crate::gfx::font;
+use crate::gfx::layout::{Node, Span};
+use crate::gfx::memory::MemoryTargets;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Screen {
+    Top,
+    Bottom,
+}
+
+/// the handful of primitives the layout tree needs to draw itself
+///
+/// everything is clipped to `clip` (or already clipped, for `fill_rect`)
+pub trait Canvas {
+    fn bounds(&self) -> Rectangle;
+
+    fn fill_rect(&mut self, area: Rectangle, color: Rgb888) -> Result<(), Error>;
+
+    /// draws a span with its top left corner at `position`, returns where the next span goes
+    fn text(&mut self, span: &Span, position: Point, clip: &Rectangle) -> Result<Point, Error>;
+
+    /// a one pixel border just inside of `area`
+    fn stroke_rect(
+        &mut self,
+        area: Rectangle,
+        color: Rgb888,
+        clip: &Rectangle,
+    ) -> Result<(), Error> {
+        let Some(bottom_right) = area.bottom_right() else {
+            return Ok(());
+        };
+        let edges = [
+            Rectangle::new(area.top_left, Size::new(area.size.width, 1)),
+            Rectangle::new(
+                Point::new(area.top_left.x, bottom_right.y),
+                Size::new(area.size.width, 1),
+            ),
+            Rectangle::new(area.top_left, Size::new(1, area.size.height)),
+            Rectangle::new(
+                Point::new(bottom_right.x, area.top_left.y),
+                Size::new(1, area.size.height),
+            ),
+        ];
+        for edge in edges {
+            let edge = edge.intersection(clip);
+            if !edge.is_zero_sized() {
+                self.fill_rect(edge, color)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// pixel by pixel, backends with real image support should override this
+    fn image(
+        &mut self,
+        image: &Tga<'static, Rgb888>,
+        position: Point,
+        clip: &Rectangle,
+    ) -> Result<(), Error> {
+        for Pixel(point, color) in image.pixels() {
+            let point = point + position;
+            if clip.contains(point) {
+                self.fill_rect(Rectangle::new(point, Size::new(1, 1)), color)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// a [`Canvas`] for anything embedded-graphics can draw on, i.e. the software renderer
+pub struct SoftwareCanvas<'a, D>(pub &'a mut D);
+
+impl<D> Canvas for SoftwareCanvas<'_, D>
+where
+    D: DrawTarget<Color = Rgb888>,
+    Error: From<D::Error>,
+{
+    fn bounds(&self) -> Rectangle {
+        self.0.bounding_box()
+    }
+
+    fn fill_rect(&mut self, area: Rectangle, color: Rgb888) -> Result<(), Error> {
+        area.into_styled(PrimitiveStyle::with_fill(color))
+            .draw(self.0)?;
+        Ok(())
+    }
+
+    fn text(&mut self, span: &Span, position: Point, clip: &Rectangle) -> Result<Point, Error> {
+        Ok(font::draw_text(
+            &span.text,
+            position,
+            span.color,
+            span.background,
+            &mut self.0.clipped(clip),
+        )?)
+    }
+
+    fn image(
+        &mut self,
+        image: &Tga<'static, Rgb888>,
+        position: Point,
+        clip: &Rectangle,
+    ) -> Result<(), Error> {
+        Image::new(image, position).draw(&mut self.0.clipped(clip))?;
+        Ok(())
+    }
+}
+
+/// something that can put laid out trees on the two screens
+pub trait Backend {
+    fn draw(&mut self, screen: Screen, node: &Node) -> Result<(), Error>;
+
+    /// shows everything drawn since the last call
+    fn present(&mut self) -> Result<(), Error>;
+
+    /// whether whats on screen survives into the next frame, if it doesnt everything has to be
+    /// drawn every frame
+    fn retains_frames(&self) -> bool;
+}
+
+impl Backend for MemoryTargets {
+    fn draw(&mut self, screen: Screen, node: &Node) -> Result<(), Error> {
+        match screen {
+            Screen::Top => node.draw(&mut SoftwareCanvas(&mut self.top)),
+            Screen::Bottom => node.draw(&mut SoftwareCanvas(&mut self.bottom)),
+        }
+    }
+
+    fn present(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn retains_frames(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,193 @@
+use std::borrow::Cow;
+
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+use once_cell::sync::Lazy;
+use u8g2_fonts::types::{FontColor, VerticalPosition};
+use u8g2_fonts::{FontRenderer, fonts};
+
+use crate::gfx::layout::LINE_HEIGHT;
+
+/// where the baseline sits, measured from the top of a line
+///
+/// the fallback fonts are a bit taller than the main one so this leaves them room above
+pub const BASELINE: i32 = 10;
+
+/// width of the box drawn for characters none of the fonts have
+const REPLACEMENT_WIDTH: i32 = 6;
+
+/// every character is drawn with the first of these that has a glyph for it
+static FONTS: Lazy<[FontRenderer; 5]> = Lazy::new(|| {
+    [
+        // ascii + latin-1, covers almost everything on irc
+        FontRenderer::new::<fonts::u8g2_font_6x10_tf>(),
+        // latin extended
+        FontRenderer::new::<fonts::u8g2_font_6x13_te>(),
+        FontRenderer::new::<fonts::u8g2_font_6x13_t_cyrillic>(),
+        FontRenderer::new::<fonts::u8g2_font_cu12_t_greek>(),
+        // chinese, also has japanese kana
+        FontRenderer::new::<fonts::u8g2_font_wqy12_t_gb2312>(),
+    ]
+});
+
+fn font_for(c: char) -> Option<&'static FontRenderer> {
+    // skip the lookups for the common case
+    if c.is_ascii_graphic() || c == ' ' {
+        return Some(&FONTS[0]);
+    }
+    FONTS.iter().find(|font| {
+        font.get_rendered_dimensions(c, Point::zero(), VerticalPosition::Baseline)
+            .is_ok()
+    })
+}
+
+/// how far the cursor moves after drawing `c`
+pub fn char_width(c: char) -> i32 {
+    match font_for(c) {
+        Some(font) => font
+            .get_rendered_dimensions(c, Point::zero(), VerticalPosition::Baseline)
+            .map(|dimensions| dimensions.advance.x)
+            .unwrap_or(REPLACEMENT_WIDTH),
+        None => REPLACEMENT_WIDTH,
+    }
+}
+
+pub fn text_width(text: &str) -> i32 {
+    text.chars().map(char_width).sum()
+}
+
+/// the start of a mirc color code, followed by up to two digits of foreground and optionally
+/// a comma and up to two digits of background
+const COLOR: char = '\x03';
+
+/// bold, reset, reverse, italics, strikethrough, underline and monospace
+const FORMATTING: [char; 7] = ['\x02', '\x0F', '\x16', '\x1D', '\x1E', '\x1F', '\x11'];
+
+/// drops mirc style formatting codes, theres no way to draw them so the fallback would turn
+/// them into replacement boxes
+pub fn strip_formatting(text: &str) -> Cow<'_, str> {
+    if !text.contains(|c| c == COLOR || FORMATTING.contains(&c)) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == COLOR {
+            // theres only a background after a foreground, and a comma without digits after it
+            // is just a comma
+            if !skip_digits(&mut chars) {
+                continue;
+            }
+            let mut background = chars.clone();
+            if background.next() == Some(',') && background.peek().is_some_and(char::is_ascii_digit)
+            {
+                skip_digits(&mut background);
+                chars = background;
+            }
+        } else if !FORMATTING.contains(&c) {
+            stripped.push(c);
+        }
+    }
+    Cow::Owned(stripped)
+}
+
+/// skips the one or two digits of a color number, false if there werent any
+fn skip_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> bool {
+    let mut skipped = false;
+    for _ in 0..2 {
+        if chars.next_if(char::is_ascii_digit).is_none() {
+            break;
+        }
+        skipped = true;
+    }
+    skipped
+}
+
+/// splits `text` into runs that are drawn with the same font, `None` for runs that no font has
+fn runs(text: &str) -> Vec<(Option<&'static FontRenderer>, &str)> {
+    let mut runs: Vec<(Option<&'static FontRenderer>, &str)> = Vec::new();
+    let mut start = 0;
+    let mut current = None;
+    for (index, c) in text.char_indices() {
+        let font = font_for(c);
+        if index > 0 && !same_font(font, current) {
+            runs.push((current, &text[start..index]));
+            start = index;
+        }
+        current = font;
+    }
+    if start < text.len() {
+        runs.push((current, &text[start..]));
+    }
+    runs
+}
+
+fn same_font(a: Option<&FontRenderer>, b: Option<&FontRenderer>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => std::ptr::eq(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// draws `text` with its top left corner at `position`, returns where the next text goes
+pub fn draw_text<D>(
+    text: &str,
+    position: Point,
+    color: Rgb888,
+    background: Option<Rgb888>,
+    target: &mut D,
+) -> Result<Point, D::Error>
+where
+    D: DrawTarget<Color = Rgb888>,
+{
+    let mut x = position.x;
+    for (font, run) in runs(text) {
+        let width = text_width(run);
+        if let Some(background) = background {
+            Rectangle::new(
+                Point::new(x, position.y),
+                Size::new(width as u32, LINE_HEIGHT),
+            )
+            .into_styled(PrimitiveStyle::with_fill(background))
+            .draw(target)?;
+        }
+
+        match font {
+            Some(font) => {
+                font.render(
+                    run,
+                    Point::new(x, position.y + BASELINE),
+                    VerticalPosition::Baseline,
+                    FontColor::Transparent(color),
+                    target,
+                )
+                .map_err(|e| match e {
+                    u8g2_fonts::Error::DisplayError(e) => e,
+                    // the glyphs were looked up when picking the font and theres no background
+                    _ => unreachable!("font lookup and rendering disagree"),
+                })?;
+            }
+            None => {
+                for index in 0..run.chars().count() as i32 {
+                    replacement_box(Point::new(x + index * REPLACEMENT_WIDTH, position.y))
+                        .into_styled(PrimitiveStyle::with_stroke(color, 1))
+                        .draw(target)?;
+                }
+            }
+        }
+        x += width;
+    }
+
+    Ok(Point::new(x, position.y))
+}
+
+/// the outline drawn in place of a missing glyph, for a character starting at `position`
+fn replacement_box(position: Point) -> Rectangle {
+    Rectangle::new(
+        position + Point::new(1, BASELINE - 8),
+        Size::new(REPLACEMENT_WIDTH as u32 - 2, 8),
+    )
+}
@@ -0,0 +1,542 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use anyhow::Error;
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::text::Alignment;
+use tinytga::Tga;
+
+use crate::gfx::backend::Canvas;
+use crate::gfx::font::{char_width, strip_formatting, text_width};
+use crate::gfx::renderers::widgets::{Action, Region};
+use crate::gfx::wrap;
+
+/// height of a line of text in a [`TextView`]
+pub const LINE_HEIGHT: u32 = 12;
+
+/// how much space a node takes up along its parents direction
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Length {
+    Fixed(u32),
+    /// shares whatever is left after the fixed nodes by weight
+    Flex(u32),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Span {
+    pub text: String,
+    pub color: Rgb888,
+    pub background: Option<Rgb888>,
+}
+
+impl Span {
+    /// any mirc formatting codes in `text` are dropped, see [`strip_formatting`]
+    pub fn new(text: impl Into<String>, color: Rgb888) -> Self {
+        let text = text.into();
+        let text = match strip_formatting(&text) {
+            Cow::Borrowed(_) => text,
+            Cow::Owned(stripped) => stripped,
+        };
+        Span {
+            text,
+            color,
+            background: None,
+        }
+    }
+
+    pub fn background(mut self, color: Rgb888) -> Self {
+        self.background = Some(color);
+        self
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VerticalAlignment {
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// lines of text, each made of differently colored spans
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TextView {
+    pub lines: Vec<Vec<Span>>,
+    pub alignment: Alignment,
+    pub vertical_alignment: VerticalAlignment,
+    /// when there are more lines than fit, show the last ones instead of the first ones
+    pub follow_tail: bool,
+    /// break lines that are too wide for the view, see [`TextView::wrap`]
+    pub wrap: bool,
+    /// how far each line is pushed right, filled in when wrapping
+    indents: Vec<i32>,
+}
+
+impl TextView {
+    pub fn new(lines: Vec<Vec<Span>>) -> Self {
+        TextView {
+            lines,
+            alignment: Alignment::Left,
+            vertical_alignment: VerticalAlignment::Top,
+            follow_tail: false,
+            wrap: false,
+            indents: vec![],
+        }
+    }
+
+    /// one line per line of `text`, all in one color
+    pub fn plain(text: &str, color: Rgb888) -> Self {
+        TextView::new(
+            text.lines()
+                .map(|line| vec![Span::new(line, color)])
+                .collect(),
+        )
+    }
+
+    /// lines that were wrapped already, each pushed right by its indent
+    pub fn wrapped(lines: Vec<Vec<Span>>, indents: Vec<i32>) -> Self {
+        TextView {
+            indents,
+            ..TextView::new(lines)
+        }
+    }
+
+    pub fn alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    pub fn vertical_alignment(mut self, vertical_alignment: VerticalAlignment) -> Self {
+        self.vertical_alignment = vertical_alignment;
+        self
+    }
+
+    pub fn follow_tail(mut self) -> Self {
+        self.follow_tail = true;
+        self
+    }
+
+    /// wraps lines to the width of the view once its laid out
+    ///
+    /// only the last span of a line gets wrapped, the continuation lines are indented to line
+    /// up with where it started so e.g. message text stays clear of the nick column
+    pub fn wrap(mut self) -> Self {
+        self.wrap = true;
+        self
+    }
+
+    fn wrap_lines(&mut self, width: i32, capacity: usize) {
+        let mut lines = Vec::new();
+        let mut indents = Vec::new();
+
+        // with follow_tail only the last few lines are ever visible so dont bother with the rest
+        let source: Box<dyn Iterator<Item = &Vec<Span>>> = if self.follow_tail {
+            Box::new(self.lines.iter().rev())
+        } else {
+            Box::new(self.lines.iter())
+        };
+        for line in source {
+            if self.follow_tail && lines.len() >= capacity {
+                break;
+            }
+
+            let Some((last, prefix)) = line.split_last() else {
+                lines.push(vec![]);
+                indents.push(0);
+                continue;
+            };
+            let indent: i32 = prefix.iter().map(|span| text_width(&span.text)).sum();
+
+            let mut wrapped = Vec::new();
+            let mut wrapped_indents = Vec::new();
+            for (index, range) in break_text(&last.text, indent, width)
+                .into_iter()
+                .enumerate()
+            {
+                let span = Span {
+                    text: last.text[range].to_string(),
+                    ..last.clone()
+                };
+                if index == 0 {
+                    let mut first = prefix.to_vec();
+                    first.push(span);
+                    wrapped.push(first);
+                    wrapped_indents.push(0);
+                } else {
+                    wrapped.push(vec![span]);
+                    wrapped_indents.push(indent);
+                }
+            }
+
+            if self.follow_tail {
+                lines.extend(wrapped.into_iter().rev());
+                indents.extend(wrapped_indents.into_iter().rev());
+            } else {
+                lines.extend(wrapped);
+                indents.extend(wrapped_indents);
+            }
+        }
+
+        if self.follow_tail {
+            lines.reverse();
+            indents.reverse();
+        }
+        self.lines = lines;
+        self.indents = indents;
+        // laying out again shouldnt wrap the already wrapped lines
+        self.wrap = false;
+    }
+
+    fn draw(
+        &self,
+        area: Rectangle,
+        canvas: &mut dyn Canvas,
+        clip: &Rectangle,
+    ) -> Result<(), Error> {
+        let capacity = (area.size.height / LINE_HEIGHT) as usize;
+        let first = if self.follow_tail && self.lines.len() > capacity {
+            self.lines.len() - capacity
+        } else {
+            0
+        };
+        let lines = &self.lines[first..];
+
+        let used_height = (lines.len() as u32 * LINE_HEIGHT) as i32;
+        let top = match self.vertical_alignment {
+            VerticalAlignment::Top => area.top_left.y,
+            VerticalAlignment::Middle => {
+                area.top_left.y + (area.size.height as i32 - used_height) / 2
+            }
+            VerticalAlignment::Bottom => area.top_left.y + area.size.height as i32 - used_height,
+        };
+
+        for (index, line) in lines.iter().enumerate() {
+            let indent = self.indents.get(index + first).copied().unwrap_or(0);
+            let width: i32 = indent + line.iter().map(|span| text_width(&span.text)).sum::<i32>();
+            let x = indent
+                + match self.alignment {
+                    Alignment::Left => area.top_left.x,
+                    Alignment::Center => area.top_left.x + (area.size.width as i32 - width) / 2,
+                    Alignment::Right => area.top_left.x + area.size.width as i32 - width,
+                };
+
+            let mut next = Point::new(x, top + (index as u32 * LINE_HEIGHT) as i32);
+            for span in line {
+                next = canvas.text(span, next, clip)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// where `text` breaks into lines when it starts `indent` pixels into a line `width` wide, as
+/// byte ranges into `text`
+///
+/// the lines after the first start at `indent` too, see [`TextView::wrap`]
+pub fn break_text(text: &str, indent: i32, width: i32) -> Vec<Range<usize>> {
+    let available = (width - indent).max(width / 4);
+    wrap::wrap(text, available, char_width)
+        .into_iter()
+        .map(|line| {
+            let start = line.as_ptr() as usize - text.as_ptr() as usize;
+            start..start + line.len()
+        })
+        .collect()
+}
+
+pub enum Content {
+    Empty,
+    /// children laid out left to right
+    Row(Vec<Node>),
+    /// children laid out top to bottom
+    Column(Vec<Node>),
+    /// a vertically scrolled view into a child thats allowed to be taller than the pane
+    Scroll {
+        offset: i32,
+        child: Box<Node>,
+    },
+    Text(TextView),
+    Image(&'static Tga<'static, Rgb888>),
+}
+
+/// a node in the layout tree
+///
+/// build a tree with the constructors and builder methods, call [`Node::layout`] once
+/// with the screen area, then draw it, hit test it or look up regions in it
+pub struct Node {
+    pub content: Content,
+    pub length: Length,
+    pub padding: u32,
+    pub background: Option<Rgb888>,
+    pub border: Option<Rgb888>,
+    pub action: Option<Action>,
+    pub region: Option<Region>,
+    /// where this node ended up, only valid after [`Node::layout`]
+    pub area: Rectangle,
+}
+
+impl Node {
+    fn new(content: Content) -> Self {
+        Node {
+            content,
+            length: Length::Flex(1),
+            padding: 0,
+            background: None,
+            border: None,
+            action: None,
+            region: None,
+            area: Rectangle::zero(),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Node::new(Content::Empty)
+    }
+
+    pub fn row(children: Vec<Node>) -> Self {
+        Node::new(Content::Row(children))
+    }
+
+    pub fn column(children: Vec<Node>) -> Self {
+        Node::new(Content::Column(children))
+    }
+
+    pub fn scroll(offset: i32, child: Node) -> Self {
+        Node::new(Content::Scroll {
+            offset,
+            child: Box::new(child),
+        })
+    }
+
+    pub fn text(view: TextView) -> Self {
+        Node::new(Content::Text(view))
+    }
+
+    pub fn image(image: &'static Tga<'static, Rgb888>) -> Self {
+        Node::new(Content::Image(image))
+    }
+
+    pub fn fixed(mut self, size: u32) -> Self {
+        self.length = Length::Fixed(size);
+        self
+    }
+
+    pub fn flex(mut self, weight: u32) -> Self {
+        self.length = Length::Flex(weight);
+        self
+    }
+
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn background(mut self, color: Rgb888) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    pub fn border(mut self, color: Rgb888) -> Self {
+        self.border = Some(color);
+        self
+    }
+
+    pub fn action(mut self, action: Action) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    pub fn region(mut self, region: Region) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    /// the area inside of the padding
+    pub fn inner_area(&self) -> Rectangle {
+        inset(self.area, self.padding)
+    }
+
+    /// how tall this node wants to be, used to size the child of a scroll pane
+    fn natural_height(&self) -> u32 {
+        let content = match &self.content {
+            Content::Column(children) => children
+                .iter()
+                .map(|child| match child.length {
+                    Length::Fixed(size) => size,
+                    Length::Flex(_) => child.natural_height(),
+                })
+                .sum(),
+            Content::Row(children) => children
+                .iter()
+                .map(|child| child.natural_height())
+                .max()
+                .unwrap_or(0),
+            Content::Scroll { child, .. } => child.natural_height(),
+            Content::Text(view) => view.lines.len() as u32 * LINE_HEIGHT,
+            Content::Image(image) => image.bounding_box().size.height,
+            Content::Empty => 0,
+        };
+        content + self.padding * 2
+    }
+
+    pub fn layout(&mut self, area: Rectangle) {
+        self.area = area;
+        let inner = self.inner_area();
+        match &mut self.content {
+            Content::Row(children) => split(children, inner, true),
+            Content::Column(children) => split(children, inner, false),
+            Content::Scroll { offset, child } => {
+                let height = child.natural_height().max(inner.size.height);
+                child.layout(Rectangle::new(
+                    inner.top_left - Point::new(0, *offset),
+                    Size::new(inner.size.width, height),
+                ));
+            }
+            Content::Text(view) if view.wrap => view.wrap_lines(
+                inner.size.width as i32,
+                (inner.size.height / LINE_HEIGHT) as usize,
+            ),
+            Content::Text(_) | Content::Image(_) | Content::Empty => {}
+        }
+    }
+
+    fn children(&self) -> &[Node] {
+        match &self.content {
+            Content::Row(children) | Content::Column(children) => children,
+            Content::Scroll { child, .. } => std::slice::from_ref(child.as_ref()),
+            Content::Text(_) | Content::Image(_) | Content::Empty => &[],
+        }
+    }
+
+    pub fn draw(&self, canvas: &mut dyn Canvas) -> Result<(), Error> {
+        let clip = canvas.bounds();
+        self.draw_clipped(canvas, &clip)
+    }
+
+    fn draw_clipped(&self, canvas: &mut dyn Canvas, clip: &Rectangle) -> Result<(), Error> {
+        let visible = self.area.intersection(clip);
+        if visible.is_zero_sized() {
+            return Ok(());
+        }
+
+        if let Some(background) = self.background {
+            canvas.fill_rect(visible, background)?;
+        }
+        if let Some(border) = self.border {
+            canvas.stroke_rect(self.area, border, &visible)?;
+        }
+
+        let inner = self.inner_area().intersection(&visible);
+        match &self.content {
+            Content::Text(view) => view.draw(self.inner_area(), canvas, &inner)?,
+            Content::Image(image) => canvas.image(image, self.inner_area().top_left, &inner)?,
+            _ => {
+                for child in self.children() {
+                    child.draw_clipped(canvas, &inner)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// finds the action of the deepest node under `point`, if any
+    pub fn hit(&self, point: Point) -> Option<Action> {
+        self.hit_clipped(point, &self.area)
+    }
+
+    fn hit_clipped(&self, point: Point, clip: &Rectangle) -> Option<Action> {
+        let visible = self.area.intersection(clip);
+        if !visible.contains(point) {
+            return None;
+        }
+
+        let inner = self.inner_area().intersection(&visible);
+        self.children()
+            .iter()
+            .find_map(|child| child.hit_clipped(point, &inner))
+            .or(self.action)
+    }
+
+    pub fn find(&self, region: Region) -> Option<&Node> {
+        if self.region == Some(region) {
+            return Some(self);
+        }
+        self.children().iter().find_map(|child| child.find(region))
+    }
+
+    /// how far a scroll pane can scroll before running out of content
+    pub fn max_scroll(&self) -> i32 {
+        match &self.content {
+            Content::Scroll { child, .. } => {
+                (child.area.size.height as i32 - self.inner_area().size.height as i32).max(0)
+            }
+            _ => 0,
+        }
+    }
+}
+
+fn inset(area: Rectangle, amount: u32) -> Rectangle {
+    Rectangle::new(
+        area.top_left + Point::new(amount as i32, amount as i32),
+        Size::new(
+            area.size.width.saturating_sub(amount * 2),
+            area.size.height.saturating_sub(amount * 2),
+        ),
+    )
+}
+
+fn split(children: &mut [Node], area: Rectangle, horizontal: bool) {
+    let total = if horizontal {
+        area.size.width
+    } else {
+        area.size.height
+    };
+    let fixed: u32 = children
+        .iter()
+        .map(|child| match child.length {
+            Length::Fixed(size) => size,
+            Length::Flex(_) => 0,
+        })
+        .sum();
+    let mut remaining = total.saturating_sub(fixed);
+    let mut remaining_weight: u32 = children
+        .iter()
+        .map(|child| match child.length {
+            Length::Fixed(_) => 0,
+            Length::Flex(weight) => weight,
+        })
+        .sum();
+
+    let mut cursor = 0;
+    for child in children {
+        let size = match child.length {
+            Length::Fixed(size) => size,
+            Length::Flex(weight) => {
+                // the last flex node soaks up any rounding leftovers
+                let size = match remaining_weight {
+                    0 => 0,
+                    _ => remaining * weight / remaining_weight,
+                };
+                remaining -= size;
+                remaining_weight -= weight;
+                size
+            }
+        };
+
+        child.layout(if horizontal {
+            Rectangle::new(
+                area.top_left + Point::new(cursor as i32, 0),
+                Size::new(size, area.size.height),
+            )
+        } else {
+            Rectangle::new(
+                area.top_left + Point::new(0, cursor as i32),
+                Size::new(area.size.width, size),
+            )
+        });
+        cursor += size;
+    }
+}
@@ -0,0 +1,70 @@
+use std::convert::Infallible;
+
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+
+/// a framebuffer that only lives in memory, same idea as the 3ds frontends `Display` but
+/// without a screen attached so it works anywhere (e.g. tests on linux)
+pub struct MemoryDisplay<const W: u32, const H: u32> {
+    /// row major, top left first
+    pub pixels: Vec<Rgb888>,
+}
+
+impl<const W: u32, const H: u32> Default for MemoryDisplay<W, H> {
+    fn default() -> Self {
+        MemoryDisplay {
+            pixels: vec![Rgb888::BLACK; (W * H) as usize],
+        }
+    }
+}
+
+impl<const W: u32, const H: u32> MemoryDisplay<W, H> {
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Rgb888> {
+        if x >= W || y >= H {
+            return None;
+        }
+        Some(self.pixels[(y * W + x) as usize])
+    }
+
+    /// packed rgb bytes, handy for writing out images
+    pub fn to_rgb_bytes(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|color| [color.r(), color.g(), color.b()])
+            .collect()
+    }
+}
+
+impl<const W: u32, const H: u32> DrawTarget for MemoryDisplay<W, H> {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(coord, color) in pixels.into_iter() {
+            if let Ok((x @ 0_u32.., y @ 0_u32..)) = coord.try_into() {
+                if x >= W || y >= H {
+                    continue;
+                }
+                self.pixels[(y * W + x) as usize] = color;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<const W: u32, const H: u32> OriginDimensions for MemoryDisplay<W, H> {
+    fn size(&self) -> Size {
+        Size::new(W, H)
+    }
+}
+
+/// in memory stand-ins for the 3ds frontends `DisplayTargets`
+#[derive(Default)]
+pub struct MemoryTargets {
+    pub top: MemoryDisplay<400, 240>,
+    pub bottom: MemoryDisplay<320, 240>,
+}
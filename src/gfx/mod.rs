@@ -1,2 +1,6 @@
-pub mod eg;
+pub mod backend;
+pub mod font;
+pub mod layout;
+pub mod memory;
 pub mod renderers;
+pub mod wrap;
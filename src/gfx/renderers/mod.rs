@@ -1,297 +1,1122 @@
+use std::time::{Duration, Instant};
+
 use anyhow::Error;
-use embedded_graphics::image::Image;
-use embedded_graphics::mono_font::MonoTextStyle;
-use embedded_graphics::mono_font::ascii::FONT_6X10;
 use embedded_graphics::pixelcolor::Rgb888;
 use embedded_graphics::prelude::*;
-use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
-use embedded_graphics::text::renderer::CharacterStyle;
-use embedded_graphics::text::{Alignment, Text};
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::text::Alignment;
+use once_cell::sync::Lazy;
 use tinytga::Tga;
 
-use crate::gfx::eg::DisplayTargets;
-use crate::irc::types::{IrcChannel, IrcMessage};
-use crate::state::State;
-
-pub fn wrap(text: &str, width: usize) -> String {
-    // TODO: better word wrapping
-    let mut wrapped = String::new();
-    let mut line_length = 0;
-    for c in text.chars() {
-        if line_length >= width {
-            wrapped.push('\n');
-            line_length = 0;
-        } else {
-            wrapped.push(c);
-            line_length += 1;
-        }
-    }
-    wrapped
-}
+use crate::completion::{self, CompletionKind};
+use crate::config::{ChannelConfig, ChannelOption, DEFAULT_NICK_WIDTH, Setting};
+use crate::directory::DirectorySort;
+use crate::gfx::backend::{Backend, Screen, SoftwareCanvas};
+use crate::gfx::font::{strip_formatting, text_width};
+use crate::gfx::layout::{LINE_HEIGHT, Node, Span, TextView, VerticalAlignment, break_text};
+use crate::gfx::renderers::widgets::{
+    Action, BAR_HEIGHT, BOTTOM_SCREEN, BUTTON_HEIGHT, CHANNEL_LIST_WIDTH, CHANNEL_ROW_HEIGHT,
+    Region, TOP_SCREEN, USER_LIST_WIDTH,
+};
+use crate::gfx::wrap::WrappedLines;
+use crate::irc::command::Moderation;
+use crate::irc::mode::{LIST_MODES, TOGGLE_MODES, list_label, list_needs, prefix_at_least};
+use crate::irc::types::{IrcChannelType, IrcMessage, display_user, strip_prefix};
+use crate::state::{BatteryState, Dirty, Panel, State};
+use crate::theme::Theme;
+
+pub mod widgets;
 
-pub fn render_info(targets: &mut DisplayTargets) -> Result<(), Error> {
+pub fn info_view(state: &State) -> Node {
     let text = format!(
         "grairc v{}
 START  > exit
 DPAD   > switch channels
-A      > send message
-X      > send raw irc command
+A      > pick a nick, then A again to type
+X      > raw irc command, X again to type
+SELECT > settings
+
+tap users to look someone up
+tap a channel to switch to it, + join for more
+drag the channel list to scroll
 ",
         env!("CARGO_PKG_VERSION")
     );
 
-    Text::with_alignment(
-        &text,
-        targets.bottom.bounding_box().top_left + Point::new(5, 10),
-        MonoTextStyle::new(&FONT_6X10, Rgb888::WHITE),
-        Alignment::Left,
-    )
-    .draw(&mut targets.bottom)?;
+    Node::text(TextView::plain(&text, state.theme.text))
+        .padding(5)
+        .background(state.theme.background)
+        .region(Region::Info)
+}
 
-    Ok(())
+/// a tappable row in the settings panel or the profile picker
+fn settings_row(label: &str, selected: bool, action: Action, theme: &Theme) -> Node {
+    list_row(label, selected, theme).action(action)
 }
 
-pub fn render_channels(targets: &mut DisplayTargets, state: &State) -> Result<(), Error> {
-    let mut next = Point::new(
-        (targets.bottom.bounding_box().size.width - 5)
-            .try_into()
-            .unwrap(),
-        10,
-    );
-    for channel in &state.channels {
-        let text = format!("{}\n", channel.name);
+/// a settings row that cant be tapped
+fn list_row(label: &str, selected: bool, theme: &Theme) -> Node {
+    let (text_color, background) = if selected {
+        (theme.selection_text, theme.selection_background)
+    } else {
+        (theme.text, theme.background)
+    };
+    Node::text(TextView::plain(label, text_color).vertical_alignment(VerticalAlignment::Middle))
+        .fixed(CHANNEL_ROW_HEIGHT)
+        .padding(3)
+        .background(background)
+}
 
-        let mut style = MonoTextStyle::new(&FONT_6X10, Rgb888::WHITE);
-        if channel.selected {
-            style.background_color = Some(Rgb888::WHITE);
-            style.text_color = Some(Rgb888::BLACK);
-        } else {
-            style.background_color = Some(Rgb888::BLACK);
-            style.text_color = Some(Rgb888::WHITE);
-        }
+pub fn settings_view(state: &State) -> Node {
+    let theme = &state.theme;
+    let binding = crate::config::Config::default();
+    let config = state.config.as_ref().unwrap_or(&binding);
+
+    let heading = |label: &str| {
+        Node::text(
+            TextView::plain(label, theme.event_text).vertical_alignment(VerticalAlignment::Middle),
+        )
+        .fixed(CHANNEL_ROW_HEIGHT)
+        .padding(3)
+    };
 
-        next =
-            Text::with_alignment(&text, next, style, Alignment::Right).draw(&mut targets.bottom)?;
+    let mut rows = vec![heading("connection")];
+    rows.extend(Setting::ALL.iter().map(|setting| {
+        settings_row(
+            &format!("{}: {}", setting.label(), setting.value(config)),
+            false,
+            Action::EditSetting(*setting),
+            theme,
+        )
+    }));
+    if state.current_channel_static().channel_type == IrcChannelType::Channel {
+        rows.push(settings_row(
+            &format!("{} settings", state.current_channel_static().name),
+            false,
+            Action::ChannelSettings,
+            theme,
+        ));
     }
+    rows.push(settings_row(
+        "browse channels",
+        false,
+        Action::Directory,
+        theme,
+    ));
+    rows.push(heading("theme"));
+    rows.extend(state.themes.iter().enumerate().map(|(index, choice)| {
+        settings_row(
+            &choice.name,
+            choice.name == theme.name,
+            Action::SelectTheme(index),
+            theme,
+        )
+    }));
+    rows.push(settings_row("close", false, Action::Settings, theme));
 
-    Ok(())
+    // the d-pad scrolls it, theres more than fits
+    Node::scroll(state.settings_scroll, Node::column(rows))
+        .padding(2)
+        .background(theme.background)
+        .region(Region::Info)
 }
 
-pub fn render_bar(targets: &mut DisplayTargets, state: &State) -> Result<(), Error> {
-    let bar_height = 16;
-    let bar_area = Rectangle::new(
-        Point::new(
-            0,
-            (targets.top.bounding_box().size.height - bar_height)
-                .try_into()
-                .unwrap(),
-        ),
-        Size::new(targets.top.bounding_box().size.width, bar_height),
+pub fn channel_list(state: &State) -> Node {
+    let mut rows: Vec<Node> = state
+        .channels
+        .iter()
+        .enumerate()
+        .map(|(index, channel)| {
+            let (text_color, background) = match (channel.selected, channel.joined) {
+                (true, _) => (state.theme.selection_text, state.theme.selection_background),
+                // kicked, greyed out until its rejoined or closed
+                (false, false) => (state.theme.event_text, state.theme.background),
+                (false, true) => (state.theme.text, state.theme.background),
+            };
+            Node::text(
+                TextView::new(vec![vec![Span::new(&channel.name, text_color)]])
+                    .alignment(Alignment::Right)
+                    .vertical_alignment(VerticalAlignment::Middle),
+            )
+            .fixed(CHANNEL_ROW_HEIGHT)
+            .padding(3)
+            .background(background)
+            .action(Action::SwitchChannel(index))
+        })
+        .collect();
+    rows.push(
+        Node::text(
+            TextView::plain("+ join", state.theme.event_text)
+                .alignment(Alignment::Right)
+                .vertical_alignment(VerticalAlignment::Middle),
+        )
+        .fixed(CHANNEL_ROW_HEIGHT)
+        .padding(3)
+        .action(Action::Join),
     );
-    bar_area
-        .into_styled(PrimitiveStyle::with_fill(Rgb888::WHITE))
-        .draw(&mut targets.top)?;
-
-    let battery_charging = include_bytes!("../../../assets/battery/charging.tga");
-    let battery_critical = include_bytes!("../../../assets/battery/critical.tga");
-    let battery_drained = include_bytes!("../../../assets/battery/drained.tga");
-    let battery_high = include_bytes!("../../../assets/battery/high.tga");
-    let battery_low = include_bytes!("../../../assets/battery/low.tga");
-    let battery_medium = include_bytes!("../../../assets/battery/medium.tga");
-    let battery_verylow = include_bytes!("../../../assets/battery/verylow.tga");
-    let battery_image: Tga<Rgb888> = Tga::from_slice(match state.battery_state {
-        crate::state::BatteryState::Charging => battery_charging,
-        crate::state::BatteryState::Critical => battery_critical,
-        crate::state::BatteryState::Drained => battery_drained,
-        crate::state::BatteryState::VeryLow => battery_verylow,
-        crate::state::BatteryState::Low => battery_low,
-        crate::state::BatteryState::Medium => battery_medium,
-        crate::state::BatteryState::High => battery_high,
-    })
-    .unwrap();
-    Image::new(
-        &battery_image,
-        targets.top.bounding_box().bottom_right().unwrap()
-            - Point::new(16, (bar_height - 1).try_into().unwrap()),
+
+    Node::scroll(state.channel_scroll, Node::column(rows))
+        .background(state.theme.background)
+        .region(Region::Channels)
+}
+
+fn buttons(buttons: &[(&str, Action)], theme: &Theme) -> Node {
+    let buttons = buttons
+        .iter()
+        .map(|(label, action)| {
+            Node::row(vec![
+                Node::text(
+                    TextView::plain(label, theme.button_text)
+                        .alignment(Alignment::Center)
+                        .vertical_alignment(VerticalAlignment::Middle),
+                )
+                .background(theme.button_background)
+                .border(theme.button_border)
+                .action(*action),
+            ])
+            .padding(2)
+        })
+        .collect();
+
+    Node::row(buttons)
+        .background(theme.background)
+        .region(Region::Buttons)
+}
+
+pub fn button_bar(state: &State) -> Node {
+    buttons(
+        &[
+            ("send", Action::Send),
+            ("raw", Action::Raw),
+            ("part", Action::Part),
+            ("users", Action::Users),
+            ("settings", Action::Settings),
+        ],
+        &state.theme,
     )
-    .draw(&mut targets.top)?;
+}
 
-    // this is actually local time because of some 3ds shit
-    let cur_time = time::OffsetDateTime::now_utc();
-    let time_text = format!(
-        "{:02}:{:02}:{:02}",
-        cur_time.hour(),
-        cur_time.minute(),
-        cur_time.second()
+pub fn profile_list(state: &State) -> Node {
+    let theme = &state.theme;
+    let mut rows = vec![
+        Node::text(
+            TextView::plain("profiles", theme.event_text)
+                .vertical_alignment(VerticalAlignment::Middle),
+        )
+        .fixed(CHANNEL_ROW_HEIGHT)
+        .padding(3),
+    ];
+    if state.profiles.is_empty() {
+        rows.push(
+            Node::text(
+                TextView::plain("no profiles yet, tap new to make one", theme.text)
+                    .vertical_alignment(VerticalAlignment::Middle),
+            )
+            .fixed(CHANNEL_ROW_HEIGHT)
+            .padding(3),
+        );
+    }
+    rows.extend(state.profiles.iter().enumerate().map(|(index, profile)| {
+        settings_row(
+            &profile.name,
+            index == state.profile_selection,
+            Action::SelectProfile(index),
+            theme,
+        )
+    }));
+
+    // keeps the highlighted profile on screen when theres more than fit
+    let visible_rows = (BOTTOM_SCREEN.height - BUTTON_HEIGHT) / CHANNEL_ROW_HEIGHT - 1;
+    let scroll = (state.profile_selection as i32 + 2 - visible_rows as i32).max(0)
+        * CHANNEL_ROW_HEIGHT as i32;
+
+    Node::scroll(scroll, Node::column(rows))
+        .padding(2)
+        .background(theme.background)
+        .region(Region::Info)
+}
+
+pub fn channel_settings_view(state: &State) -> Node {
+    let theme = &state.theme;
+    let channel = state.current_channel_static();
+    let defaults = ChannelConfig::new(&channel.name);
+    let channel_config = state.channel_config(&channel.name).unwrap_or(&defaults);
+
+    let mut rows = vec![
+        Node::text(
+            TextView::plain(&channel.name, theme.event_text)
+                .vertical_alignment(VerticalAlignment::Middle),
+        )
+        .fixed(CHANNEL_ROW_HEIGHT)
+        .padding(3),
+    ];
+    rows.extend(ChannelOption::ALL.iter().map(|option| {
+        settings_row(
+            &format!("{}: {}", option.label(), option.value(channel_config)),
+            false,
+            Action::ChannelOption(*option),
+            theme,
+        )
+    }));
+    if prefix_at_least(state.our_prefix(), '%') {
+        rows.push(settings_row("moderation", false, Action::Moderation, theme));
+    }
+    rows.push(settings_row(
+        "move up",
+        false,
+        Action::MoveChannel(-1),
+        theme,
+    ));
+    rows.push(settings_row(
+        "move down",
+        false,
+        Action::MoveChannel(1),
+        theme,
+    ));
+    rows.push(settings_row("back", false, Action::Settings, theme));
+
+    Node::column(rows)
+        .padding(2)
+        .background(theme.background)
+        .region(Region::Info)
+}
+
+/// the completion picker, tapping one opens the keyboard with it already typed
+pub fn compose_view(state: &State) -> Node {
+    let theme = &state.theme;
+    let tabs = if state.completion_kind == CompletionKind::RawHistory {
+        Node::text(
+            TextView::plain(CompletionKind::RawHistory.label(), theme.event_text)
+                .vertical_alignment(VerticalAlignment::Middle),
+        )
+        .padding(3)
+    } else {
+        Node::row(
+            CompletionKind::MESSAGE
+                .iter()
+                .map(|kind| {
+                    settings_row(
+                        kind.label(),
+                        *kind == state.completion_kind,
+                        Action::CompletionKind(*kind),
+                        theme,
+                    )
+                    .flex(1)
+                })
+                .collect(),
+        )
+    }
+    .fixed(CHANNEL_ROW_HEIGHT);
+
+    let completions = completion::completions(state, state.completion_kind);
+    let mut rows: Vec<Node> = completions
+        .iter()
+        .enumerate()
+        .map(|(index, completion)| {
+            settings_row(
+                &completion.label,
+                state.completion_selection == Some(index),
+                Action::Complete(index),
+                theme,
+            )
+        })
+        .collect();
+    if rows.is_empty() {
+        rows.push(
+            Node::text(
+                TextView::plain("nothing to pick here", theme.text)
+                    .vertical_alignment(VerticalAlignment::Middle),
+            )
+            .fixed(CHANNEL_ROW_HEIGHT)
+            .padding(3),
+        );
+    }
+
+    // same as the profile list, the highlight stays on screen
+    let visible_rows = (BOTTOM_SCREEN.height - BUTTON_HEIGHT) / CHANNEL_ROW_HEIGHT - 2;
+    let selection = state.completion_selection.unwrap_or(0) as i32;
+    let scroll = (selection + 2 - visible_rows as i32).max(0) * CHANNEL_ROW_HEIGHT as i32;
+
+    Node::column(vec![tabs, Node::scroll(scroll, Node::column(rows))])
+        .padding(2)
+        .background(theme.background)
+        .region(Region::Info)
+}
+
+/// the people in the channel, or what the server told us about the one picked
+pub fn user_view(state: &State) -> Node {
+    let theme = &state.theme;
+    let heading = |label: &str| {
+        Node::text(
+            TextView::plain(label, theme.event_text).vertical_alignment(VerticalAlignment::Middle),
+        )
+        .fixed(CHANNEL_ROW_HEIGHT)
+        .padding(3)
+    };
+
+    if let Some(nick) = &state.selected_user {
+        let lines = match state.get_user_info(nick) {
+            Some(info) => info.lines(),
+            None => vec![nick.clone(), "looking them up...".to_string()],
+        };
+        let lines = lines
+            .iter()
+            .map(|line| vec![Span::new(line, theme.text)])
+            .collect();
+        let mut rows = vec![Node::text(TextView::new(lines).wrap()).padding(3)];
+        // op tools, only for people in the channel on screen
+        let channel = state.current_channel_static();
+        if channel.has_user(nick) {
+            rows.extend(
+                Moderation::available(state.our_prefix(), channel.prefixes_of(nick))
+                    .into_iter()
+                    .map(|moderation| {
+                        settings_row(
+                            moderation.label(),
+                            false,
+                            Action::Moderate(moderation),
+                            theme,
+                        )
+                    }),
+            );
+        }
+        rows.push(settings_row("back", false, Action::Users, theme));
+        return Node::column(rows)
+            .padding(2)
+            .background(theme.background)
+            .region(Region::Info);
+    }
+
+    let channel = state.current_channel_static();
+    let mut rows = vec![heading(&format!(
+        "{} ({})",
+        channel.name,
+        channel.users.len()
+    ))];
+    if channel.users.is_empty() {
+        rows.push(heading("nobody here"));
+    }
+    rows.extend(
+        channel
+            .users
+            .iter()
+            .enumerate()
+            .skip(state.user_list_scroll)
+            .map(|(index, user)| {
+                settings_row(&display_user(user), false, Action::SelectUser(index), theme)
+            }),
     );
-    Text::with_alignment(
-        &time_text,
-        targets.top.bounding_box().bottom_right().unwrap() - Point::new(28, 5),
-        MonoTextStyle::new(&FONT_6X10, Rgb888::BLACK),
-        Alignment::Right,
+
+    Node::column(rows)
+        .padding(2)
+        .background(theme.background)
+        .region(Region::Info)
+}
+
+/// the channel browser, tapping a channel highlights it and tapping it again joins it
+pub fn directory_view(state: &State) -> Node {
+    let theme = &state.theme;
+    let directory = &state.directory;
+    let controls = |label: &str, action: Action| settings_row(label, false, action, theme);
+
+    let sorts = Node::row(
+        DirectorySort::ALL
+            .iter()
+            .map(|sort| {
+                settings_row(
+                    sort.label(),
+                    *sort == directory.sort,
+                    Action::DirectorySort(*sort),
+                    theme,
+                )
+                .flex(1)
+            })
+            .collect(),
     )
-    .draw(&mut targets.top)?;
+    .fixed(CHANNEL_ROW_HEIGHT);
+    let filter = if directory.filter.is_empty() {
+        "filter".to_string()
+    } else {
+        format!("filter: {}", directory.filter)
+    };
+    let filter = Node::row(vec![
+        controls(&filter, Action::DirectoryFilter).flex(1),
+        controls("join", Action::JoinListed).fixed(40),
+    ])
+    .fixed(CHANNEL_ROW_HEIGHT);
 
-    let binding = crate::config::Config::default();
-    let config = state.config.as_ref().unwrap_or(&binding);
-    Text::with_alignment(
-        &config.nickname,
-        Point::new(5, targets.top.bounding_box().size.height as i32 - 5),
-        MonoTextStyle::new(&FONT_6X10, Rgb888::BLACK),
-        Alignment::Left,
+    let shown = directory.visible().len();
+    let mut status = if shown == directory.entries.len() {
+        format!("{} channels", shown)
+    } else {
+        format!("{} of {} channels", shown, directory.entries.len())
+    };
+    if directory.listing {
+        status.push_str(", listing...");
+    }
+    let status = Node::text(
+        TextView::plain(&status, theme.event_text).vertical_alignment(VerticalAlignment::Middle),
     )
-    .draw(&mut targets.top)?;
+    .fixed(CHANNEL_ROW_HEIGHT)
+    .padding(3);
 
-    Ok(())
+    // only the rows on screen get laid out, big networks have tens of thousands of channels
+    let visible_rows = (BOTTOM_SCREEN.height - BUTTON_HEIGHT) / CHANNEL_ROW_HEIGHT - 4;
+    let selection = directory.selected_position().unwrap_or(0);
+    let first = (selection + 2).saturating_sub(visible_rows as usize);
+    let mut rows = vec![];
+    for (index, entry) in directory.visible().skip(first).take(visible_rows as usize) {
+        let selected = directory.is_selected(index);
+        rows.push(settings_row(
+            &format!("{} ({})", entry.name, entry.users),
+            selected,
+            Action::SelectListed(index),
+            theme,
+        ));
+        if selected && !entry.topic.is_empty() {
+            rows.push(
+                Node::text(
+                    TextView::plain(&entry.topic, theme.event_text)
+                        .vertical_alignment(VerticalAlignment::Middle),
+                )
+                .fixed(CHANNEL_ROW_HEIGHT)
+                .padding(3),
+            );
+        }
+    }
+
+    Node::column(vec![sorts, filter, status, Node::column(rows)])
+        .padding(2)
+        .background(theme.background)
+        .region(Region::Info)
 }
 
-pub fn render_user_list(targets: &mut DisplayTargets, state: &State) -> Result<(), Error> {
-    let user_list_area = Rectangle::new(
-        Point::new(
-            (targets.top.bounding_box().size.width - 80)
-                .try_into()
-                .unwrap(),
-            0,
-        ),
-        Size::new(80, targets.top.bounding_box().size.height - 16),
-    );
-    user_list_area
-        .into_styled(PrimitiveStyle::with_fill(Rgb888::new(24, 24, 24)))
-        .draw(&mut targets.top)?;
-
-    let current_channel = state.current_channel_static();
-    let mut next = Point::new(
-        (targets.top.bounding_box().size.width - 75)
-            .try_into()
-            .unwrap(),
-        10,
-    );
-    for user in &current_channel.users {
-        let text = format!("{}\n", user);
-        next = Text::with_alignment(
-            &text,
-            next,
-            MonoTextStyle::new(&FONT_6X10, Rgb888::WHITE),
-            Alignment::Left,
+/// op tools for the channel on screen, the modes come in after its opened
+pub fn moderation_view(state: &State) -> Node {
+    let theme = &state.theme;
+    let channel = state.current_channel_static();
+    let heading = |label: &str| {
+        Node::text(
+            TextView::plain(label, theme.event_text).vertical_alignment(VerticalAlignment::Middle),
         )
-        .draw(&mut targets.top)?;
+        .fixed(CHANNEL_ROW_HEIGHT)
+        .padding(3)
+    };
+    let op = prefix_at_least(state.our_prefix(), '@');
+
+    let modes = channel.mode_string();
+    let mut rows = vec![heading(&format!(
+        "{} {}",
+        channel.name,
+        if modes.is_empty() {
+            "(no modes)"
+        } else {
+            &modes
+        }
+    ))];
+    // anyone can change the topic without +t
+    if op || !channel.modes.contains_key(&'t') {
+        rows.push(settings_row("set topic", false, Action::SetTopic, theme));
     }
-    Ok(())
+    if op {
+        rows.extend(TOGGLE_MODES.iter().map(|(mode, label)| {
+            let value = if channel.modes.contains_key(mode) {
+                "yes"
+            } else {
+                "no"
+            };
+            settings_row(
+                &format!("{} (+{}): {}", label, mode, value),
+                false,
+                Action::ToggleMode(*mode),
+                theme,
+            )
+        }));
+    }
+    rows.extend(
+        LIST_MODES
+            .iter()
+            .map(|mode| settings_row(list_label(*mode), false, Action::ModeList(*mode), theme)),
+    );
+    rows.push(settings_row("back", false, Action::Settings, theme));
+
+    Node::column(rows)
+        .padding(2)
+        .background(theme.background)
+        .region(Region::Info)
+}
+
+/// the masks of one list mode, tapping one highlights it and tapping it again removes it
+///
+/// without the prefix for changing the list its only shown
+pub fn mode_list_view(state: &State) -> Node {
+    let theme = &state.theme;
+    let channel = state.current_channel_static();
+    let masks = state.mode_list_masks();
+    let editable = state.can_edit_mode_list();
+    let heading = match state.mask_selection {
+        Some(_) if editable => "tap again to remove it".to_string(),
+        _ => format!("{} ({})", list_label(state.mode_list), masks.len()),
+    };
+    let heading = Node::text(
+        TextView::plain(&heading, theme.event_text).vertical_alignment(VerticalAlignment::Middle),
+    )
+    .fixed(CHANNEL_ROW_HEIGHT)
+    .padding(3);
+
+    // same as the channel browser, only whats on screen is laid out
+    let visible_rows = (BOTTOM_SCREEN.height - BUTTON_HEIGHT) / CHANNEL_ROW_HEIGHT - 3;
+    let selection = state.mask_selection.unwrap_or(0);
+    let first = (selection + 2).saturating_sub(visible_rows as usize);
+    let mut rows: Vec<Node> = masks
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(visible_rows as usize)
+        .map(|(index, mask)| {
+            let row = list_row(mask, state.mask_selection == Some(index), theme);
+            if editable {
+                row.action(Action::SelectMask(index))
+            } else {
+                row
+            }
+        })
+        .collect();
+    if masks.is_empty() {
+        rows.push(
+            Node::text(
+                TextView::plain(&format!("nothing in {}", channel.name), theme.text)
+                    .vertical_alignment(VerticalAlignment::Middle),
+            )
+            .fixed(CHANNEL_ROW_HEIGHT)
+            .padding(3),
+        );
+    }
+
+    let mut column = vec![heading, Node::column(rows)];
+    if !editable {
+        column.push(
+            Node::text(
+                TextView::plain(
+                    &format!("read only, removing needs {}", list_needs(state.mode_list)),
+                    theme.event_text,
+                )
+                .vertical_alignment(VerticalAlignment::Middle),
+            )
+            .fixed(CHANNEL_ROW_HEIGHT)
+            .padding(3),
+        );
+    }
+    column.push(settings_row("back", false, Action::Settings, theme));
+
+    Node::column(column)
+        .padding(2)
+        .background(theme.background)
+        .region(Region::Info)
+}
+
+/// the bottom screen with `region` building each part of it
+fn bottom_screen_with(state: &State, mut region: impl FnMut(Region) -> Node) -> Node {
+    // the profile picker at startup, before theres a config or a connection
+    if state.panel == Panel::Profiles {
+        return Node::column(vec![
+            region(Region::Info),
+            region(Region::Buttons).fixed(BUTTON_HEIGHT),
+        ]);
+    }
+
+    Node::column(vec![
+        Node::row(vec![
+            region(Region::Info),
+            region(Region::Channels).fixed(CHANNEL_LIST_WIDTH),
+        ]),
+        region(Region::Buttons).fixed(BUTTON_HEIGHT),
+    ])
 }
 
-pub fn render_messages(targets: &mut DisplayTargets, state: &State) -> Result<(), Error> {
-    let message_area = Rectangle::new(
-        Point::new(0, 0),
-        Size::new(
-            (targets.top.bounding_box().size.width - 80)
-                .try_into()
-                .unwrap(),
-            (targets.top.bounding_box().size.height - 16)
-                .try_into()
-                .unwrap(),
+fn bottom_region(state: &State, region: Region) -> Node {
+    match (region, state.panel) {
+        (Region::Info, Panel::Info) => info_view(state),
+        (Region::Info, Panel::Profiles) => profile_list(state),
+        (Region::Info, Panel::Settings) => settings_view(state),
+        (Region::Info, Panel::Channel) => channel_settings_view(state),
+        (Region::Info, Panel::Compose) => compose_view(state),
+        (Region::Info, Panel::User) => user_view(state),
+        (Region::Info, Panel::Directory) => directory_view(state),
+        (Region::Info, Panel::Moderation) => moderation_view(state),
+        (Region::Info, Panel::ModeList) => mode_list_view(state),
+        (Region::Channels, _) => channel_list(state),
+        (Region::Buttons, Panel::Profiles) => buttons(
+            &[
+                ("new", Action::NewProfile),
+                ("copy", Action::DuplicateProfile),
+                ("delete", Action::DeleteProfile),
+                ("open", Action::OpenProfile),
+            ],
+            &state.theme,
         ),
-    );
-    message_area
-        .into_styled(PrimitiveStyle::with_fill(Rgb888::BLACK))
-        .draw(&mut targets.top)?;
-
-    let mut next = Point::new(5, 10);
-    let mut messages = &state.current_channel_static().messages;
-
-    // you know what at this point i just want to finish this fucking project
-    // if it works it works
-    let mut wrapped_messages: Vec<IrcMessage> = Vec::new();
-    for message in messages.iter() {
-        let text = if let Some(nick) = &message.nick {
-            format!("{:12}: {}", nick, message.content)
+        (Region::Buttons, _) => button_bar(state),
+        (Region::Messages | Region::UserList | Region::Bar, _) => Node::empty(),
+    }
+}
+
+pub fn bottom_screen(state: &State) -> Node {
+    bottom_screen_with(state, |region| bottom_region(state, region))
+}
+
+pub fn layout_bottom_screen(state: &State) -> Node {
+    let mut tree = bottom_screen(state);
+    tree.layout(Rectangle::new(Point::zero(), BOTTOM_SCREEN));
+    tree
+}
+
+/// lays out only `region` of the bottom screen, the rest is left empty
+pub fn layout_bottom_region(state: &State, region: Region) -> Node {
+    let mut tree = bottom_screen_with(state, |part| {
+        if part == region {
+            bottom_region(state, part)
         } else {
-            format!("{}", message.content)
-        };
-        let wrapped_content = wrap(&text, 50);
-        let content_without_nick = if let Some(nick) = &message.nick {
-            let prefix = format!("{:12}: ", nick);
-            wrapped_content
-                .lines()
-                .map(|line| {
-                    if line.starts_with(&prefix) {
-                        line[prefix.len()..].to_string()
-                    } else {
-                        line.to_string()
-                    }
-                })
-                .collect::<Vec<String>>()
-                .join("\n")
+            Node::empty()
+        }
+    });
+    tree.layout(Rectangle::new(Point::zero(), BOTTOM_SCREEN));
+    tree
+}
+
+static BATTERY_CHARGING: Lazy<Tga<'static, Rgb888>> =
+    Lazy::new(|| Tga::from_slice(include_bytes!("../../../assets/battery/charging.tga")).unwrap());
+static BATTERY_CRITICAL: Lazy<Tga<'static, Rgb888>> =
+    Lazy::new(|| Tga::from_slice(include_bytes!("../../../assets/battery/critical.tga")).unwrap());
+static BATTERY_DRAINED: Lazy<Tga<'static, Rgb888>> =
+    Lazy::new(|| Tga::from_slice(include_bytes!("../../../assets/battery/drained.tga")).unwrap());
+static BATTERY_HIGH: Lazy<Tga<'static, Rgb888>> =
+    Lazy::new(|| Tga::from_slice(include_bytes!("../../../assets/battery/high.tga")).unwrap());
+static BATTERY_LOW: Lazy<Tga<'static, Rgb888>> =
+    Lazy::new(|| Tga::from_slice(include_bytes!("../../../assets/battery/low.tga")).unwrap());
+static BATTERY_MEDIUM: Lazy<Tga<'static, Rgb888>> =
+    Lazy::new(|| Tga::from_slice(include_bytes!("../../../assets/battery/medium.tga")).unwrap());
+static BATTERY_VERYLOW: Lazy<Tga<'static, Rgb888>> =
+    Lazy::new(|| Tga::from_slice(include_bytes!("../../../assets/battery/verylow.tga")).unwrap());
+
+/// lag from here on is shown in the highlight color
+const LAG_WARNING: Duration = Duration::from_secs(5);
+
+pub fn status_bar(state: &State) -> Node {
+    let battery_image: &'static Tga<Rgb888> = match state.battery_state {
+        BatteryState::Charging => &BATTERY_CHARGING,
+        BatteryState::Critical => &BATTERY_CRITICAL,
+        BatteryState::Drained => &BATTERY_DRAINED,
+        BatteryState::VeryLow => &BATTERY_VERYLOW,
+        BatteryState::Low => &BATTERY_LOW,
+        BatteryState::Medium => &BATTERY_MEDIUM,
+        BatteryState::High => &BATTERY_HIGH,
+    };
+
+    let time_text = format!(
+        "{:02}:{:02}:{:02}",
+        state.clock.hour(),
+        state.clock.minute(),
+        state.clock.second()
+    );
+    let frame_time_text = format!("{:.1}ms", state.frame_time.as_secs_f32() * 1000.0);
+
+    let binding = crate::config::Config::default();
+    let config = state.config.as_ref().unwrap_or(&binding);
+    let nickname = state.nickname.as_ref().unwrap_or(&config.nickname);
+
+    let mut nodes = vec![
+        Node::text(
+            TextView::plain(nickname, state.theme.bar_text)
+                .vertical_alignment(VerticalAlignment::Middle),
+        )
+        .padding(3),
+    ];
+    // only there while flood control is holding lines back
+    if state.queued_lines > 0 {
+        nodes.push(
+            Node::text(
+                TextView::plain(
+                    &format!("{} queued", state.queued_lines),
+                    state.theme.highlight,
+                )
+                .alignment(Alignment::Right)
+                .vertical_alignment(VerticalAlignment::Middle),
+            )
+            .fixed(66)
+            .padding(3),
+        );
+    }
+    if let Some(lag) = state.lag {
+        // a slow connection is worth noticing
+        let color = if lag >= LAG_WARNING {
+            state.theme.highlight
         } else {
-            wrapped_content
+            state.theme.bar_dim_text
         };
-        wrapped_messages.push(IrcMessage {
-            nick: message.nick.clone(),
-            content: content_without_nick.trim_end().to_string(),
-        });
-    }
-    messages = &wrapped_messages;
-
-    let max_lines = 22;
-    let mut line_count = 0;
-    let mut message_count = 0;
-    for message in messages.iter().rev() {
-        let content_line_count = message.content.matches('\n').count() + 1;
-        if line_count + content_line_count > max_lines {
-            break;
-        }
-        line_count += content_line_count;
-        message_count += 1;
+        nodes.push(
+            Node::text(
+                TextView::plain(&format!("lag {:.1}s", lag.as_secs_f32()), color)
+                    .alignment(Alignment::Right)
+                    .vertical_alignment(VerticalAlignment::Middle),
+            )
+            .fixed(66)
+            .padding(3),
+        );
     }
-    let start = messages.len().saturating_sub(message_count);
-    let binding = messages[start..].to_vec();
-    messages = &binding;
+    nodes.extend([
+        Node::text(
+            TextView::plain(&frame_time_text, state.theme.bar_dim_text)
+                .alignment(Alignment::Right)
+                .vertical_alignment(VerticalAlignment::Middle),
+        )
+        .fixed(50)
+        .padding(3),
+        Node::text(
+            TextView::plain(&time_text, state.theme.bar_text)
+                .alignment(Alignment::Right)
+                .vertical_alignment(VerticalAlignment::Middle),
+        )
+        .fixed(60)
+        .padding(3),
+        Node::image(battery_image).fixed(16).padding(1),
+    ]);
 
-    let messages: Vec<IrcMessage> = messages
+    Node::row(nodes)
+        .background(state.theme.bar_background)
+        .region(Region::Bar)
+}
+
+pub fn user_list(state: &State) -> Node {
+    let lines = state
+        .current_channel_static()
+        .users
         .iter()
-        .map(|msg| IrcMessage {
-            nick: msg.nick.clone(),
-            content: msg.content.trim_end().to_string(),
+        .map(|user| {
+            if state.selected_user.as_deref() == Some(strip_prefix(user)) {
+                vec![
+                    Span::new(display_user(user), state.theme.selection_text)
+                        .background(state.theme.selection_background),
+                ]
+            } else {
+                vec![Span::new(display_user(user), state.theme.user_list_text)]
+            }
         })
         .collect();
 
-    for message in messages {
-        let text = if let Some(nick) = &message.nick {
-            format!("{:12}: {}\n", nick, message.content)
-        } else {
-            format!("{}\n", message.content)
+    Node::text(TextView::new(lines))
+        .padding(5)
+        .background(state.theme.user_list_background)
+        .region(Region::UserList)
+}
+
+/// padding around the message view
+const MESSAGE_PADDING: u32 = 5;
+/// how wide the lines in the message view are, messages are wrapped to fit before its laid out
+const MESSAGE_WIDTH: i32 = (TOP_SCREEN.width - USER_LIST_WIDTH - MESSAGE_PADDING * 2) as i32;
+/// how many lines of messages fit on screen
+const MESSAGE_LINES: usize =
+    ((TOP_SCREEN.height - BAR_HEIGHT - MESSAGE_PADDING * 2) / LINE_HEIGHT) as usize;
+
+fn nick_column(nick: &str, nick_width: usize) -> String {
+    format!("{:1$}:", nick, nick_width)
+}
+
+/// where `message` breaks into lines in the message view, the ranges are into its content with
+/// the formatting stripped
+fn wrap_message(message: &IrcMessage, nick_width: usize) -> WrappedLines {
+    // the message text starts after the nick column and the space after it
+    let indent = match &message.nick {
+        Some(nick) => text_width(&nick_column(nick, nick_width)) + text_width(" "),
+        None => 0,
+    };
+    let content = strip_formatting(&message.content);
+    (break_text(&content, indent, MESSAGE_WIDTH), indent)
+}
+
+fn nick_width(state: &State) -> usize {
+    state
+        .channel_config(&state.current_channel_static().name)
+        .map_or(DEFAULT_NICK_WIDTH, |channel| channel.nick_width())
+}
+
+/// wraps the messages of the channel on screen that werent wrapped yet, see
+/// [`WrapCache`](crate::gfx::wrap::WrapCache)
+pub fn wrap_messages(state: &mut State) {
+    let nick_width = nick_width(state);
+    let channel = state.current_channel();
+    channel.wrapped.fill(
+        channel.messages.len(),
+        (MESSAGE_WIDTH, nick_width),
+        |index| wrap_message(&channel.messages[index], nick_width),
+    );
+}
+
+pub fn message_view(state: &State) -> Node {
+    let theme = &state.theme;
+    let binding = crate::config::Config::default();
+    let config = state.config.as_ref().unwrap_or(&binding);
+    let nickname = state
+        .nickname
+        .as_ref()
+        .unwrap_or(&config.nickname)
+        .to_lowercase();
+
+    let channel = state.current_channel_static();
+    let nick_width = nick_width(state);
+    let mute_highlights = state
+        .channel_config(&channel.name)
+        .is_some_and(|channel| channel.mute_highlights);
+
+    // only the last few messages fit, so thats all that gets looked at. theyre usually wrapped
+    // already, see `wrap_messages`
+    let mut lines = vec![];
+    let mut indents = vec![];
+    for (index, message) in channel.messages.iter().enumerate().rev() {
+        if lines.len() >= MESSAGE_LINES {
+            break;
+        }
+        let unwrapped;
+        let (ranges, indent) = match channel.wrapped.get(index, (MESSAGE_WIDTH, nick_width)) {
+            Some(wrapped) => wrapped,
+            None => {
+                unwrapped = wrap_message(message, nick_width);
+                &unwrapped
+            }
         };
 
-        let old_next = next;
+        let content = strip_formatting(&message.content);
+        let text_color = match &message.nick {
+            Some(_) if !mute_highlights && content.to_lowercase().contains(&nickname) => {
+                theme.highlight
+            }
+            Some(_) => theme.text,
+            None => theme.event_text,
+        };
+        for (line, range) in ranges.iter().enumerate().rev() {
+            let text = Span::new(&content[range.clone()], text_color);
+            match &message.nick {
+                Some(nick) if line == 0 => {
+                    lines.push(vec![
+                        Span::new(nick_column(nick, nick_width), theme.nick_text)
+                            .background(theme.nick_background),
+                        Span::new(" ", text_color),
+                        text,
+                    ]);
+                    indents.push(0);
+                }
+                _ => {
+                    lines.push(vec![text]);
+                    indents.push(if line == 0 { 0 } else { *indent });
+                }
+            }
+        }
+    }
+    lines.reverse();
+    indents.reverse();
 
-        next = Text::with_alignment(
-            &text,
-            next,
-            MonoTextStyle::new(&FONT_6X10, Rgb888::WHITE),
-            Alignment::Left,
-        )
-        .draw(&mut targets.top)?;
-
-        // shit solution: draw just the nick over it with different colors
-        if let Some(nick) = &message.nick {
-            let mut style = MonoTextStyle::new(&FONT_6X10, Rgb888::BLACK);
-            style.set_background_color(Some(Rgb888::WHITE));
-            Text::with_alignment(&format!("{:12}:", nick), old_next, style, Alignment::Left)
-                .draw(&mut targets.top)?;
+    Node::text(TextView::wrapped(lines, indents).follow_tail())
+        .padding(MESSAGE_PADDING)
+        .background(theme.background)
+        .region(Region::Messages)
+}
+
+/// the top screen with `region` building each part of it
+fn top_screen_with(mut region: impl FnMut(Region) -> Node) -> Node {
+    Node::column(vec![
+        Node::row(vec![
+            region(Region::Messages),
+            region(Region::UserList).fixed(USER_LIST_WIDTH),
+        ]),
+        region(Region::Bar).fixed(BAR_HEIGHT),
+    ])
+}
+
+fn top_region(state: &State, region: Region) -> Node {
+    match region {
+        Region::Messages => message_view(state),
+        Region::UserList => user_list(state),
+        Region::Bar => status_bar(state),
+        Region::Info | Region::Channels | Region::Buttons => Node::empty(),
+    }
+}
+
+pub fn top_screen(state: &State) -> Node {
+    top_screen_with(|region| top_region(state, region))
+}
+
+pub fn layout_top_screen(state: &State) -> Node {
+    let mut tree = top_screen(state);
+    tree.layout(Rectangle::new(Point::zero(), TOP_SCREEN));
+    tree
+}
+
+/// lays out only `region` of the top screen, the rest is left empty
+pub fn layout_top_region(state: &State, region: Region) -> Node {
+    let mut tree = top_screen_with(|part| {
+        if part == region {
+            top_region(state, part)
+        } else {
+            Node::empty()
         }
+    });
+    tree.layout(Rectangle::new(Point::zero(), TOP_SCREEN));
+    tree
+}
+
+fn render_region<D>(target: &mut D, tree: &Node, region: Region) -> Result<(), Error>
+where
+    D: DrawTarget<Color = Rgb888>,
+    Error: From<D::Error>,
+{
+    if let Some(node) = tree.find(region) {
+        node.draw(&mut SoftwareCanvas(target))?;
     }
     Ok(())
 }
 
-pub fn render_main_screen(targets: &mut DisplayTargets, state: &State) -> Result<(), Error> {
-    render_messages(targets, state)?;
-    render_bar(targets, state)?;
-    render_user_list(targets, state)?;
-    Ok(())
+pub fn render_info<D>(target: &mut D, state: &State) -> Result<(), Error>
+where
+    D: DrawTarget<Color = Rgb888>,
+    Error: From<D::Error>,
+{
+    render_region(target, &layout_bottom_screen(state), Region::Info)
 }
 
-pub fn render(targets: &mut DisplayTargets, state: &State) -> Result<(), Error> {
-    render_info(targets)?;
-    render_channels(targets, state)?;
-    render_main_screen(targets, state)?;
-    Ok(())
+pub fn render_channels<D>(target: &mut D, state: &State) -> Result<(), Error>
+where
+    D: DrawTarget<Color = Rgb888>,
+    Error: From<D::Error>,
+{
+    render_region(target, &layout_bottom_screen(state), Region::Channels)
+}
+
+pub fn render_buttons<D>(target: &mut D, state: &State) -> Result<(), Error>
+where
+    D: DrawTarget<Color = Rgb888>,
+    Error: From<D::Error>,
+{
+    render_region(target, &layout_bottom_screen(state), Region::Buttons)
+}
+
+pub fn render_bar<D>(target: &mut D, state: &State) -> Result<(), Error>
+where
+    D: DrawTarget<Color = Rgb888>,
+    Error: From<D::Error>,
+{
+    render_region(target, &layout_top_screen(state), Region::Bar)
+}
+
+pub fn render_user_list<D>(target: &mut D, state: &State) -> Result<(), Error>
+where
+    D: DrawTarget<Color = Rgb888>,
+    Error: From<D::Error>,
+{
+    render_region(target, &layout_top_screen(state), Region::UserList)
+}
+
+pub fn render_messages<D>(target: &mut D, state: &State) -> Result<(), Error>
+where
+    D: DrawTarget<Color = Rgb888>,
+    Error: From<D::Error>,
+{
+    render_region(target, &layout_top_screen(state), Region::Messages)
+}
+
+pub fn render_main_screen<D>(target: &mut D, state: &State) -> Result<(), Error>
+where
+    D: DrawTarget<Color = Rgb888>,
+    Error: From<D::Error>,
+{
+    layout_top_screen(state).draw(&mut SoftwareCanvas(target))
+}
+
+pub fn render_bottom_screen<D>(target: &mut D, state: &State) -> Result<(), Error>
+where
+    D: DrawTarget<Color = Rgb888>,
+    Error: From<D::Error>,
+{
+    layout_bottom_screen(state).draw(&mut SoftwareCanvas(target))
+}
+
+/// how many frames the frame time is averaged over
+const FRAME_TIME_WINDOW: u32 = 60;
+
+/// redraws only the parts of the screens that changed
+pub struct Renderer {
+    /// what got drawn last frame, the framebuffers are double buffered so the other buffer
+    /// still has whatever was there before that and needs it drawn too
+    previous: Dirty,
+    frame_time_total: Duration,
+    frame_count: u32,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Renderer {
+            previous: Dirty::ALL,
+            frame_time_total: Duration::ZERO,
+            frame_count: 0,
+        }
+    }
+}
+
+impl Renderer {
+    pub fn render(&mut self, backend: &mut dyn Backend, state: &mut State) -> Result<(), Error> {
+        let start = Instant::now();
+
+        // this is actually local time because of some 3ds shit
+        state.set_clock(time::OffsetDateTime::now_utc().time());
+
+        let dirty = std::mem::take(&mut state.dirty);
+        let to_draw = if backend.retains_frames() {
+            dirty.union(self.previous)
+        } else {
+            Dirty::ALL
+        };
+        self.previous = dirty;
+
+        // only whats dirty gets built, the bar alone changes every second and the messages
+        // are the expensive part
+        if to_draw.messages {
+            wrap_messages(state);
+        }
+        let top = [
+            (to_draw.messages, Region::Messages),
+            (to_draw.users, Region::UserList),
+            (to_draw.bar, Region::Bar),
+        ];
+        for (_, region) in top.into_iter().filter(|(dirty, _)| *dirty) {
+            if let Some(node) = layout_top_region(state, region).find(region) {
+                backend.draw(Screen::Top, node)?;
+            }
+        }
+
+        let bottom = [
+            (to_draw.info, Region::Info),
+            (to_draw.channels, Region::Channels),
+            (to_draw.buttons, Region::Buttons),
+        ];
+        for (_, region) in bottom.into_iter().filter(|(dirty, _)| *dirty) {
+            if let Some(node) = layout_bottom_region(state, region).find(region) {
+                backend.draw(Screen::Bottom, node)?;
+            }
+        }
+
+        self.frame_time_total += start.elapsed();
+        self.frame_count += 1;
+        if self.frame_count == FRAME_TIME_WINDOW {
+            state.frame_time = self.frame_time_total / FRAME_TIME_WINDOW;
+            self.frame_time_total = Duration::ZERO;
+            self.frame_count = 0;
+        }
+
+        backend.present()
+    }
+}
+
+/// draws everything, regardless of whats dirty
+pub fn render(backend: &mut dyn Backend, state: &State) -> Result<(), Error> {
+    backend.draw(Screen::Bottom, &layout_bottom_screen(state))?;
+    backend.draw(Screen::Top, &layout_top_screen(state))?;
+    backend.present()
 }
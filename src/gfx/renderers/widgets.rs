@@ -0,0 +1,142 @@
+use embedded_graphics::prelude::*;
+
+use crate::completion::CompletionKind;
+use crate::config::{ChannelOption, Setting};
+use crate::directory::DirectorySort;
+use crate::gfx::renderers::layout_bottom_screen;
+use crate::irc::command::Moderation;
+use crate::state::{Dirty, State};
+
+pub const TOP_SCREEN: Size = Size::new(400, 240);
+pub const BOTTOM_SCREEN: Size = Size::new(320, 240);
+
+pub const BAR_HEIGHT: u32 = 16;
+pub const USER_LIST_WIDTH: u32 = 80;
+pub const BUTTON_HEIGHT: u32 = 32;
+pub const CHANNEL_LIST_WIDTH: u32 = 140;
+pub const CHANNEL_ROW_HEIGHT: u32 = 16;
+
+/// how far (in pixels) a touch has to move before its a drag instead of a tap
+pub const DRAG_THRESHOLD: i32 = 6;
+
+/// something the user can do by tapping the bottom screen
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    SwitchChannel(usize),
+    /// opens the completion picker, or the keyboard if its already open
+    Send,
+    /// opens the keyboard starting with a completion from the picker
+    Complete(usize),
+    CompletionKind(CompletionKind),
+    Raw,
+    Part,
+    /// opens and closes the settings panel
+    Settings,
+    SelectTheme(usize),
+    EditSetting(Setting),
+    /// opens the settings for the channel on screen
+    ChannelSettings,
+    /// opens the keyboard for a channel to join
+    Join,
+    /// moves the channel on screen up or down the channel list
+    MoveChannel(isize),
+    /// opens the user panel, or goes back to its list
+    Users,
+    /// looks up someone from the user panel list
+    SelectUser(usize),
+    /// opens the channel browser and asks the server for its channels
+    Directory,
+    DirectorySort(DirectorySort),
+    /// opens the keyboard for what to look for in the channel browser
+    DirectoryFilter,
+    /// highlights a channel in the browser by its index in `Directory::entries`, tapping it
+    /// again joins it
+    SelectListed(usize),
+    /// joins the highlighted channel in the browser
+    JoinListed,
+    /// does something to whoever is open in the user panel
+    Moderate(Moderation),
+    /// opens the op tools for the channel on screen
+    Moderation,
+    /// sets or unsets one of `mode::TOGGLE_MODES`
+    ToggleMode(char),
+    /// opens the keyboard for a new topic
+    SetTopic,
+    /// fetches and shows a list mode, like the bans
+    ModeList(char),
+    /// highlights a mask in the mode list, tapping it again removes it
+    SelectMask(usize),
+    ChannelOption(ChannelOption),
+    /// highlights a profile in the picker, or opens it if it already was
+    SelectProfile(usize),
+    OpenProfile,
+    NewProfile,
+    DuplicateProfile,
+    DeleteProfile,
+}
+
+/// named parts of the layout tree that get looked up after layout
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Region {
+    Messages,
+    UserList,
+    Bar,
+    /// the left side of the bottom screen, help text or the settings panel, or the profile
+    /// list while picking one
+    Info,
+    Channels,
+    Buttons,
+}
+
+/// finds the action under a touch point on the bottom screen, if any
+pub fn hit(state: &State, point: Point) -> Option<Action> {
+    layout_bottom_screen(state).hit(point)
+}
+
+pub fn in_region(state: &State, region: Region, point: Point) -> bool {
+    layout_bottom_screen(state)
+        .find(region)
+        .is_some_and(|node| node.area.contains(point))
+}
+
+pub fn scroll_channels(state: &mut State, delta: i32) {
+    let max_scroll = layout_bottom_screen(state)
+        .find(Region::Channels)
+        .map_or(0, |node| node.max_scroll());
+    let channel_scroll = (state.channel_scroll + delta).clamp(0, max_scroll);
+    if channel_scroll != state.channel_scroll {
+        state.channel_scroll = channel_scroll;
+        state.mark_dirty(Dirty::CHANNELS);
+    }
+}
+
+/// the d-pad scrolls the settings panel a row at a time
+pub fn scroll_settings(state: &mut State, rows: i32) {
+    let max_scroll = layout_bottom_screen(state)
+        .find(Region::Info)
+        .map_or(0, |node| node.max_scroll());
+    let settings_scroll =
+        (state.settings_scroll + rows * CHANNEL_ROW_HEIGHT as i32).clamp(0, max_scroll);
+    if settings_scroll != state.settings_scroll {
+        state.settings_scroll = settings_scroll;
+        state.mark_dirty(Dirty::INFO);
+    }
+}
+
+/// scrolls the channel list just enough for the selected channel to be on screen
+pub fn scroll_to_selected(state: &mut State) {
+    let Some(index) = state.channels.iter().position(|ch| ch.selected) else {
+        return;
+    };
+    let visible_height = layout_bottom_screen(state)
+        .find(Region::Channels)
+        .map_or(0, |node| node.inner_area().size.height as i32);
+    let row_top = index as i32 * CHANNEL_ROW_HEIGHT as i32;
+    let row_bottom = row_top + CHANNEL_ROW_HEIGHT as i32;
+
+    if row_top < state.channel_scroll {
+        scroll_channels(state, row_top - state.channel_scroll);
+    } else if row_bottom > state.channel_scroll + visible_height {
+        scroll_channels(state, row_bottom - visible_height - state.channel_scroll);
+    }
+}
@@ -0,0 +1,127 @@
+use std::ops::Range;
+
+/// breaks `text` into lines no wider than `width`, using `char_width` to measure
+///
+/// lines break on whitespace, which gets dropped at the break. words that dont fit on a line
+/// of their own are broken wherever they hit the edge. newlines in `text` always start a new
+/// line. the lines are all slices of `text`, even the empty ones
+pub fn wrap(text: &str, width: i32, char_width: impl Fn(char) -> i32) -> Vec<&str> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        wrap_paragraph(paragraph, width, &char_width, &mut lines);
+    }
+    lines
+}
+
+fn wrap_paragraph<'a>(
+    paragraph: &'a str,
+    width: i32,
+    char_width: &impl Fn(char) -> i32,
+    lines: &mut Vec<&'a str>,
+) {
+    let measure = |text: &str| text.chars().map(char_width).sum::<i32>();
+
+    // the line being built, as byte offsets into `paragraph` and its width
+    let mut line: Option<(usize, usize, i32)> = None;
+    let mut first_word = true;
+
+    for (word_start, word) in words(paragraph) {
+        let word_end = word_start + word.len();
+        let word_width = measure(word);
+
+        if let Some((start, end, line_width)) = line {
+            let gap = measure(&paragraph[end..word_start]);
+            if line_width + gap + word_width <= width {
+                line = Some((start, word_end, line_width + gap + word_width));
+                continue;
+            }
+            lines.push(&paragraph[start..end]);
+        }
+
+        // leading whitespace is kept on the first line, its probably there on purpose
+        let start = if first_word && measure(&paragraph[..word_end]) <= width {
+            0
+        } else {
+            word_start
+        };
+        first_word = false;
+
+        let start_width = measure(&paragraph[start..word_start]) + word_width;
+        if start_width <= width {
+            line = Some((start, word_end, start_width));
+            continue;
+        }
+
+        // too long for any line, break it wherever it hits the edge
+        let mut piece_start = word_start;
+        let mut piece_width = 0;
+        for (offset, c) in word.char_indices() {
+            let index = word_start + offset;
+            let c_width = char_width(c);
+            if piece_width + c_width > width && index > piece_start {
+                lines.push(&paragraph[piece_start..index]);
+                piece_start = index;
+                piece_width = 0;
+            }
+            piece_width += c_width;
+        }
+        line = Some((piece_start, word_end, piece_width));
+    }
+
+    match line {
+        Some((start, end, _)) => lines.push(&paragraph[start..end]),
+        // nothing but whitespace, still takes up a line
+        None => lines.push(&paragraph[..0]),
+    }
+}
+
+/// the runs of non-whitespace in `text` with their byte offsets
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(char::is_whitespace)
+        .filter(|word| !word.is_empty())
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+/// lines of a wrapped message, as byte ranges into its text, and how far the lines after the
+/// first are indented
+pub type WrappedLines = (Vec<Range<usize>>, i32);
+
+/// where each message of a channel breaks into lines on the top screen, so redrawing only
+/// wraps the messages that are new since the last frame
+///
+/// it lives next to the messages, anything that changes a message thats already there has to
+/// [`WrapCache::forget`] it
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct WrapCache {
+    /// the width and nick column width the messages were wrapped for
+    key: (i32, usize),
+    messages: Vec<WrappedLines>,
+}
+
+impl WrapCache {
+    /// the lines of message `index`, if its been wrapped for `key`
+    pub fn get(&self, index: usize, key: (i32, usize)) -> Option<&WrappedLines> {
+        if self.key != key {
+            return None;
+        }
+        self.messages.get(index)
+    }
+
+    /// wraps the first `len` messages with `wrap`, keeping whatever was already wrapped for
+    /// `key`
+    pub fn fill(&mut self, len: usize, key: (i32, usize), wrap: impl Fn(usize) -> WrappedLines) {
+        if self.key != key {
+            self.key = key;
+            self.messages.clear();
+        }
+        self.messages.truncate(len);
+        for index in self.messages.len()..len {
+            self.messages.push(wrap(index));
+        }
+    }
+
+    /// drops message `index` and everything after it, theyre wrapped again on the next fill
+    pub fn forget(&mut self, index: usize) {
+        self.messages.truncate(index);
+    }
+}
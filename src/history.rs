@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// how many lines are kept per window, older ones fall off the end
+pub const HISTORY_LENGTH: usize = 50;
+
+/// what the user sent, so long lines dont have to be typed again
+///
+/// newest first everywhere. lines with passwords in them (see `is_secret`) are only kept until
+/// grairc closes, theyre left out when saving
+#[derive(Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct History {
+    /// lines from the A button, per window by lowercased name
+    #[serde(default)]
+    pub windows: HashMap<String, Vec<String>>,
+    /// lines from the X button, theyre not tied to a window
+    #[serde(default)]
+    pub raw: Vec<String>,
+}
+
+/// moves `line` to the front, dropping it from further back and anything past the limit
+fn remember(lines: &mut Vec<String>, line: &str) {
+    if line.trim().is_empty() {
+        return;
+    }
+    lines.retain(|existing| existing != line);
+    lines.insert(0, line.to_string());
+    lines.truncate(HISTORY_LENGTH);
+}
+
+/// commands that carry a password, the first word of a raw line or a /command
+const SECRET_COMMANDS: [&str; 4] = ["PASS", "OPER", "AUTHENTICATE", "NS"];
+
+/// whether `line` probably has a password in it, like `/msg NickServ IDENTIFY hunter2` or
+/// `PASS hunter2`. anything sent in a query with nickserv counts too
+pub fn is_secret(window: &str, line: &str) -> bool {
+    if window.eq_ignore_ascii_case("nickserv") {
+        return true;
+    }
+    let mut words = line
+        .split_whitespace()
+        .map(|word| word.trim_start_matches('/').to_uppercase());
+    let Some(mut first) = words.next() else {
+        return false;
+    };
+    // /quote and /raw send the rest as it is
+    if first == "QUOTE" || first == "RAW" {
+        first = words.next().unwrap_or_default();
+    }
+    SECRET_COMMANDS.contains(&first.as_str())
+        || std::iter::once(first)
+            .chain(words)
+            .any(|word| word == "NICKSERV" || word == "IDENTIFY")
+}
+
+impl History {
+    pub fn push(&mut self, window: &str, line: &str) {
+        remember(self.windows.entry(window.to_lowercase()).or_default(), line);
+    }
+
+    pub fn push_raw(&mut self, line: &str) {
+        remember(&mut self.raw, line);
+    }
+
+    pub fn lines(&self, window: &str) -> &[String] {
+        self.windows
+            .get(&window.to_lowercase())
+            .map_or(&[], |lines| lines.as_slice())
+    }
+
+    /// a missing file is just an empty history
+    pub fn load(path: &Path) -> Result<History, String> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(History::default()),
+            Err(e) => return Err(format!("couldnt read the history: {}", e)),
+        };
+        toml::from_str(&contents).map_err(|e| format!("couldnt read the history: {}", e.message()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let saved = History {
+            windows: self
+                .windows
+                .iter()
+                .map(|(window, lines)| {
+                    let lines = lines
+                        .iter()
+                        .filter(|line| !is_secret(window, line))
+                        .cloned()
+                        .collect();
+                    (window.clone(), lines)
+                })
+                .collect(),
+            raw: self
+                .raw
+                .iter()
+                .filter(|line| !is_secret("", line))
+                .cloned()
+                .collect(),
+        };
+        std::fs::write(path, toml::to_string_pretty(&saved)?)?;
+        Ok(())
+    }
+}
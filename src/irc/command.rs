@@ -0,0 +1,242 @@
+use crate::irc::mode::prefix_at_least;
+
+/// the slash commands understood in the message keyboard, with how to use them
+///
+/// in the order the completion picker lists them
+pub const COMMANDS: [(&str, &str); 8] = [
+    ("join", "/join #channel [key]"),
+    ("part", "/part [#channel] [reason]"),
+    ("msg", "/msg nick message"),
+    ("nick", "/nick newnick"),
+    ("topic", "/topic [new topic]"),
+    ("whois", "/whois nick"),
+    ("list", "/list [filter]"),
+    ("raw", "/raw IRC COMMAND"),
+];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Command {
+    Join {
+        channel: String,
+        key: Option<String>,
+    },
+    /// none leaves the channel on screen
+    Part {
+        channel: Option<String>,
+        reason: Option<String>,
+    },
+    Msg {
+        target: String,
+        text: String,
+    },
+    Nick(String),
+    /// none asks for the topic instead of setting it
+    Topic(Option<String>),
+    Whois(String),
+    /// opens the channel browser, only showing channels matching the filter
+    List(Option<String>),
+    Raw(String),
+}
+
+/// what the user typed into the message keyboard
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Input {
+    Message(String),
+    Command(Command),
+}
+
+impl Input {
+    /// anything starting with a single / is a command, // sends a message starting with /
+    pub fn parse(text: &str) -> Result<Input, String> {
+        let Some(command) = text.strip_prefix('/') else {
+            return Ok(Input::Message(text.to_string()));
+        };
+        if command.starts_with('/') {
+            return Ok(Input::Message(command.to_string()));
+        }
+
+        let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+        let name = match name.to_lowercase().as_str() {
+            "quote" => "raw".to_string(),
+            name => name.to_string(),
+        };
+        let args = args.trim();
+        let mut words = args.splitn(2, ' ');
+        let first = words.next().filter(|word| !word.is_empty());
+        let rest = words
+            .next()
+            .map(|rest| rest.trim().to_string())
+            .filter(|rest| !rest.is_empty());
+        let usage = || match COMMANDS.iter().find(|(command, _)| *command == name) {
+            Some((_, usage)) => format!("usage: {}", usage),
+            None => format!("usage: /{}", name),
+        };
+
+        let command = match name.as_str() {
+            "join" => Command::Join {
+                channel: first.ok_or_else(usage)?.to_string(),
+                key: rest,
+            },
+            "part" => match first {
+                Some(channel) if channel.starts_with('#') => Command::Part {
+                    channel: Some(channel.to_string()),
+                    reason: rest,
+                },
+                _ => Command::Part {
+                    channel: None,
+                    reason: Some(args.to_string()).filter(|reason| !reason.is_empty()),
+                },
+            },
+            "msg" => Command::Msg {
+                target: first.ok_or_else(usage)?.to_string(),
+                text: rest.ok_or_else(usage)?,
+            },
+            "nick" => Command::Nick(first.ok_or_else(usage)?.to_string()),
+            "whois" => Command::Whois(first.ok_or_else(usage)?.to_string()),
+            "list" => Command::List(Some(args.to_string()).filter(|filter| !filter.is_empty())),
+            "topic" => Command::Topic(Some(args.to_string()).filter(|topic| !topic.is_empty())),
+            "raw" if !args.is_empty() => Command::Raw(args.to_string()),
+            "raw" => return Err(usage()),
+            _ => {
+                let names: Vec<String> = COMMANDS
+                    .iter()
+                    .map(|(command, _)| format!("/{}", command))
+                    .collect();
+                return Err(format!(
+                    "unknown command /{}, try {}",
+                    name,
+                    names.join(" ")
+                ));
+            }
+        };
+        Ok(Input::Command(command))
+    }
+}
+
+impl Command {
+    /// the line sent to the server, `channel` is the one on screen if its a channel
+    ///
+    /// errors if the command needs a channel and theres none
+    pub fn line(&self, channel: Option<&str>) -> Result<String, String> {
+        let needs_channel =
+            |command: &str| channel.ok_or_else(|| format!("/{} only works in a channel", command));
+        let line = match self {
+            Command::Join { channel, key } => match key {
+                Some(key) => format!("JOIN {} {}", channel, key),
+                None => format!("JOIN {}", channel),
+            },
+            Command::Part {
+                channel: target,
+                reason,
+            } => {
+                let target = match target {
+                    Some(target) => target,
+                    None => needs_channel("part")?,
+                };
+                match reason {
+                    Some(reason) => format!("PART {} :{}", target, reason),
+                    None => format!("PART {}", target),
+                }
+            }
+            Command::Msg { target, text } => format!("PRIVMSG {} :{}", target, text),
+            Command::Nick(nick) => format!("NICK {}", nick),
+            Command::Topic(Some(topic)) => format!("TOPIC {} :{}", needs_channel("topic")?, topic),
+            Command::Topic(None) => format!("TOPIC {}", needs_channel("topic")?),
+            // asking their server too gets the idle time
+            Command::Whois(nick) => format!("WHOIS {} {}", nick, nick),
+            // filtering happens on our end, not every server can
+            Command::List(_) => "LIST".to_string(),
+            Command::Raw(line) => line.clone(),
+        };
+        Ok(line)
+    }
+}
+
+/// what an op can do to someone from the user panel
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Moderation {
+    Op,
+    Deop,
+    Voice,
+    Devoice,
+    Kick,
+    Ban,
+    /// bans first so they cant come straight back
+    KickBan,
+}
+
+impl Moderation {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Moderation::Op => "op",
+            Moderation::Deop => "deop",
+            Moderation::Voice => "voice",
+            Moderation::Devoice => "devoice",
+            Moderation::Kick => "kick",
+            Moderation::Ban => "ban",
+            Moderation::KickBan => "kick and ban",
+        }
+    }
+
+    /// the prefix we need in the channel, halfops can do everything but (de)op
+    pub fn needs(&self) -> char {
+        match self {
+            Moderation::Op | Moderation::Deop => '@',
+            _ => '%',
+        }
+    }
+
+    /// whats offered for someone with the prefixes `theirs` (highest first) when our highest
+    /// is `ours`, nothing if were not an op or theyre ranked above us
+    pub fn available(ours: Option<char>, theirs: &str) -> Vec<Moderation> {
+        if let Some(highest) = theirs.chars().next()
+            && !prefix_at_least(ours, highest)
+        {
+            return vec![];
+        }
+        let op = if theirs.contains('@') {
+            Moderation::Deop
+        } else {
+            Moderation::Op
+        };
+        let voice = if theirs.contains('+') {
+            Moderation::Devoice
+        } else {
+            Moderation::Voice
+        };
+        [
+            op,
+            voice,
+            Moderation::Kick,
+            Moderation::Ban,
+            Moderation::KickBan,
+        ]
+        .into_iter()
+        .filter(|moderation| prefix_at_least(ours, moderation.needs()))
+        .collect()
+    }
+
+    /// the lines to send, `mask` is only used for bans and `reason` for kicks
+    pub fn lines(
+        &self,
+        channel: &str,
+        nick: &str,
+        mask: &str,
+        reason: Option<&str>,
+    ) -> Vec<String> {
+        let kick = match reason {
+            Some(reason) => format!("KICK {} {} :{}", channel, nick, reason),
+            None => format!("KICK {} {}", channel, nick),
+        };
+        let ban = format!("MODE {} +b {}", channel, mask);
+        match self {
+            Moderation::Op => vec![format!("MODE {} +o {}", channel, nick)],
+            Moderation::Deop => vec![format!("MODE {} -o {}", channel, nick)],
+            Moderation::Voice => vec![format!("MODE {} +v {}", channel, nick)],
+            Moderation::Devoice => vec![format!("MODE {} -v {}", channel, nick)],
+            Moderation::Kick => vec![kick],
+            Moderation::Ban => vec![ban],
+            Moderation::KickBan => vec![ban, kick],
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::io::{Error, ErrorKind};
+
+/// the most a line can be, crlf included
+///
+/// for more information see https://modern.ircdocs.horse/#message-format
+pub const MAX_LINE_BYTES: usize = 512;
+
+/// hostnames can be up to this long, used when we dont know ours yet
+pub const MAX_HOST_BYTES: usize = 63;
+
+/// checks a line before its sent, line breaks would let the rest of it through as another
+/// command
+pub fn validate_line(line: &str) -> Result<(), Error> {
+    if line.contains(['\r', '\n', '\0']) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "lines cant contain line breaks",
+        ));
+    }
+    if line.len() + 2 > MAX_LINE_BYTES {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("lines cant be longer than {} bytes", MAX_LINE_BYTES - 2),
+        ));
+    }
+    Ok(())
+}
+
+/// splits text from the keyboard into pieces of at most `max_bytes`
+///
+/// every line break starts a new piece and empty lines are dropped. long lines are split at
+/// the last space that fits, or in the middle of a word if theres none near the end, but never
+/// inside a character
+pub fn split_message(text: &str, max_bytes: usize) -> Vec<String> {
+    let max_bytes = max_bytes.max(4);
+    let mut pieces = vec![];
+
+    for line in text.split(['\r', '\n']) {
+        let mut rest = line.trim_end();
+        while rest.len() > max_bytes {
+            let mut end = max_bytes;
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+            // a space in the back half means the word goes to the next piece whole
+            if let Some(space) = rest[..end].rfind(' ')
+                && space > end / 2
+            {
+                end = space;
+            }
+            pieces.push(rest[..end].to_string());
+            rest = rest[end..].trim_start_matches(' ');
+        }
+        if !rest.is_empty() {
+            pieces.push(rest.to_string());
+        }
+    }
+
+    pieces
+}
@@ -1,3 +1,8 @@
+pub mod command;
+pub mod line;
+pub mod mode;
+pub mod queue;
 pub mod regex;
 pub mod server;
+pub mod transport;
 pub mod types;
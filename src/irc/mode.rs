@@ -0,0 +1,111 @@
+/// the modes that give someone a prefix in the user list, highest first
+///
+/// servers can announce different ones in RPL_ISUPPORT but these are what nearly all of them use
+pub const PREFIX_MODES: [(char, char); 5] =
+    [('q', '~'), ('a', '&'), ('o', '@'), ('h', '%'), ('v', '+')];
+
+/// bans, ban exceptions and invite exceptions, each is a list of masks
+pub const LIST_MODES: [char; 3] = ['b', 'e', 'I'];
+
+/// modes that always have a param, like the channel key
+const ALWAYS_PARAM_MODES: [char; 1] = ['k'];
+/// modes that only have a param when theyre set, like the user limit
+const SET_PARAM_MODES: [char; 1] = ['l'];
+
+/// one letter out of a MODE line
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ModeChange {
+    /// + or -
+    pub adding: bool,
+    pub mode: char,
+    pub param: Option<String>,
+}
+
+/// the prefix `mode` gives in the user list, if its one of those
+pub fn prefix_for(mode: char) -> Option<char> {
+    PREFIX_MODES
+        .iter()
+        .find(|(prefix_mode, _)| *prefix_mode == mode)
+        .map(|(_, prefix)| *prefix)
+}
+
+/// whether `prefix` is `at_least` or higher, for checking what we can do in a channel
+pub fn prefix_at_least(prefix: Option<char>, at_least: char) -> bool {
+    let rank = |prefix: char| PREFIX_MODES.iter().position(|(_, p)| *p == prefix);
+    match (prefix.and_then(rank), rank(at_least)) {
+        (Some(rank), Some(at_least)) => rank <= at_least,
+        _ => false,
+    }
+}
+
+/// splits the modes of a MODE line (or RPL_CHANNELMODEIS) into single changes, handing out the
+/// params to the modes that take one
+///
+/// list modes without a mask are someone asking for the list, theyre left out
+pub fn parse_modes(modes: &str, params: &[&str]) -> Vec<ModeChange> {
+    let mut params = params.iter();
+    let mut adding = true;
+    let mut changes = vec![];
+
+    for mode in modes.chars() {
+        match mode {
+            '+' => adding = true,
+            '-' => adding = false,
+            mode => {
+                let takes_param = prefix_for(mode).is_some()
+                    || LIST_MODES.contains(&mode)
+                    || ALWAYS_PARAM_MODES.contains(&mode)
+                    || (adding && SET_PARAM_MODES.contains(&mode));
+                let param = if takes_param {
+                    params.next().map(|param| param.to_string())
+                } else {
+                    None
+                };
+                if param.is_none() && (prefix_for(mode).is_some() || LIST_MODES.contains(&mode)) {
+                    continue;
+                }
+                changes.push(ModeChange {
+                    adding,
+                    mode,
+                    param,
+                });
+            }
+        }
+    }
+
+    changes
+}
+
+/// the yes/no channel modes the moderation panel can flip, with what theyre called there
+pub const TOGGLE_MODES: [(char, &str); 3] = [
+    ('m', "moderated"),
+    ('i', "invite only"),
+    ('t', "ops only topic"),
+];
+
+/// the prefix we need to take a mask off a list mode, halfops can unban but only ops can touch
+/// the exceptions
+pub fn list_needs(mode: char) -> char {
+    match mode {
+        'b' => '%',
+        _ => '@',
+    }
+}
+
+/// what a list mode is called in the moderation panel
+pub fn list_label(mode: char) -> &'static str {
+    match mode {
+        'b' => "bans",
+        'e' => "ban exceptions",
+        'I' => "invite exceptions",
+        _ => "list",
+    }
+}
+
+/// bans the host when we know it so a new nick doesnt get around it, otherwise just the nick
+pub fn ban_mask(nick: &str, host: Option<&str>) -> String {
+    match host {
+        Some(host) => format!("*!*@{}", host),
+        None => format!("{}!*@*", nick),
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    collections::VecDeque,
+    io::{Error, ErrorKind, Write},
+    time::{Duration, Instant},
+};
+
+/// how many lines can go out back to back before the rate limit kicks in
+pub const DEFAULT_BURST: u32 = 5;
+/// one more line is allowed this often once the burst is used up
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// outgoing lines waiting for the rate limit, servers kill clients that send too much at once
+/// (excess flood)
+///
+/// its a token bucket, every line costs a token and tokens come back one per `interval` up to
+/// `burst`
+pub struct SendQueue {
+    lines: VecDeque<String>,
+    /// go out before `lines` in the order they came in, without using tokens
+    priority: VecDeque<String>,
+    /// whats left of a line the socket only took part of
+    partial: Vec<u8>,
+    burst: u32,
+    interval: Duration,
+    tokens: u32,
+    /// when the last token came back, or when the bucket was last full
+    refilled: Instant,
+}
+
+impl Default for SendQueue {
+    fn default() -> Self {
+        SendQueue::new(DEFAULT_BURST, DEFAULT_INTERVAL)
+    }
+}
+
+impl SendQueue {
+    pub fn new(burst: u32, interval: Duration) -> Self {
+        let burst = burst.max(1);
+        SendQueue {
+            lines: VecDeque::new(),
+            priority: VecDeque::new(),
+            partial: vec![],
+            burst,
+            interval,
+            tokens: burst,
+            refilled: Instant::now(),
+        }
+    }
+
+    /// changes the limits, whats already queued stays queued
+    pub fn set_limits(&mut self, burst: u32, interval: Duration) {
+        self.burst = burst.max(1);
+        self.interval = interval;
+        self.tokens = self.tokens.min(self.burst);
+    }
+
+    pub fn push(&mut self, line: &str) {
+        self.lines.push_back(format!("{}\r\n", line));
+    }
+
+    /// skips the queued lines and the rate limit, for replies the server is waiting on like PONG
+    ///
+    /// these dont use a token so they cant hold back or let through any other lines
+    pub fn push_priority(&mut self, line: &str) {
+        self.priority.push_back(format!("{}\r\n", line));
+    }
+
+    /// lines that havent been fully written yet
+    pub fn len(&self) -> usize {
+        self.lines.len() + self.priority.len() + usize::from(!self.partial.is_empty())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// drops everything, for when the connection goes away
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.priority.clear();
+        self.partial.clear();
+        self.tokens = self.burst;
+        self.refilled = Instant::now();
+    }
+
+    fn refill(&mut self) {
+        if self.tokens >= self.burst {
+            self.refilled = Instant::now();
+            return;
+        }
+        if self.interval.is_zero() {
+            self.tokens = self.burst;
+            return;
+        }
+        while self.tokens < self.burst && self.refilled.elapsed() >= self.interval {
+            self.tokens += 1;
+            self.refilled += self.interval;
+        }
+    }
+
+    /// writes as much as the rate limit and the socket allow
+    ///
+    /// the socket is nonblocking so it might only take part of a line, the rest goes out on a
+    /// later call
+    pub fn flush(&mut self, stream: &mut impl Write) -> Result<(), Error> {
+        self.refill();
+        loop {
+            if self.partial.is_empty() {
+                let line = match self.priority.pop_front() {
+                    Some(line) => line,
+                    None if self.tokens == 0 => return Ok(()),
+                    None => {
+                        let Some(line) = self.lines.pop_front() else {
+                            return Ok(());
+                        };
+                        self.tokens -= 1;
+                        line
+                    }
+                };
+                self.partial = line.into_bytes();
+            }
+
+            match stream.write(&self.partial) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(size) => {
+                    self.partial.drain(..size);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
@@ -1,16 +1,92 @@
 #![allow(unused)]
 
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+// compiled on first use, `irc_handler` runs every one of these against every line
+
 /// 1: nickname, 2: username, 3: host, 4: channel, 5: message content
-pub const PRIVMSG_REGEX: &str = r":(.*)!(.*)@(.*) PRIVMSG (.*) :(.*)";
+pub static PRIVMSG_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^:(\S+)!(\S+)@(\S+) PRIVMSG (\S+) :(.*)$").unwrap());
 
 /// 1: server, 2: client, 3: symbol, 4: channel, 5: user list
 ///
 /// for more information see https://modern.ircdocs.horse/#rplnamreply-353
-pub const RPL_NAMREPLY_REGEX: &str = r":(.*) 353 (.*) (.{1}) (.*) :(.*)";
+pub static RPL_NAMREPLY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r":(.*) 353 (.*) (.{1}) (.*) :(.*)").unwrap());
 
 /// 1: nickname, 2: username, 3: host, 4: channel, 5: optional leaving message
-pub const PART_REGEX: &str = r":(.*)!(.*)@(.*) PART (.*) :(.*)";
+pub static PART_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r":(\S*)!(\S*)@(\S*) PART (\S*)(?: :?(.*))?").unwrap());
+
+/// 1: nickname of whoever kicked, 2: username, 3: host, 4: channel, 5: who got kicked,
+/// 6: optional reason
+pub static KICK_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r":(\S*)!(\S*)@(\S*) KICK (\S*) (\S*)(?: :?(.*))?").unwrap());
 
 /// idk why its having the : i cant find it on the spec
 /// 1: nickname, 2: username, 3: host, 4: channel
-pub const JOIN_REGEX: &str = r":(.*)!(.*)@(.*) JOIN :?(.*)";
+pub static JOIN_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r":(.*)!(.*)@(.*) JOIN :?(.*)").unwrap());
+
+/// 1: old nickname, 2: username, 3: host, 4: new nickname
+pub static NICK_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^:(\S+)!(\S+)@(\S+) NICK :?(\S+)$").unwrap());
+
+/// 1: nickname, 2: username, 3: host, 4: reason
+pub static QUIT_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^:(\S+)!(\S+)@(\S+) QUIT :?(.*)$").unwrap());
+
+/// 1: the token to send back in the PONG
+///
+/// servers usually leave out the prefix but its allowed, see
+/// https://modern.ircdocs.horse/#ping-message
+pub static PING_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?::\S+ )?PING :?(.*)$").unwrap());
+
+/// 1: the token from our PING
+pub static PONG_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?::\S+ )?PONG \S+ :?(.*)$").unwrap());
+
+/// the numerics of a WHOIS or WHOWAS reply, see `UserInfo::apply`
+///
+/// 1: server, 2: numeric, 3: client, 4: nickname, 5: the rest of the params
+pub static WHOIS_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^:(\S*) (301|311|312|314|317|318|319|330|369|401|406|671) (\S*) (\S*) ?(.*)$")
+        .unwrap()
+});
+
+/// RPL_LISTSTART, RPL_LIST and RPL_LISTEND, the start is optional and often left out
+///
+/// 1: server, 2: numeric, 3: client, 4: the rest of the params
+pub static LIST_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^:(\S*) (321|322|323) (\S*) ?(.*)$").unwrap());
+
+/// 1: whoever set it (nick!user@host or a server), 2: channel or nickname, 3: modes,
+/// 4: their params
+pub static MODE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^:(\S*) MODE (\S*) :?(\S*) ?(.*)$").unwrap());
+
+/// RPL_CHANNELMODEIS and RPL_CREATIONTIME, which comes right after it
+///
+/// 1: server, 2: numeric, 3: client, 4: channel, 5: modes or the creation time, 6: mode params
+pub static RPL_CHANNELMODEIS_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^:(\S*) (324|329) (\S*) (\S*) :?(\S*) ?(.*)$").unwrap());
+
+/// the entries and ends of the invite exception (346/347), ban exception (348/349) and ban
+/// (367/368) lists
+///
+/// 1: server, 2: numeric, 3: client, 4: channel, 5: mask and who set it when, or the end message
+pub static MODE_LIST_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^:(\S*) (346|347|348|349|367|368) (\S*) (\S*) ?(.*)$").unwrap());
+
+/// 1: server, 2: nickname, 3: welcome message
+///
+/// for more information see https://modern.ircdocs.horse/#rplwelcome-001
+pub static RPL_WELCOME_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^:(\S+) 001 (\S+) :(.*)$").unwrap());
+
+/// 1: server, 2: client, 3: nickname that was taken, 4: message
+///
+/// for more information see https://modern.ircdocs.horse/#errnicknameinuse-433
+pub static ERR_NICKNAMEINUSE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^:(\S+) 433 (\S+) (\S+) :(.*)$").unwrap());
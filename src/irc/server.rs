@@ -1,107 +1,551 @@
 use std::{
-    io::{Error, Read, Write},
-    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    io::{Error, ErrorKind},
+    net::TcpStream,
+    time::{Duration, Instant},
 };
 
-use ctru::prelude::Soc;
-use regex::Regex;
-
 use crate::{
+    config::ChannelConfig,
+    directory::DirectoryEntry,
     irc::{
-        regex::{JOIN_REGEX, PART_REGEX, PRIVMSG_REGEX, RPL_NAMREPLY_REGEX},
-        types::{IrcChannel, IrcChannelType, IrcMessage},
+        line::{MAX_HOST_BYTES, MAX_LINE_BYTES, split_message, validate_line},
+        mode::parse_modes,
+        queue::SendQueue,
+        regex::{
+            ERR_NICKNAMEINUSE_REGEX, JOIN_REGEX, KICK_REGEX, LIST_REGEX, MODE_LIST_REGEX,
+            MODE_REGEX, NICK_REGEX, PART_REGEX, PING_REGEX, PONG_REGEX, PRIVMSG_REGEX, QUIT_REGEX,
+            RPL_CHANNELMODEIS_REGEX, RPL_NAMREPLY_REGEX, RPL_WELCOME_REGEX, WHOIS_REGEX,
+        },
+        transport::{Connector, Transport, connect_tcp},
+        types::{IrcChannel, IrcChannelType, IrcMessage, Membership, split_params},
     },
-    state::State,
+    state::{Dirty, State},
 };
 
-pub struct IrcServer<'a> {
-    _addr: SocketAddr,
-    stream: TcpStream,
-    _soc_service: &'a Soc, // this always needs to be kept alive
+fn join_command(channel: &str, key: Option<&str>) -> String {
+    match key {
+        Some(key) => format!("JOIN {} {}", channel, key),
+        None => format!("JOIN {}", channel),
+    }
+}
+
+/// how long to wait between reconnect attempts
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// how often we PING the server to see how laggy the connection is
+pub const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(30);
+/// the connection counts as dead when nothing at all has come from the server for this long
+pub const DEFAULT_PING_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// on the 3ds the soc service has to be kept alive for as long as this is around
+pub struct IrcServer<T: Transport = TcpStream> {
+    connector: Connector<T>,
+    hostname: String,
+    port: u16,
+    /// none while the connection is down
+    stream: Option<T>,
+    /// bytes of a line that hasnt fully arrived yet
+    read_buffer: Vec<u8>,
+    /// lines waiting to go out, see `send_line`
+    send_queue: SendQueue,
+    /// the nickname from the config, collisions get a number appended to this
+    base_nick: String,
+    /// the nickname were using (or trying to use) right now
+    pub nick: String,
+    nick_attempts: u32,
+    channels: Vec<ChannelConfig>,
+    /// whether the server has welcomed us yet
+    pub registered: bool,
+    last_reconnect: Option<Instant>,
+    /// the user@host the server puts in front of our messages, from the echo of our own JOIN
+    user_host: Option<String>,
+    /// when anything last came from the server
+    last_received: Instant,
+    /// the token of our lag check PING and when it went out, none once its answered
+    lag_ping: Option<(String, Instant)>,
+    /// when the last lag check was answered
+    last_lag_check: Instant,
+    lag_pings_sent: u32,
+    ping_interval: Duration,
+    ping_timeout: Duration,
+}
+
+impl IrcServer<TcpStream> {
+    pub fn new(hostname: &str, port: &u16) -> Self {
+        IrcServer::with_connector(hostname, *port, Box::new(connect_tcp))
+            .expect("Failed to connect to IRC server")
+    }
 }
 
-impl<'a> IrcServer<'a> {
-    pub fn new(hostname: &str, port: &u16, soc: &'a Soc) -> Self {
-        let addr = format!("{}:{}", hostname, port)
-            .to_socket_addrs()
-            .expect("Invalid IRC hostname")
-            .next()
-            .expect("No addresses found for IRC hostname");
-        println!("Connecting to IRC server at {}", addr);
-
-        let stream = TcpStream::connect(addr).expect("Failed to connect to IRC server");
-        println!("Setting stream to non-blocking mode");
-        stream
-            .set_nonblocking(true)
-            .expect("set_nonblocking call failed");
-        println!("Connected to the IRC socket successfully");
-
-        IrcServer {
-            _addr: addr,
-            stream,
-            _soc_service: soc,
-        }
-    }
-
-    pub fn irc_ident(&mut self, nick: &str, channels: &[String]) -> Result<(), Error> {
-        let user_cmd = format!("USER {} 0 * :{}\r\n", nick, nick);
-        let nick_cmd = format!("NICK {}\r\n", nick);
-        let join_cmd = channels
+impl<T: Transport> IrcServer<T> {
+    pub fn with_connector(
+        hostname: &str,
+        port: u16,
+        mut connector: Connector<T>,
+    ) -> Result<Self, Error> {
+        let stream = connector(hostname, port)?;
+        Ok(IrcServer {
+            connector,
+            hostname: hostname.to_string(),
+            port,
+            stream: Some(stream),
+            read_buffer: vec![],
+            send_queue: SendQueue::default(),
+            base_nick: String::new(),
+            nick: String::new(),
+            nick_attempts: 0,
+            channels: vec![],
+            registered: false,
+            last_reconnect: None,
+            user_host: None,
+            last_received: Instant::now(),
+            lag_ping: None,
+            last_lag_check: Instant::now(),
+            lag_pings_sent: 0,
+            ping_interval: DEFAULT_PING_INTERVAL,
+            ping_timeout: DEFAULT_PING_TIMEOUT,
+        })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// queues a single line and sends whatever the rate limit allows, the crlf is added here
+    ///
+    /// lines with line breaks in them or that are too long are refused. a failed write drops
+    /// the connection, the next `handler` call reconnects
+    pub fn send_line(&mut self, line: &str) -> Result<(), Error> {
+        validate_line(line)?;
+        if !self.is_connected() {
+            return Err(Error::new(
+                ErrorKind::NotConnected,
+                "not connected to the IRC server",
+            ));
+        }
+
+        self.send_queue.push(line);
+        self.flush()
+    }
+
+    /// like `send_line` but ahead of everything queued, for replies the server is waiting on
+    fn send_priority_line(&mut self, line: &str) -> Result<(), Error> {
+        validate_line(line)?;
+        self.send_queue.push_priority(line);
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        let Some(stream) = &mut self.stream else {
+            return Ok(());
+        };
+        let result = self.send_queue.flush(stream);
+        if result.is_err() {
+            self.stream = None;
+            self.send_queue.clear();
+        }
+        result
+    }
+
+    /// lines still waiting for the rate limit
+    pub fn queued(&self) -> usize {
+        self.send_queue.len()
+    }
+
+    /// how many lines can go out at once and how often another one can after that
+    pub fn set_flood_control(&mut self, burst: u32, interval: Duration) {
+        self.send_queue.set_limits(burst, interval);
+    }
+
+    /// how often to check the lag, and how long the server can stay quiet before we give up on
+    /// the connection and reconnect
+    pub fn set_ping_timing(&mut self, interval: Duration, timeout: Duration) {
+        self.ping_interval = interval;
+        self.ping_timeout = timeout;
+    }
+
+    /// registers with the server, the channels are joined once its welcomed us
+    pub fn irc_ident(&mut self, nick: &str, channels: &[ChannelConfig]) -> Result<(), Error> {
+        self.base_nick = nick.to_string();
+        self.nick = nick.to_string();
+        self.nick_attempts = 0;
+        self.channels = channels.to_vec();
+        self.registered = false;
+
+        self.register()
+    }
+
+    /// changes the nickname, while registered the server has to accept it first
+    pub fn set_nick(&mut self, nick: &str) -> Result<(), Error> {
+        self.base_nick = nick.to_string();
+        self.nick_attempts = 0;
+        if !self.registered {
+            self.nick = nick.to_string();
+        }
+        if !self.is_connected() {
+            return Ok(());
+        }
+        self.send_line(&format!("NICK {}", nick))
+    }
+
+    /// joins any channels that werent on the list before
+    pub fn set_channels(&mut self, channels: &[ChannelConfig]) -> Result<(), Error> {
+        let added: Vec<ChannelConfig> = channels
             .iter()
-            .map(|ch| format!("JOIN {}\r\n", ch))
-            .collect::<String>();
+            .filter(|channel| {
+                !self
+                    .channels
+                    .iter()
+                    .any(|old| old.name.eq_ignore_ascii_case(&channel.name))
+            })
+            .cloned()
+            .collect();
+        self.channels = channels.to_vec();
+
+        if self.registered {
+            for channel in added {
+                self.send_line(&join_command(&channel.name, channel.key.as_deref()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// leaves the current server, the next `handler` call connects to the new one
+    ///
+    /// the channels belong to the old server so theyre closed
+    pub fn set_server(&mut self, hostname: &str, port: u16, state: &mut State) {
+        if self.hostname == hostname && self.port == port {
+            return;
+        }
 
-        self.stream.write_all(user_cmd.as_bytes())?;
-        self.stream.write_all(nick_cmd.as_bytes())?;
-        self.stream.write_all(join_cmd.as_bytes())?;
+        if self.is_connected() {
+            self.send_line("QUIT :changing servers").ok();
+        }
+        self.hostname = hostname.to_string();
+        self.port = port;
+        self.stream = None;
+        self.registered = false;
+        self.read_buffer.clear();
+        self.send_queue.clear();
+        self.last_reconnect = None;
+        self.user_host = None;
+        state.set_lag(None);
+
+        state
+            .channels
+            .retain(|channel| channel.channel_type == IrcChannelType::System);
+        state.channel_scroll = 0;
+        state.select_channel(0);
+        state.push_system_message(format!("switching to {}:{}", hostname, port));
+    }
+
+    fn register(&mut self) -> Result<(), Error> {
+        // NAMES with every prefix someone has instead of just the highest, servers without it
+        // ignore the CAP lines
+        self.send_line("CAP REQ :multi-prefix")?;
+        self.send_line(&format!("USER {} 0 * :{}", self.base_nick, self.base_nick))?;
+        self.send_line(&format!("NICK {}", self.nick))?;
+        self.send_line("CAP END")?;
 
         Ok(())
     }
 
+    /// how much text fits in one PRIVMSG to `target` once the server puts our prefix in front
+    /// of it for everyone else
+    pub fn message_budget(&self, target: &str) -> usize {
+        let user_host = self.user_host.as_ref().map_or(
+            // ~user@ and the longest hostname there can be
+            self.base_nick.len() + 2 + MAX_HOST_BYTES,
+            |user_host| user_host.len(),
+        );
+        let prefix = format!(":{}! PRIVMSG {} :", self.nick, target).len() + user_host;
+        MAX_LINE_BYTES.saturating_sub(prefix + 2)
+    }
+
+    /// sends `message` as one PRIVMSG per line, with long lines split up so none are cut off
     pub fn irc_send(
         &mut self,
         message: &str,
         channel: &str,
-        nick: &str,
         state: &mut State,
     ) -> Result<(), Error> {
-        let msg_cmd = format!("PRIVMSG {} :{}\r\n", channel, message);
-        self.stream.write_all(msg_cmd.as_bytes())?;
-
-        state
-            .get_channel_by_name(channel)
-            .unwrap()
-            .messages
-            .push(IrcMessage {
-                nick: Some(nick.to_string()),
-                content: message.to_string(),
-            });
+        for piece in split_message(message, self.message_budget(channel)) {
+            self.send_line(&format!("PRIVMSG {} :{}", channel, piece))?;
+
+            state.push_message(
+                channel,
+                IrcMessage {
+                    nick: Some(self.nick.clone()),
+                    content: piece,
+                },
+            );
+        }
+        state.set_queued_lines(self.queued());
 
         Ok(())
     }
 
     pub fn irc_raw_send(&mut self, message: &str) -> Result<(), Error> {
-        self.stream
-            .write_all(format!("{}\r\n", message).as_bytes())?;
-        Ok(())
+        self.send_line(message)
     }
 
-    pub fn irc_handler(&mut self, message: &str, state: &mut State) {
-        let privmsg_regex = Regex::new(PRIVMSG_REGEX).unwrap();
-        let rpl_namreply_regex = Regex::new(RPL_NAMREPLY_REGEX).unwrap();
-        let part_regex = Regex::new(PART_REGEX).unwrap();
-        let join_regex = Regex::new(JOIN_REGEX).unwrap();
+    /// joins the configured channels plus anything we were in before a reconnect
+    fn join_channels(&mut self, state: &State) {
+        let mut joins: Vec<String> = self
+            .channels
+            .iter()
+            .map(|channel| join_command(&channel.name, channel.key.as_deref()))
+            .collect();
+        for channel in &state.channels {
+            let configured = self
+                .channels
+                .iter()
+                .any(|configured| configured.name.eq_ignore_ascii_case(&channel.name));
+            // channels we got kicked from stay left until theyre joined by hand
+            if channel.channel_type == IrcChannelType::Channel && channel.joined && !configured {
+                joins.push(join_command(&channel.name, None));
+            }
+        }
 
+        for join in joins {
+            if self.send_line(&join).is_err() {
+                return;
+            }
+        }
+    }
+
+    pub fn irc_handler(&mut self, message: &str, state: &mut State) {
         match message {
-            msg if msg.starts_with("PING") => {
-                let response = msg.replace("PING", "PONG");
-                self.stream
-                    .write_all(response.as_bytes())
-                    .expect("Failed to send PONG response");
+            // first so nothing in the token can make it look like something else
+            caps if PING_REGEX.captures(caps).is_some() => {
+                let captures = PING_REGEX.captures(caps).unwrap();
+                let token = captures.get(1).unwrap().as_str();
+
+                // a failed write drops the connection, the next handler call reconnects
+                self.send_priority_line(&format!("PONG :{}", token)).ok();
+            }
+
+            // only while looking someone up, away replies and missing nicks for messages we
+            // sent go to the system channel like before
+            caps if WHOIS_REGEX
+                .captures(caps)
+                .is_some_and(|captures| state.is_looking_up(&captures[4])) =>
+            {
+                let captures = WHOIS_REGEX.captures(caps).unwrap();
+                let numeric = captures.get(2).unwrap().as_str();
+                let nick = captures.get(4).unwrap().as_str();
+                let params = split_params(captures.get(5).unwrap().as_str());
+
+                // not online, but maybe they were a little while ago
+                if numeric == "401" {
+                    self.send_line(&format!("WHOWAS {}", nick)).ok();
+                }
+                state.update_user_info(nick, |info| {
+                    info.apply(numeric, &params);
+                });
+            }
+
+            // only when the browser asked, a LIST typed with X shows up in the system channel
+            caps if state.directory.listing && LIST_REGEX.captures(caps).is_some() => {
+                let captures = LIST_REGEX.captures(caps).unwrap();
+                let numeric = captures.get(2).unwrap().as_str();
+                let params = split_params(captures.get(4).unwrap().as_str());
+
+                match (numeric, params.as_slice()) {
+                    ("322", [name, users, rest @ ..]) => {
+                        let entry = DirectoryEntry {
+                            name: name.to_string(),
+                            users: users.parse().unwrap_or(0),
+                            topic: rest.last().unwrap_or(&"").to_string(),
+                        };
+                        state.update_directory(|directory| directory.push(entry));
+                    }
+                    ("323", _) => state.update_directory(|directory| directory.finish()),
+                    _ => {}
+                }
+            }
+
+            // user modes on ourselves go to the system channel like before
+            caps if MODE_REGEX
+                .captures(caps)
+                .is_some_and(|captures| captures[2].starts_with(['#', '&'])) =>
+            {
+                let captures = MODE_REGEX.captures(caps).unwrap();
+                let setter = captures.get(1).unwrap().as_str();
+                let setter = setter.split_once('!').map_or(setter, |(nick, _)| nick);
+                let channel_name = captures.get(2).unwrap().as_str();
+                let modes = captures.get(3).unwrap().as_str();
+                let params = split_params(captures.get(4).unwrap().as_str());
+
+                let Some(ch) = state.get_channel_by_name(channel_name) else {
+                    return;
+                };
+                for change in parse_modes(modes, &params) {
+                    ch.apply_mode(&change);
+                }
+                state.mark_channel_dirty(channel_name, Dirty::USERS.union(Dirty::INFO));
+                let mut content = format!("{} sets {}", setter, modes);
+                for param in params {
+                    content.push_str(&format!(" {}", param));
+                }
+                state.push_message(
+                    channel_name,
+                    IrcMessage {
+                        nick: None,
+                        content,
+                    },
+                );
+            }
+
+            caps if RPL_CHANNELMODEIS_REGEX.captures(caps).is_some() => {
+                let captures = RPL_CHANNELMODEIS_REGEX.captures(caps).unwrap();
+                let numeric = captures.get(2).unwrap().as_str();
+                let channel_name = captures.get(4).unwrap().as_str();
+                let modes = captures.get(5).unwrap().as_str();
+                let params = split_params(captures.get(6).unwrap().as_str());
+
+                // nothing to show for when the channel was made
+                if numeric == "329" {
+                    return;
+                }
+                let Some(ch) = state.get_channel_by_name(channel_name) else {
+                    return;
+                };
+                ch.modes.clear();
+                for change in parse_modes(modes, &params) {
+                    ch.apply_mode(&change);
+                }
+                state.mark_channel_dirty(channel_name, Dirty::INFO);
+            }
+
+            caps if MODE_LIST_REGEX.captures(caps).is_some() => {
+                let captures = MODE_LIST_REGEX.captures(caps).unwrap();
+                let numeric = captures.get(2).unwrap().as_str();
+                let channel_name = captures.get(4).unwrap().as_str();
+                let params = split_params(captures.get(5).unwrap().as_str());
+                let (mode, end) = match numeric {
+                    "346" => ('I', false),
+                    "347" => ('I', true),
+                    "348" => ('e', false),
+                    "349" => ('e', true),
+                    "367" => ('b', false),
+                    _ => ('b', true),
+                };
+
+                let Some(ch) = state.get_channel_by_name(channel_name) else {
+                    return;
+                };
+                let list = ch.mode_lists.entry(mode).or_default();
+                if !end
+                    && let Some(mask) = params.first()
+                    && !list.iter().any(|entry| entry == mask)
+                {
+                    list.push(mask.to_string());
+                }
+                state.mark_channel_dirty(channel_name, Dirty::INFO);
+            }
+
+            caps if RPL_WELCOME_REGEX.captures(caps).is_some() => {
+                let captures = RPL_WELCOME_REGEX.captures(caps).unwrap();
+                let nick = captures.get(2).unwrap().as_str();
+                let content = captures.get(3).unwrap().as_str();
+
+                self.registered = true;
+                self.last_reconnect = None;
+                self.lag_ping = None;
+                self.last_lag_check = Instant::now();
+                self.nick = nick.to_string();
+                state.set_nickname(nick);
+                state.push_system_message(content.to_string());
+                self.join_channels(state);
+            }
+
+            caps if ERR_NICKNAMEINUSE_REGEX.captures(caps).is_some() => {
+                let captures = ERR_NICKNAMEINUSE_REGEX.captures(caps).unwrap();
+                let nick = captures.get(3).unwrap().as_str();
+
+                if self.registered {
+                    state.push_system_message(format!("{} is already in use", nick));
+                    return;
+                }
+
+                self.nick_attempts += 1;
+                self.nick = format!("{}{}", self.base_nick, self.nick_attempts);
+                state.push_system_message(format!("{} is taken, trying {}", nick, self.nick));
+                let nick_cmd = format!("NICK {}", self.nick);
+                self.send_line(&nick_cmd).ok();
+            }
+
+            caps if NICK_REGEX.captures(caps).is_some() => {
+                let captures = NICK_REGEX.captures(caps).unwrap();
+                let old_nick = captures.get(1).unwrap().as_str();
+                let new_nick = captures.get(4).unwrap().as_str();
+
+                // every channel they were in gets the rename
+                let channel_names: Vec<String> = state
+                    .channels
+                    .iter_mut()
+                    .filter_map(|channel| {
+                        channel
+                            .rename_user(old_nick, new_nick)
+                            .then(|| channel.name.clone())
+                    })
+                    .collect();
+                for channel_name in channel_names {
+                    state.mark_channel_dirty(&channel_name, Dirty::USERS);
+                    state.push_membership(
+                        &channel_name,
+                        Membership::Nick {
+                            old: old_nick.to_string(),
+                            new: new_nick.to_string(),
+                        },
+                    );
+                }
+
+                if old_nick == self.nick {
+                    self.nick = new_nick.to_string();
+                    state.set_nickname(new_nick);
+                    state.push_system_message(format!("you are now known as {}", new_nick));
+                }
+            }
+
+            caps if QUIT_REGEX.captures(caps).is_some() => {
+                let captures = QUIT_REGEX.captures(caps).unwrap();
+                let nick = captures.get(1).unwrap().as_str();
+                let reason = captures.get(4).unwrap().as_str();
+
+                let channel_names: Vec<String> = state
+                    .channels
+                    .iter_mut()
+                    .filter_map(|channel| channel.remove_user(nick).then(|| channel.name.clone()))
+                    .collect();
+                for channel_name in channel_names {
+                    state.mark_channel_dirty(&channel_name, Dirty::USERS);
+                    state.push_membership(
+                        &channel_name,
+                        Membership::Quit {
+                            nick: nick.to_string(),
+                            reason: reason.to_string(),
+                        },
+                    );
+                }
+            }
+
+            caps if PONG_REGEX.captures(caps).is_some() => {
+                let captures = PONG_REGEX.captures(caps).unwrap();
+                let token = captures.get(1).unwrap().as_str();
+
+                if let Some((sent_token, sent)) = &self.lag_ping
+                    && sent_token == token
+                {
+                    state.set_lag(Some(sent.elapsed()));
+                    self.lag_ping = None;
+                    self.last_lag_check = Instant::now();
+                }
             }
 
-            caps if privmsg_regex.captures(caps).is_some() => {
-                let captures = privmsg_regex.captures(caps).unwrap();
+            caps if PRIVMSG_REGEX.captures(caps).is_some() => {
+                let captures = PRIVMSG_REGEX.captures(caps).unwrap();
                 let nick = captures.get(1).unwrap().as_str();
                 let channel_name = captures.get(4).unwrap().as_str();
                 let content = captures.get(5).unwrap().as_str();
@@ -110,17 +554,20 @@ impl<'a> IrcServer<'a> {
                     return;
                 }
 
-                if let Some(ch) = state.get_channel_by_name(channel_name) {
-                    println!("{:12}: {}", nick, content);
-                    ch.messages.push(IrcMessage {
+                let pushed = state.push_message(
+                    channel_name,
+                    IrcMessage {
                         nick: Some(nick.to_string()),
                         content: content.to_string(),
-                    });
+                    },
+                );
+                if pushed {
+                    println!("{:12}: {}", nick, content);
                 }
             }
 
-            caps if rpl_namreply_regex.captures(caps).is_some() => {
-                let captures = rpl_namreply_regex.captures(caps).unwrap();
+            caps if RPL_NAMREPLY_REGEX.captures(caps).is_some() => {
+                let captures = RPL_NAMREPLY_REGEX.captures(caps).unwrap();
                 let channel_name = captures.get(4).unwrap().as_str();
                 let user_list = captures.get(5).unwrap().as_str();
 
@@ -130,13 +577,10 @@ impl<'a> IrcServer<'a> {
 
                 let channel_exists = state.get_channel_by_name(channel_name).is_some();
                 if !channel_exists {
-                    state.channels.push(IrcChannel {
-                        selected: false,
-                        name: channel_name.to_string(),
-                        users: vec![],
-                        messages: vec![],
-                        channel_type: IrcChannelType::Channel,
-                    });
+                    state
+                        .channels
+                        .push(IrcChannel::new(channel_name, IrcChannelType::Channel));
+                    state.mark_dirty(Dirty::CHANNELS);
                 }
 
                 let channel = state
@@ -149,28 +593,35 @@ impl<'a> IrcServer<'a> {
                     .split_whitespace()
                     .map(|user| user.to_string())
                     .collect();
+                state.mark_channel_dirty(channel_name, Dirty::USERS);
             }
 
-            caps if join_regex.captures(caps).is_some() => {
-                let captures = join_regex.captures(caps).unwrap();
+            caps if JOIN_REGEX.captures(caps).is_some() => {
+                let captures = JOIN_REGEX.captures(caps).unwrap();
                 let nick = captures.get(1).unwrap().as_str();
                 let channel_name = captures.get(4).unwrap().as_str();
 
+                if nick == self.nick {
+                    let user = captures.get(2).unwrap().as_str();
+                    let host = captures.get(3).unwrap().as_str();
+                    self.user_host = Some(format!("{}@{}", user, host));
+                }
                 if !channel_name.starts_with('#') {
                     return;
                 }
 
+                if nick == self.nick {
+                    state.open_channel(channel_name);
+                }
                 if let Some(ch) = state.get_channel_by_name(channel_name) {
                     ch.users.push(nick.to_string());
-                    ch.messages.push(IrcMessage {
-                        nick: None,
-                        content: format!("-> {} joined", nick),
-                    });
+                    state.mark_channel_dirty(channel_name, Dirty::USERS);
+                    state.push_membership(channel_name, Membership::Join(nick.to_string()));
                 }
             }
 
-            caps if part_regex.captures(caps).is_some() => {
-                let captures = part_regex.captures(caps).unwrap();
+            caps if PART_REGEX.captures(caps).is_some() => {
+                let captures = PART_REGEX.captures(caps).unwrap();
                 let nick = captures.get(1).unwrap().as_str();
                 let channel_name = captures.get(4).unwrap().as_str();
 
@@ -178,26 +629,56 @@ impl<'a> IrcServer<'a> {
                     return;
                 }
 
+                // we left, the channel goes away with us
+                if nick == self.nick {
+                    state.close_channel(channel_name);
+                    return;
+                }
                 if let Some(ch) = state.get_channel_by_name(channel_name) {
-                    ch.users.retain(|user| user != nick);
-                    ch.messages.push(IrcMessage {
+                    ch.remove_user(nick);
+                    state.mark_channel_dirty(channel_name, Dirty::USERS);
+                    state.push_membership(channel_name, Membership::Part(nick.to_string()));
+                }
+            }
+
+            caps if KICK_REGEX.captures(caps).is_some() => {
+                let captures = KICK_REGEX.captures(caps).unwrap();
+                let kicker = captures.get(1).unwrap().as_str();
+                let channel_name = captures.get(4).unwrap().as_str();
+                let nick = captures.get(5).unwrap().as_str();
+                let reason = captures.get(6).map_or("", |reason| reason.as_str());
+
+                let Some(ch) = state.get_channel_by_name(channel_name) else {
+                    return;
+                };
+                ch.remove_user(nick);
+                let who = if nick == self.nick { "you" } else { nick };
+                let mut content = format!("<- {} got kicked by {}", who, kicker);
+                if !reason.is_empty() {
+                    content.push_str(&format!(" ({})", reason));
+                }
+                state.mark_channel_dirty(channel_name, Dirty::USERS);
+                state.push_message(
+                    channel_name,
+                    IrcMessage {
                         nick: None,
-                        content: format!("<- {} left", nick),
-                    });
+                        content,
+                    },
+                );
+                if nick == self.nick {
+                    state.leave_channel(channel_name);
                 }
             }
 
+            // the answer to asking for multi-prefix, whether it was taken or not theres nothing
+            // to show
+            msg if msg.split_whitespace().nth(1) == Some("CAP") => {}
+
             msg if msg.starts_with(":") => {
                 if let Some((_, trailing)) = msg.split_once(" :") {
-                    state.get_system_channel().messages.push(IrcMessage {
-                        nick: None,
-                        content: trailing.to_string(),
-                    });
+                    state.push_system_message(trailing.to_string());
                 } else {
-                    state.get_system_channel().messages.push(IrcMessage {
-                        nick: None,
-                        content: msg.to_string(),
-                    });
+                    state.push_system_message(msg.to_string());
                 }
             }
 
@@ -207,28 +688,116 @@ impl<'a> IrcServer<'a> {
         }
     }
 
-    /// checks for incoming messages and handles them
+    /// checks for incoming messages and handles them, reconnecting if the connection dropped
     /// run this in the main loop
-    pub fn handler(&mut self, state: &mut State) -> Result<(), Error> {
+    pub fn handler(&mut self, state: &mut State) {
+        if self.stream.is_none() {
+            self.reconnect(state);
+            return;
+        }
+        // lines held back by the rate limit
+        if let Err(e) = self.flush() {
+            self.connection_lost(state, &e.to_string());
+            return;
+        }
+        state.set_queued_lines(self.queued());
+        let Some(stream) = &mut self.stream else {
+            return;
+        };
+
         let mut buffer = [0; 512]; // irc is max 512 bytes per message
-        match self.stream.read(&mut buffer) {
-            Ok(size) if size > 0 => {
-                let message = String::from_utf8_lossy(&buffer[..size]);
-                for line in message.split("\r\n") {
+        match stream.read(&mut buffer) {
+            Ok(0) => self.connection_lost(state, "connection closed"),
+            Ok(size) => {
+                self.last_received = Instant::now();
+                // lines can be split across reads so only handle the ones that are complete
+                self.read_buffer.extend_from_slice(&buffer[..size]);
+                while let Some(end) = self.read_buffer.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = self.read_buffer.drain(..=end).collect();
+                    let line = String::from_utf8_lossy(&line);
+                    let line = line.trim_end_matches(['\r', '\n']);
                     if !line.is_empty() {
                         self.irc_handler(line, state);
                     }
                 }
             }
-            Ok(_) => {}                                                // no data read
-            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {} // no data available right now
-            Err(e) if e.kind() == std::io::ErrorKind::NetworkDown => {
-                panic!(
-                    "Network connection lost. Unfortunately there is no way to resume the network connection. If you know a way to do this, please open an issue on GitHub."
-                );
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {} // no data available right now
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => self.connection_lost(state, &e.to_string()),
+        }
+
+        self.check_lag(state);
+    }
+
+    /// sends lag check PINGs and gives up on connections that went quiet
+    fn check_lag(&mut self, state: &mut State) {
+        if !self.is_connected() {
+            return;
+        }
+        if self.last_received.elapsed() > self.ping_timeout {
+            let reason = format!(
+                "nothing from the server for {} seconds",
+                self.ping_timeout.as_secs()
+            );
+            self.connection_lost(state, &reason);
+            return;
+        }
+        if !self.registered {
+            return;
+        }
+
+        match &self.lag_ping {
+            // the lag shown keeps growing while the answer is late
+            Some((_, sent)) if state.lag.is_none_or(|lag| sent.elapsed() > lag) => {
+                state.set_lag(Some(sent.elapsed()));
+            }
+            Some(_) => {}
+            None if self.last_lag_check.elapsed() >= self.ping_interval => {
+                self.lag_pings_sent += 1;
+                let token = format!("grairc-{}", self.lag_pings_sent);
+                // ahead of the queue, otherwise flood control would count as lag
+                if self.send_priority_line(&format!("PING :{}", token)).is_ok() {
+                    self.lag_ping = Some((token, Instant::now()));
+                }
             }
-            Err(e) => return Err(e), // actual error
+            None => {}
+        }
+    }
+
+    fn connection_lost(&mut self, state: &mut State, reason: &str) {
+        self.stream = None;
+        self.registered = false;
+        self.read_buffer.clear();
+        self.send_queue.clear();
+        self.lag_ping = None;
+        state.set_queued_lines(0);
+        state.set_lag(None);
+        // the rest of a LIST isnt coming
+        state.update_directory(|directory| directory.finish());
+        state.push_system_message(format!("disconnected from the server: {}", reason));
+    }
+
+    fn reconnect(&mut self, state: &mut State) {
+        if let Some(last_reconnect) = self.last_reconnect
+            && last_reconnect.elapsed() < RECONNECT_INTERVAL
+        {
+            return;
+        }
+        self.last_reconnect = Some(Instant::now());
+
+        state.push_system_message("reconnecting...".to_string());
+        match (self.connector)(&self.hostname, self.port) {
+            Ok(stream) => {
+                self.stream = Some(stream);
+                self.send_queue.clear();
+                self.last_received = Instant::now();
+                self.nick = self.base_nick.clone();
+                self.nick_attempts = 0;
+                if let Err(e) = self.register() {
+                    self.connection_lost(state, &e.to_string());
+                }
+            }
+            Err(e) => state.push_system_message(format!("reconnecting failed: {}", e)),
         }
-        Ok(())
     }
 }
@@ -0,0 +1,31 @@
+use std::{
+    io::{Error, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+/// something irc bytes go over, a tcp socket normally or a scripted server in the tests
+///
+/// reads have to be nonblocking, `WouldBlock` means nothing is there yet and `Ok(0)` means the
+/// connection is gone
+pub trait Transport: Read + Write {}
+
+impl<T: Read + Write> Transport for T {}
+
+/// opens a new connection to a hostname and port, called once at the start and again every time
+/// the connection drops or the server changes
+pub type Connector<T> = Box<dyn FnMut(&str, u16) -> Result<T, Error>>;
+
+pub fn connect_tcp(hostname: &str, port: u16) -> Result<TcpStream, Error> {
+    let addr = format!("{}:{}", hostname, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| Error::other("No addresses found for IRC hostname"))?;
+    println!("Connecting to IRC server at {}", addr);
+
+    let stream = TcpStream::connect(addr)?;
+    println!("Setting stream to non-blocking mode");
+    stream.set_nonblocking(true)?;
+    println!("Connected to the IRC socket successfully");
+
+    Ok(stream)
+}
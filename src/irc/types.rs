@@ -1,10 +1,160 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use time::OffsetDateTime;
+
+use crate::gfx::wrap::WrapCache;
+use crate::irc::mode::{LIST_MODES, ModeChange, PREFIX_MODES, prefix_for};
+
 #[derive(PartialEq, Eq, Clone)]
 pub struct IrcChannel {
     pub selected: bool,
     pub name: String,
+    /// nicks with every @/+ style prefix they have in front, highest first like NAMES sends
+    /// them with multi-prefix
     pub users: Vec<String>,
     pub messages: Vec<IrcMessage>,
     pub channel_type: IrcChannelType,
+    /// when each nick last said something here, for the smart filter
+    pub last_spoke: HashMap<String, Instant>,
+    /// the joins/parts/quits/nick changes folded into the last message, empty if the last
+    /// message is something else
+    pub membership_run: Vec<Membership>,
+    /// false once we were kicked, the channel stays greyed out until its closed or rejoined
+    pub joined: bool,
+    /// modes that arent lists or prefixes, like n or l with its limit
+    pub modes: BTreeMap<char, Option<String>>,
+    /// the masks of each list mode, a list is only here once its been asked for
+    pub mode_lists: BTreeMap<char, Vec<String>>,
+    /// where the messages break into lines on the top screen, filled in by the renderer
+    pub wrapped: WrapCache,
+}
+
+impl IrcChannel {
+    pub fn new(name: &str, channel_type: IrcChannelType) -> Self {
+        IrcChannel {
+            selected: false,
+            name: name.to_string(),
+            users: vec![],
+            messages: vec![],
+            channel_type,
+            last_spoke: HashMap::new(),
+            membership_run: vec![],
+            joined: true,
+            modes: BTreeMap::new(),
+            mode_lists: BTreeMap::new(),
+            wrapped: WrapCache::default(),
+        }
+    }
+
+    pub fn has_user(&self, nick: &str) -> bool {
+        self.users.iter().any(|user| strip_prefix(user) == nick)
+    }
+
+    /// returns false if `nick` wasnt here
+    pub fn remove_user(&mut self, nick: &str) -> bool {
+        let len = self.users.len();
+        self.users.retain(|user| strip_prefix(user) != nick);
+        self.users.len() != len
+    }
+
+    /// renames `old` keeping its prefix, returns false if `old` wasnt here
+    pub fn rename_user(&mut self, old: &str, new: &str) -> bool {
+        let Some(user) = self.users.iter_mut().find(|user| strip_prefix(user) == old) else {
+            return false;
+        };
+        let prefix_len = user.len() - strip_prefix(user).len();
+        *user = format!("{}{}", &user[..prefix_len], new);
+
+        if let Some(last_spoke) = self.last_spoke.remove(old) {
+            self.last_spoke.insert(new.to_string(), last_spoke);
+        }
+        true
+    }
+
+    /// the highest @/+ style prefix `nick` has here
+    pub fn prefix_of(&self, nick: &str) -> Option<char> {
+        self.prefixes_of(nick).chars().next()
+    }
+
+    /// every @/+ style prefix `nick` has here, highest first
+    pub fn prefixes_of(&self, nick: &str) -> &str {
+        let Some(user) = self.users.iter().find(|user| strip_prefix(user) == nick) else {
+            return "";
+        };
+        &user[..user.len() - strip_prefix(user).len()]
+    }
+
+    /// keeps the modes, lists and prefixes up to date with a MODE line
+    pub fn apply_mode(&mut self, change: &ModeChange) {
+        if let Some(prefix) = prefix_for(change.mode) {
+            let Some(nick) = &change.param else {
+                return;
+            };
+            let Some(user) = self
+                .users
+                .iter_mut()
+                .find(|user| strip_prefix(user) == nick)
+            else {
+                return;
+            };
+            let current = &user[..user.len() - nick.len()];
+            // rebuilt in PREFIX_MODES order so the highest stays in front
+            let prefixes: String = PREFIX_MODES
+                .iter()
+                .map(|(_, other)| *other)
+                .filter(|other| match *other == prefix {
+                    true => change.adding,
+                    false => current.contains(*other),
+                })
+                .collect();
+            *user = format!("{}{}", prefixes, nick);
+        } else if LIST_MODES.contains(&change.mode) {
+            let (Some(list), Some(mask)) = (self.mode_lists.get_mut(&change.mode), &change.param)
+            else {
+                return;
+            };
+            list.retain(|entry| entry != mask);
+            if change.adding {
+                list.push(mask.clone());
+            }
+        } else if change.adding {
+            self.modes.insert(change.mode, change.param.clone());
+        } else {
+            self.modes.remove(&change.mode);
+        }
+    }
+
+    /// the modes like a MODE line would have them, "+ntl 50", empty if there arent any
+    pub fn mode_string(&self) -> String {
+        if self.modes.is_empty() {
+            return String::new();
+        }
+        let mut modes = "+".to_string();
+        let mut params = vec![];
+        for (mode, param) in &self.modes {
+            modes.push(*mode);
+            params.extend(param.as_deref());
+        }
+        params.insert(0, &modes);
+        params.join(" ")
+    }
+}
+
+/// the nick without any @/+ style channel status prefix from NAMES
+pub fn strip_prefix(user: &str) -> &str {
+    user.trim_start_matches(['~', '&', '@', '%', '+'])
+}
+
+/// how a user is shown in the user lists, with only their highest prefix
+pub fn display_user(user: &str) -> Cow<'_, str> {
+    let nick = strip_prefix(user);
+    let mut prefixes = user[..user.len() - nick.len()].chars();
+    match (prefixes.next(), prefixes.next()) {
+        (Some(highest), Some(_)) => Cow::Owned(format!("{}{}", highest, nick)),
+        _ => Cow::Borrowed(user),
+    }
 }
 
 #[derive(PartialEq, Eq, Clone)]
@@ -20,3 +170,257 @@ pub enum IrcChannelType {
     Channel,
     PrivateMessage,
 }
+
+/// someone coming, going or changing their nick in a channel
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Membership {
+    Join(String),
+    Part(String),
+    Quit { nick: String, reason: String },
+    Nick { old: String, new: String },
+}
+
+impl Membership {
+    /// the nick the smart filter looks at, the new one for nick changes since thats what
+    /// `IrcChannel::rename_user` moves the last message time to
+    pub fn nick(&self) -> &str {
+        match self {
+            Membership::Join(nick) | Membership::Part(nick) => nick,
+            Membership::Quit { nick, .. } => nick,
+            Membership::Nick { new, .. } => new,
+        }
+    }
+
+    /// the line for a single event
+    pub fn describe(&self) -> String {
+        match self {
+            Membership::Join(nick) => format!("-> {} joined", nick),
+            Membership::Part(nick) => format!("<- {} left", nick),
+            Membership::Quit { nick, reason } if reason.is_empty() => {
+                format!("<- {} quit", nick)
+            }
+            Membership::Quit { nick, reason } => format!("<- {} quit ({})", nick, reason),
+            Membership::Nick { old, new } => format!("{} is now {}", old, new),
+        }
+    }
+
+    /// one line for a run of events, like "3 joined, 2 left, alice is now bob"
+    pub fn summarize(events: &[Membership]) -> String {
+        if let [event] = events {
+            return event.describe();
+        }
+
+        let joined: Vec<&str> = events
+            .iter()
+            .filter_map(|event| match event {
+                Membership::Join(nick) => Some(nick.as_str()),
+                _ => None,
+            })
+            .collect();
+        let left: Vec<&str> = events
+            .iter()
+            .filter_map(|event| match event {
+                Membership::Part(nick) | Membership::Quit { nick, .. } => Some(nick.as_str()),
+                _ => None,
+            })
+            .collect();
+        // names when its just one, counts otherwise
+        let count = |nicks: &[&str], verb: &str| match nicks {
+            [] => None,
+            [nick] => Some(format!("{} {}", nick, verb)),
+            nicks => Some(format!("{} {}", nicks.len(), verb)),
+        };
+
+        let mut parts: Vec<String> = [count(&joined, "joined"), count(&left, "left")]
+            .into_iter()
+            .flatten()
+            .collect();
+        parts.extend(events.iter().filter_map(|event| match event {
+            Membership::Nick { .. } => Some(event.describe()),
+            _ => None,
+        }));
+        parts.join(", ")
+    }
+}
+
+/// what WHOIS (or WHOWAS for people who already left) told us about someone
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct UserInfo {
+    pub nick: String,
+    pub username: Option<String>,
+    pub host: Option<String>,
+    pub realname: Option<String>,
+    pub server: Option<String>,
+    /// usually where the server is
+    pub server_info: Option<String>,
+    pub idle: Option<Duration>,
+    /// unix time
+    pub signon: Option<i64>,
+    /// the services account theyre logged in to
+    pub account: Option<String>,
+    /// with their status prefix in each
+    pub channels: Vec<String>,
+    /// tls, from 671
+    pub secure: bool,
+    pub away: Option<String>,
+    /// the info came from WHOWAS, theyre not online
+    pub offline: bool,
+    /// the end of the reply came in, more could still be on the way otherwise
+    pub complete: bool,
+    /// WHOIS came up empty and a WHOWAS went out, so only the end of that counts
+    pub whowas: bool,
+    /// the server couldnt find them at all
+    pub error: Option<String>,
+}
+
+impl UserInfo {
+    pub fn new(nick: &str) -> Self {
+        UserInfo {
+            nick: nick.to_string(),
+            ..UserInfo::default()
+        }
+    }
+
+    /// fills in one numeric of a WHOIS or WHOWAS reply
+    ///
+    /// `params` are the ones after the nick, with the trailing one last. returns false for
+    /// numerics that arent part of a reply
+    pub fn apply(&mut self, numeric: &str, params: &[&str]) -> bool {
+        let param = |index: usize| params.get(index).map(|param| param.to_string());
+        match numeric {
+            // RPL_WHOISUSER and RPL_WHOWASUSER: user host * :realname
+            "311" | "314" => {
+                // WHOWAS found them after WHOIS didnt
+                self.error = None;
+                self.username = param(0);
+                self.host = param(1);
+                self.realname = param(3);
+                self.offline = numeric == "314";
+            }
+            // RPL_AWAY
+            "301" => self.away = param(0),
+            // RPL_WHOISSERVER: server :info
+            "312" => {
+                self.server = param(0);
+                self.server_info = param(1);
+            }
+            // RPL_WHOISIDLE: seconds signon :seconds idle, signon time
+            "317" => {
+                self.idle = params
+                    .first()
+                    .and_then(|idle| idle.parse().ok())
+                    .map(Duration::from_secs);
+                self.signon = params.get(1).and_then(|signon| signon.parse().ok());
+            }
+            // RPL_WHOISCHANNELS, can come more than once for people in lots of channels
+            "319" => {
+                if let Some(channels) = params.last() {
+                    self.channels
+                        .extend(channels.split_whitespace().map(|c| c.to_string()));
+                }
+            }
+            // RPL_WHOISACCOUNT: account :is logged in as
+            "330" => self.account = param(0),
+            // RPL_WHOISSECURE
+            "671" => self.secure = true,
+            // RPL_ENDOFWHOIS and RPL_ENDOFWHOWAS
+            "318" => self.complete = !self.whowas,
+            "369" => self.complete = true,
+            // ERR_NOSUCHNICK, the server sends a WHOWAS when it sees this
+            "401" => {
+                self.error = params.last().map(|error| error.to_string());
+                self.whowas = true;
+            }
+            // ERR_WASNOSUCHNICK
+            "406" => self.error = params.last().map(|error| error.to_string()),
+            _ => return false,
+        }
+        true
+    }
+
+    /// whats shown in the user panel, a line per thing we know
+    pub fn lines(&self) -> Vec<String> {
+        if let Some(error) = &self.error {
+            return vec![self.nick.clone(), error.clone()];
+        }
+
+        let mut lines = vec![match (&self.username, &self.host) {
+            (Some(username), Some(host)) => format!("{} ({}@{})", self.nick, username, host),
+            _ => self.nick.clone(),
+        }];
+        if self.offline {
+            lines.push("not online, this is from when they were".to_string());
+        }
+        if let Some(realname) = &self.realname {
+            lines.push(format!("name: {}", realname));
+        }
+        if let Some(account) = &self.account {
+            lines.push(format!("account: {}", account));
+        }
+        if let Some(away) = &self.away {
+            lines.push(format!("away: {}", away));
+        }
+        if !self.channels.is_empty() {
+            lines.push(format!("channels: {}", self.channels.join(" ")));
+        }
+        match (&self.server, &self.server_info) {
+            (Some(server), Some(info)) => lines.push(format!("server: {} ({})", server, info)),
+            (Some(server), None) => lines.push(format!("server: {}", server)),
+            _ => {}
+        }
+        if let Some(idle) = self.idle {
+            lines.push(format!("idle: {}", format_duration(idle)));
+        }
+        if let Some(signon) = self
+            .signon
+            .and_then(|signon| OffsetDateTime::from_unix_timestamp(signon).ok())
+        {
+            lines.push(format!(
+                "connected since: {} {:02}:{:02} utc",
+                signon.date(),
+                signon.hour(),
+                signon.minute()
+            ));
+        }
+        if self.secure {
+            lines.push("using a secure connection".to_string());
+        }
+        if !self.complete {
+            lines.push("looking them up...".to_string());
+        }
+        lines
+    }
+}
+
+/// the middle params and the trailing one of whats left of a line, trailing last
+pub fn split_params(rest: &str) -> Vec<&str> {
+    let (middle, trailing) = match rest.strip_prefix(':') {
+        Some(trailing) => ("", Some(trailing)),
+        None => match rest.split_once(" :") {
+            Some((middle, trailing)) => (middle, Some(trailing)),
+            None => (rest, None),
+        },
+    };
+    middle.split_whitespace().chain(trailing).collect()
+}
+
+/// like 2d 3h, 4m 5s or 12s, only the two biggest units
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let units = [
+        (seconds / 86400, "d"),
+        (seconds / 3600 % 24, "h"),
+        (seconds / 60 % 60, "m"),
+        (seconds % 60, "s"),
+    ];
+    let first = units
+        .iter()
+        .position(|(value, _)| *value > 0)
+        .unwrap_or(units.len() - 1);
+    units[first..]
+        .iter()
+        .take(2)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
@@ -0,0 +1,14 @@
+//! everything that doesnt depend on the 3ds, the ctru frontend lives in `main.rs` and the
+//! desktop simulator in `bin/simulator.rs`
+
+pub mod app;
+pub mod completion;
+pub mod config;
+pub mod directory;
+pub mod gfx;
+pub mod history;
+pub mod irc;
+pub mod logs;
+pub mod profile;
+pub mod state;
+pub mod theme;
@@ -0,0 +1,47 @@
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+
+use time::Time;
+
+use crate::irc::types::IrcMessage;
+
+/// one file per channel, named after it with anything that cant go in a file name swapped out
+pub fn log_path(dir: &Path, channel_name: &str) -> PathBuf {
+    let file_name: String = channel_name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    dir.join(format!("{}.log", file_name))
+}
+
+/// `[13:37:42] <nick> message` for messages and `[13:37:42] * event` for everything else
+pub fn format_line(time: Time, message: &IrcMessage) -> String {
+    let (hour, minute, second) = time.as_hms();
+    match &message.nick {
+        Some(nick) => format!(
+            "[{:02}:{:02}:{:02}] <{}> {}",
+            hour, minute, second, nick, message.content
+        ),
+        None => format!(
+            "[{:02}:{:02}:{:02}] * {}",
+            hour, minute, second, message.content
+        ),
+    }
+}
+
+pub fn append(
+    dir: &Path,
+    channel_name: &str,
+    time: Time,
+    message: &IrcMessage,
+) -> Result<(), Error> {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(log_path(dir, channel_name))?;
+    writeln!(file, "{}", format_line(time, message))
+}
@@ -1,14 +1,12 @@
-use ctru::{prelude::*, services::ptm::user::PTMUser};
+#[cfg(target_os = "horizon")]
+mod frontend;
 
-use crate::grairc::Grairc;
+#[cfg(target_os = "horizon")]
+fn main() {
+    use ctru::{prelude::*, services::ptm::user::PTMUser};
 
-pub mod config;
-pub mod gfx;
-pub mod grairc;
-pub mod irc;
-pub mod state;
+    use crate::frontend::grairc::Grairc;
 
-fn main() {
     let mut apt = Apt::new().expect("Couldn't obtain APT controller");
     let mut hid = Hid::new().expect("Couldn't obtain HID controller");
     let gfx = Gfx::new().expect("Couldn't obtain GFX controller");
@@ -19,3 +17,10 @@ fn main() {
 
     Grairc::new(&mut apt, &mut hid, &gfx, &mut soc, &mut ptmu).run();
 }
+
+#[cfg(not(target_os = "horizon"))]
+fn main() {
+    eprintln!(
+        "grairc only runs on the 3ds, use `cargo run --features simulator --bin grairc-sim` to try it on a pc"
+    );
+}
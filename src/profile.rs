@@ -0,0 +1,112 @@
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::config::CONFIG_FILE;
+
+/// every profile is a directory in here, named after the profile
+pub const PROFILES_DIR: &str = "/3ds/grairc/profiles";
+
+/// a named set of settings and logs, so several people can share a 3ds
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Profile {
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+impl Profile {
+    pub fn new(root: &Path, name: &str) -> Self {
+        Profile {
+            name: name.to_string(),
+            dir: root.join(name),
+        }
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.dir.join("config.toml")
+    }
+
+    pub fn history_path(&self) -> PathBuf {
+        self.dir.join("history.toml")
+    }
+
+    pub fn logs_dir(&self) -> PathBuf {
+        self.dir.join("logs")
+    }
+
+    /// every profile in `root`, sorted by name
+    pub fn list(root: &Path) -> Vec<Profile> {
+        let Ok(entries) = std::fs::read_dir(root) else {
+            return vec![];
+        };
+
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort_by_key(|name| name.to_lowercase());
+
+        names.iter().map(|name| Profile::new(root, name)).collect()
+    }
+
+    pub fn create(root: &Path, name: &str) -> Result<Profile, Error> {
+        let profile = Profile::new(root, name);
+        if profile.dir.exists() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("theres already a profile called {}", name),
+            ));
+        }
+        std::fs::create_dir_all(profile.logs_dir())?;
+        Ok(profile)
+    }
+
+    /// a new profile with the same settings, the logs stay behind
+    pub fn duplicate(&self, name: &str) -> Result<Profile, Error> {
+        let root = self.dir.parent().unwrap_or(Path::new(PROFILES_DIR));
+        let profile = Profile::create(root, name)?;
+        if self.config_path().exists() {
+            std::fs::copy(self.config_path(), profile.config_path())?;
+        }
+        Ok(profile)
+    }
+
+    pub fn delete(&self) -> Result<(), Error> {
+        std::fs::remove_dir_all(&self.dir)
+    }
+
+    /// moves the config from before profiles existed into a profile called default, so
+    /// updating doesnt mean setting everything up again
+    ///
+    /// does nothing once there are any profiles
+    pub fn migrate_legacy(root: &Path, legacy_config: &Path) -> Result<Option<Profile>, Error> {
+        if !legacy_config.exists() || !Profile::list(root).is_empty() {
+            return Ok(None);
+        }
+        let profile = Profile::create(root, "default")?;
+        std::fs::rename(legacy_config, profile.config_path())?;
+        Ok(Some(profile))
+    }
+
+    /// the profiles in `PROFILES_DIR`, after moving an old `CONFIG_FILE` into one
+    pub fn load_all() -> Result<Vec<Profile>, Error> {
+        let root = Path::new(PROFILES_DIR);
+        Profile::migrate_legacy(root, Path::new(CONFIG_FILE))?;
+        Ok(Profile::list(root))
+    }
+}
+
+/// profile names end up as directory names so anything a filesystem might choke on is out
+pub fn validate_profile_name(input: &str) -> Result<(), String> {
+    let name = input.trim();
+    if name.is_empty() {
+        return Err("the name cant be empty".to_string());
+    }
+    if name == "." || name == ".." || name.starts_with('.') {
+        return Err("the name cant start with a dot".to_string());
+    }
+    if name.contains(|c: char| "/\\:*?\"<>|".contains(c) || c.is_control()) {
+        return Err("the name cant contain / \\ : * ? \" < > |".to_string());
+    }
+    Ok(())
+}
@@ -1,14 +1,179 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use time::Time;
+
 use crate::{
-    config::Config,
-    irc::types::{IrcChannel, IrcChannelType},
+    completion::{self, CompletionKind},
+    config::{ChannelConfig, Config},
+    directory::Directory,
+    history::History,
+    irc::{
+        mode::{list_needs, prefix_at_least},
+        types::{IrcChannel, IrcChannelType, IrcMessage, Membership, UserInfo},
+    },
+    logs,
+    profile::Profile,
+    theme::Theme,
 };
 
+/// joins, parts and quits from people who havent spoken for this long are hidden by the smart
+/// filter
+pub const SMART_FILTER_DELAY: Duration = Duration::from_secs(5 * 60);
+
 pub struct State {
     pub config: Option<Config>,
+    /// the nickname the server knows us by, can differ from the configured one after a collision
+    pub nickname: Option<String>,
     pub channels: Vec<IrcChannel>,
     pub battery_state: BatteryState,
+    /// how far the bottom screen channel list is scrolled, in pixels
+    pub channel_scroll: i32,
+    /// how far the settings panel is scrolled, in pixels
+    pub settings_scroll: i32,
+    /// parts of the screens that changed since the last frame
+    pub dirty: Dirty,
+    /// average time spent rendering a frame, shown in the bar
+    pub frame_time: Duration,
+    /// the time shown in the bar, to the second
+    pub clock: Time,
+    pub theme: Theme,
+    /// the built in themes followed by the custom ones
+    pub themes: Vec<Theme>,
+    /// whats shown on the left side of the bottom screen
+    pub panel: Panel,
+    /// the profile in use, none until one is picked
+    pub profile: Option<Profile>,
+    /// the profiles to pick from at startup
+    pub profiles: Vec<Profile>,
+    /// the highlighted profile in the picker
+    pub profile_selection: usize,
+    /// messages are appended to a file per channel in here, nothing is logged without one
+    pub log_dir: Option<PathBuf>,
+    /// the tab shown in the completion picker
+    pub completion_kind: CompletionKind,
+    /// the highlighted completion, none sends a message without one
+    pub completion_selection: Option<usize>,
+    /// what was sent, saved in the profile
+    pub history: History,
+    /// outgoing lines held back by flood control, shown in the bar
+    pub queued_lines: usize,
+    /// how long the server took to answer our last PING, none until it has
+    pub lag: Option<Duration>,
+    /// WHOIS replies by lowercased nick
+    pub user_info: HashMap<String, UserInfo>,
+    /// the nick shown in the user panel, none shows the list to pick from
+    pub selected_user: Option<String>,
+    /// how many rows down the user panel list is scrolled
+    pub user_list_scroll: usize,
+    /// the channel browser
+    pub directory: Directory,
+    /// switch to this channel as soon as its joined, for channels joined from the ui
+    pub focus_on_join: Option<String>,
+    /// the list mode shown in the mode list panel, b, e or I
+    pub mode_list: char,
+    /// the highlighted mask in the mode list panel
+    pub mask_selection: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Panel {
+    Info,
+    Settings,
+    /// settings for the channel on screen
+    Channel,
+    /// takes over the whole bottom screen at startup
+    Profiles,
+    /// nicks, channels and commands to start a message with
+    Compose,
+    /// the people in the channel on screen, or whats known about one of them
+    User,
+    /// every channel on the server, from LIST
+    Directory,
+    /// op tools for the channel on screen
+    Moderation,
+    /// one of the ban/exception/invite lists of the channel on screen, see `State::mode_list`
+    ModeList,
+}
+
+/// which regions of the screens need to be redrawn
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Dirty {
+    pub messages: bool,
+    pub users: bool,
+    pub bar: bool,
+    pub info: bool,
+    pub channels: bool,
+    pub buttons: bool,
+}
+
+impl Dirty {
+    pub const NONE: Dirty = Dirty {
+        messages: false,
+        users: false,
+        bar: false,
+        info: false,
+        channels: false,
+        buttons: false,
+    };
+    pub const ALL: Dirty = Dirty {
+        messages: true,
+        users: true,
+        bar: true,
+        info: true,
+        channels: true,
+        buttons: true,
+    };
+    pub const MESSAGES: Dirty = Dirty {
+        messages: true,
+        ..Dirty::NONE
+    };
+    pub const USERS: Dirty = Dirty {
+        users: true,
+        ..Dirty::NONE
+    };
+    pub const BAR: Dirty = Dirty {
+        bar: true,
+        ..Dirty::NONE
+    };
+    pub const INFO: Dirty = Dirty {
+        info: true,
+        ..Dirty::NONE
+    };
+    pub const CHANNELS: Dirty = Dirty {
+        channels: true,
+        ..Dirty::NONE
+    };
+    /// everything that depends on which channel is selected
+    pub const CHANNEL_SWITCH: Dirty = Dirty {
+        messages: true,
+        users: true,
+        channels: true,
+        ..Dirty::NONE
+    };
+
+    pub fn union(self, other: Dirty) -> Dirty {
+        Dirty {
+            messages: self.messages || other.messages,
+            users: self.users || other.users,
+            bar: self.bar || other.bar,
+            info: self.info || other.info,
+            channels: self.channels || other.channels,
+            buttons: self.buttons || other.buttons,
+        }
+    }
+
+    pub fn top(&self) -> bool {
+        self.messages || self.users || self.bar
+    }
+
+    pub fn bottom(&self) -> bool {
+        self.info || self.channels || self.buttons
+    }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum BatteryState {
     Charging,
     Critical,
@@ -23,14 +188,36 @@ impl Default for State {
     fn default() -> Self {
         State {
             config: None,
+            nickname: None,
             channels: vec![IrcChannel {
                 selected: true,
-                name: "<system>".to_string(),
-                users: vec![],
-                messages: vec![],
-                channel_type: IrcChannelType::System,
+                ..IrcChannel::new("<system>", IrcChannelType::System)
             }],
             battery_state: BatteryState::Drained,
+            channel_scroll: 0,
+            settings_scroll: 0,
+            dirty: Dirty::ALL,
+            frame_time: Duration::ZERO,
+            clock: Time::MIDNIGHT,
+            theme: Theme::dark(),
+            themes: Theme::builtin(),
+            panel: Panel::Info,
+            profile: None,
+            profiles: vec![],
+            profile_selection: 0,
+            log_dir: None,
+            completion_kind: CompletionKind::Nicks,
+            completion_selection: None,
+            history: History::default(),
+            queued_lines: 0,
+            lag: None,
+            user_info: HashMap::new(),
+            selected_user: None,
+            user_list_scroll: 0,
+            directory: Directory::default(),
+            focus_on_join: None,
+            mode_list: 'b',
+            mask_selection: None,
         }
     }
 }
@@ -50,6 +237,360 @@ impl State {
             ch.selected = false;
         }
         self.channels[new_index as usize].selected = true;
+        self.channel_switched();
+    }
+
+    pub fn select_channel(&mut self, index: usize) {
+        if index >= self.channels.len() {
+            return;
+        }
+
+        for ch in &mut self.channels {
+            ch.selected = false;
+        }
+        self.channels[index].selected = true;
+        self.channel_switched();
+    }
+
+    fn channel_switched(&mut self) {
+        self.mark_dirty(Dirty::CHANNEL_SWITCH);
+        // the completions and the user panel are for the old channel
+        self.completion_selection = None;
+        self.select_user(None);
+        self.user_list_scroll = 0;
+        self.mask_selection = None;
+        // the settings panels show things about the current channel
+        if self.panel != Panel::Info {
+            self.mark_dirty(Dirty::INFO);
+        }
+    }
+
+    pub fn mark_dirty(&mut self, dirty: Dirty) {
+        self.dirty = self.dirty.union(dirty);
+    }
+
+    /// marks regions dirty, but only if `channel_name` is the channel on screen
+    pub fn mark_channel_dirty(&mut self, channel_name: &str, dirty: Dirty) {
+        if self.current_channel_static().name == channel_name {
+            self.mark_dirty(dirty);
+        }
+    }
+
+    /// everything gets clobbered when an applet like the keyboard takes over the screens
+    pub fn redraw_all(&mut self) {
+        self.mark_dirty(Dirty::ALL);
+    }
+
+    /// returns false if theres no channel called `channel_name`
+    pub fn push_message(&mut self, channel_name: &str, message: IrcMessage) -> bool {
+        let Some(channel) = self.get_channel_by_name(channel_name) else {
+            return false;
+        };
+        if let Some(nick) = &message.nick {
+            channel.last_spoke.insert(nick.clone(), Instant::now());
+        }
+        channel.membership_run.clear();
+        channel.messages.push(message);
+
+        self.log(channel_name, self.last_message(channel_name));
+        self.mark_channel_dirty(channel_name, Dirty::MESSAGES);
+        true
+    }
+
+    pub fn push_system_message(&mut self, content: String) {
+        let name = self.get_system_channel().name.clone();
+        self.push_message(
+            &name,
+            IrcMessage {
+                nick: None,
+                content,
+            },
+        );
+    }
+
+    /// shows a join/part/quit/nick change in `channel_name`, folded into the line before it if
+    /// that was one too
+    ///
+    /// everything is logged, even what the channel settings hide
+    pub fn push_membership(&mut self, channel_name: &str, event: Membership) {
+        let line = IrcMessage {
+            nick: None,
+            content: event.describe(),
+        };
+        self.log(channel_name, Some(&line));
+
+        if self.hides_membership(channel_name, event.nick()) {
+            return;
+        }
+        let Some(channel) = self.get_channel_by_name(channel_name) else {
+            return;
+        };
+        channel.membership_run.push(event);
+        let summary = Membership::summarize(&channel.membership_run);
+        match channel.messages.last_mut() {
+            Some(last) if channel.membership_run.len() > 1 => {
+                last.content = summary;
+                channel.wrapped.forget(channel.messages.len() - 1);
+            }
+            _ => channel.messages.push(IrcMessage {
+                nick: None,
+                content: summary,
+            }),
+        }
+        self.mark_channel_dirty(channel_name, Dirty::MESSAGES);
+    }
+
+    /// hidden either completely by the channel settings, or by the smart filter because `nick`
+    /// hasnt said anything in a while
+    ///
+    /// channels without their own settings go by `Config::smart_filter`
+    fn hides_membership(&self, channel_name: &str, nick: &str) -> bool {
+        let smart_filter = match self.channel_config(channel_name) {
+            Some(channel) if channel.hide_joins_parts => return true,
+            Some(channel) => channel.smart_filter,
+            None => self
+                .config
+                .as_ref()
+                .is_none_or(|config| config.smart_filter),
+        };
+        if !smart_filter {
+            return false;
+        }
+        let spoke_recently = self
+            .channels
+            .iter()
+            .find(|channel| channel.name == channel_name)
+            .and_then(|channel| channel.last_spoke.get(nick))
+            .is_some_and(|last_spoke| last_spoke.elapsed() < SMART_FILTER_DELAY);
+        !spoke_recently
+    }
+
+    fn last_message(&self, channel_name: &str) -> Option<&IrcMessage> {
+        self.channels
+            .iter()
+            .find(|channel| channel.name == channel_name)
+            .and_then(|channel| channel.messages.last())
+    }
+
+    fn log(&self, channel_name: &str, message: Option<&IrcMessage>) {
+        let (Some(dir), Some(message)) = (&self.log_dir, message) else {
+            return;
+        };
+        if self
+            .channel_config(channel_name)
+            .is_some_and(|channel| !channel.log)
+        {
+            return;
+        }
+        if let Err(e) = logs::append(dir, channel_name, self.clock, message) {
+            // not worth a system message, that would just fail to log too
+            println!("couldnt write to the {} log: {}", channel_name, e);
+        }
+    }
+
+    /// the settings for `channel_name` if its in the config
+    pub fn channel_config(&self, channel_name: &str) -> Option<&ChannelConfig> {
+        self.config.as_ref()?.channel(channel_name)
+    }
+
+    pub fn set_profiles(&mut self, profiles: Vec<Profile>) {
+        self.profiles = profiles;
+        self.profile_selection = self
+            .profile_selection
+            .min(self.profiles.len().saturating_sub(1));
+        self.mark_dirty(Dirty::INFO);
+    }
+
+    pub fn select_profile(&mut self, index: usize) {
+        if index < self.profiles.len() && index != self.profile_selection {
+            self.profile_selection = index;
+            self.mark_dirty(Dirty::INFO);
+        }
+    }
+
+    /// moves the picker highlight, wrapping around at the ends
+    pub fn move_profile_selection(&mut self, change: isize) {
+        let len = self.profiles.len() as isize;
+        if len == 0 {
+            return;
+        }
+        let index = (self.profile_selection as isize + change).rem_euclid(len);
+        self.select_profile(index as usize);
+    }
+
+    /// switches the completion picker tab, dropping the highlight
+    pub fn set_completion_kind(&mut self, kind: CompletionKind) {
+        self.completion_kind = kind;
+        self.completion_selection = None;
+        self.mark_dirty(Dirty::INFO);
+    }
+
+    /// moves the completion highlight, past either end goes back to no completion
+    pub fn move_completion_selection(&mut self, change: isize) {
+        let len = completion::completions(self, self.completion_kind).len() as isize;
+        let index = match self.completion_selection {
+            Some(index) => index as isize + change,
+            None if change < 0 => len - 1,
+            None => 0,
+        };
+        self.completion_selection = (0..len).contains(&index).then_some(index as usize);
+        self.mark_dirty(Dirty::INFO);
+    }
+
+    /// shows `nick` in the user panel and highlights them in the user list
+    pub fn select_user(&mut self, nick: Option<&str>) {
+        if self.selected_user.as_deref() != nick {
+            self.selected_user = nick.map(|nick| nick.to_string());
+            self.mark_dirty(Dirty::USERS.union(Dirty::INFO));
+        }
+    }
+
+    /// scrolls the user panel list a row at a time, it doesnt go past the last person
+    pub fn scroll_user_list(&mut self, change: isize) {
+        if self.selected_user.is_some() {
+            return;
+        }
+        let last = self.current_channel_static().users.len().saturating_sub(1);
+        let scroll = self
+            .user_list_scroll
+            .saturating_add_signed(change)
+            .min(last);
+        if scroll != self.user_list_scroll {
+            self.user_list_scroll = scroll;
+            self.mark_dirty(Dirty::INFO);
+        }
+    }
+
+    /// forgets anything known about `nick` until the WHOIS reply comes in
+    pub fn start_lookup(&mut self, nick: &str) {
+        self.user_info
+            .insert(nick.to_lowercase(), UserInfo::new(nick));
+        self.mark_dirty(Dirty::INFO);
+    }
+
+    /// whether were waiting on a WHOIS reply about `nick`
+    pub fn is_looking_up(&self, nick: &str) -> bool {
+        self.user_info
+            .get(&nick.to_lowercase())
+            .is_some_and(|info| !info.complete)
+    }
+
+    pub fn get_user_info(&self, nick: &str) -> Option<&UserInfo> {
+        self.user_info.get(&nick.to_lowercase())
+    }
+
+    pub fn update_user_info(&mut self, nick: &str, update: impl FnOnce(&mut UserInfo)) {
+        let Some(info) = self.user_info.get_mut(&nick.to_lowercase()) else {
+            return;
+        };
+        update(info);
+        if self.panel == Panel::User {
+            self.mark_dirty(Dirty::INFO);
+        }
+    }
+
+    /// our @/+ style prefix in the channel on screen
+    pub fn our_prefix(&self) -> Option<char> {
+        let nickname = self.nickname.as_ref()?;
+        self.current_channel_static().prefix_of(nickname)
+    }
+
+    /// empties `mode` of the channel on screen and shows it, the server fills it back in
+    pub fn open_mode_list(&mut self, mode: char) {
+        self.current_channel().mode_lists.insert(mode, vec![]);
+        self.mode_list = mode;
+        self.mask_selection = None;
+        self.set_panel(Panel::ModeList);
+        self.mark_dirty(Dirty::INFO);
+    }
+
+    /// whether we can take masks off the open mode list, its read only otherwise
+    pub fn can_edit_mode_list(&self) -> bool {
+        prefix_at_least(self.our_prefix(), list_needs(self.mode_list))
+    }
+
+    /// the masks in the open mode list panel
+    pub fn mode_list_masks(&self) -> &[String] {
+        self.current_channel_static()
+            .mode_lists
+            .get(&self.mode_list)
+            .map_or(&[], |masks| masks.as_slice())
+    }
+
+    /// moves the mode list highlight, it stops at either end
+    pub fn move_mask_selection(&mut self, change: isize) {
+        let len = self.mode_list_masks().len();
+        self.mask_selection = match self.mask_selection {
+            _ if len == 0 => None,
+            Some(index) => Some(index.saturating_add_signed(change).min(len - 1)),
+            None => Some(0),
+        };
+        self.mark_dirty(Dirty::INFO);
+    }
+
+    /// changes the channel browser, redrawing it if its open
+    pub fn update_directory(&mut self, update: impl FnOnce(&mut Directory)) {
+        update(&mut self.directory);
+        if self.panel == Panel::Directory {
+            self.mark_dirty(Dirty::INFO);
+        }
+    }
+
+    pub fn set_nickname(&mut self, nickname: &str) {
+        if self.nickname.as_deref() != Some(nickname) {
+            self.nickname = Some(nickname.to_string());
+            self.mark_dirty(Dirty::BAR);
+        }
+    }
+
+    pub fn set_clock(&mut self, clock: Time) {
+        let clock = clock.replace_nanosecond(0).unwrap_or(clock);
+        if self.clock != clock {
+            self.clock = clock;
+            self.mark_dirty(Dirty::BAR);
+        }
+    }
+
+    /// returns false if theres no theme called `name`
+    pub fn set_theme(&mut self, name: &str) -> bool {
+        let Some(theme) = self.themes.iter().find(|theme| theme.name == name) else {
+            return false;
+        };
+        if self.theme != *theme {
+            self.theme = theme.clone();
+            self.redraw_all();
+        }
+        true
+    }
+
+    pub fn set_panel(&mut self, panel: Panel) {
+        if self.panel != panel {
+            self.panel = panel;
+            self.mark_dirty(Dirty::INFO);
+        }
+    }
+
+    pub fn set_queued_lines(&mut self, queued_lines: usize) {
+        if self.queued_lines != queued_lines {
+            self.queued_lines = queued_lines;
+            self.mark_dirty(Dirty::BAR);
+        }
+    }
+
+    /// to a tenth of a second, finer than that would just redraw the bar all the time
+    pub fn set_lag(&mut self, lag: Option<Duration>) {
+        let lag = lag.map(|lag| Duration::from_millis(lag.as_millis() as u64 / 100 * 100));
+        if self.lag != lag {
+            self.lag = lag;
+            self.mark_dirty(Dirty::BAR);
+        }
+    }
+
+    pub fn set_battery_state(&mut self, battery_state: BatteryState) {
+        if self.battery_state != battery_state {
+            self.battery_state = battery_state;
+            self.mark_dirty(Dirty::BAR);
+        }
     }
 
     pub fn current_channel(&mut self) -> &mut IrcChannel {
@@ -71,4 +612,74 @@ impl State {
     pub fn get_channel_by_name(&mut self, name: &str) -> Option<&mut IrcChannel> {
         self.channels.iter_mut().find(|ch| ch.name == name)
     }
+
+    /// opens a channel we just joined, or brings back one we were kicked from
+    pub fn open_channel(&mut self, name: &str) {
+        match self.get_channel_by_name(name) {
+            Some(channel) if channel.joined => return,
+            Some(channel) => channel.joined = true,
+            None => self
+                .channels
+                .push(IrcChannel::new(name, IrcChannelType::Channel)),
+        }
+        self.mark_dirty(Dirty::CHANNELS);
+        if self
+            .focus_on_join
+            .as_ref()
+            .is_some_and(|focus| focus.eq_ignore_ascii_case(name))
+        {
+            self.focus_on_join = None;
+            if let Some(index) = self.channels.iter().position(|ch| ch.name == name) {
+                self.select_channel(index);
+            }
+        }
+    }
+
+    /// greys out a channel we were kicked from, whats been said stays readable
+    pub fn leave_channel(&mut self, name: &str) {
+        let Some(channel) = self.get_channel_by_name(name) else {
+            return;
+        };
+        channel.joined = false;
+        channel.users.clear();
+        self.mark_dirty(Dirty::CHANNELS);
+        self.mark_channel_dirty(name, Dirty::USERS);
+    }
+
+    /// gets rid of a channel, the one above it is shown if it was on screen
+    pub fn close_channel(&mut self, name: &str) {
+        let Some(index) = self
+            .channels
+            .iter()
+            .position(|ch| ch.name == name && ch.channel_type != IrcChannelType::System)
+        else {
+            return;
+        };
+        let was_selected = self.channels.remove(index).selected;
+        self.mark_dirty(Dirty::CHANNELS);
+        if was_selected {
+            self.select_channel(index - 1);
+        }
+    }
+
+    /// moves the channel at `index` up or down the list, the system channel stays on top
+    ///
+    /// returns false if it cant go any further
+    pub fn move_channel(&mut self, index: usize, change: isize) -> bool {
+        let Some(target) = index.checked_add_signed(change) else {
+            return false;
+        };
+        let movable = |index: usize| {
+            self.channels
+                .get(index)
+                .is_some_and(|ch| ch.channel_type != IrcChannelType::System)
+        };
+        if !movable(index) || !movable(target) {
+            return false;
+        }
+        let channel = self.channels.remove(index);
+        self.channels.insert(target, channel);
+        self.mark_dirty(Dirty::CHANNELS);
+        true
+    }
 }
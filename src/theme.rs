@@ -0,0 +1,247 @@
+use std::path::Path;
+
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// custom themes go here, one toml file per theme, theyre shared by every profile
+pub const THEMES_DIR: &str = "/3ds/grairc/themes";
+
+/// every color grairc draws with
+///
+/// custom themes only need the colors they want to change, everything else comes from the dark
+/// theme
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct Theme {
+    /// named after the file its loaded from when left out
+    #[serde(default)]
+    pub name: String,
+
+    /// messages, help text and the channel list
+    #[serde(with = "hex")]
+    pub background: Rgb888,
+    #[serde(with = "hex")]
+    pub text: Rgb888,
+    /// joins, parts and other lines without a nick
+    #[serde(with = "hex")]
+    pub event_text: Rgb888,
+    /// messages that mention us
+    #[serde(with = "hex")]
+    pub highlight: Rgb888,
+
+    #[serde(with = "hex")]
+    pub nick_background: Rgb888,
+    #[serde(with = "hex")]
+    pub nick_text: Rgb888,
+
+    #[serde(with = "hex")]
+    pub bar_background: Rgb888,
+    #[serde(with = "hex")]
+    pub bar_text: Rgb888,
+    /// the less important bits of the bar, like the frame time
+    #[serde(with = "hex")]
+    pub bar_dim_text: Rgb888,
+
+    #[serde(with = "hex")]
+    pub user_list_background: Rgb888,
+    #[serde(with = "hex")]
+    pub user_list_text: Rgb888,
+
+    /// the selected channel and settings entries
+    #[serde(with = "hex")]
+    pub selection_background: Rgb888,
+    #[serde(with = "hex")]
+    pub selection_text: Rgb888,
+
+    #[serde(with = "hex")]
+    pub button_background: Rgb888,
+    #[serde(with = "hex")]
+    pub button_text: Rgb888,
+    #[serde(with = "hex")]
+    pub button_border: Rgb888,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Theme {
+            name: "dark".to_string(),
+            background: Rgb888::BLACK,
+            text: Rgb888::WHITE,
+            event_text: Rgb888::new(160, 160, 160),
+            highlight: Rgb888::new(255, 200, 64),
+            nick_background: Rgb888::WHITE,
+            nick_text: Rgb888::BLACK,
+            bar_background: Rgb888::WHITE,
+            bar_text: Rgb888::BLACK,
+            bar_dim_text: Rgb888::new(96, 96, 96),
+            user_list_background: Rgb888::new(24, 24, 24),
+            user_list_text: Rgb888::WHITE,
+            selection_background: Rgb888::WHITE,
+            selection_text: Rgb888::BLACK,
+            button_background: Rgb888::new(24, 24, 24),
+            button_text: Rgb888::WHITE,
+            button_border: Rgb888::WHITE,
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            name: "light".to_string(),
+            background: Rgb888::WHITE,
+            text: Rgb888::BLACK,
+            event_text: Rgb888::new(110, 110, 110),
+            highlight: Rgb888::new(200, 60, 0),
+            nick_background: Rgb888::new(40, 40, 40),
+            nick_text: Rgb888::WHITE,
+            bar_background: Rgb888::new(40, 40, 40),
+            bar_text: Rgb888::WHITE,
+            bar_dim_text: Rgb888::new(170, 170, 170),
+            user_list_background: Rgb888::new(232, 232, 232),
+            user_list_text: Rgb888::BLACK,
+            selection_background: Rgb888::new(40, 40, 40),
+            selection_text: Rgb888::WHITE,
+            button_background: Rgb888::new(232, 232, 232),
+            button_text: Rgb888::BLACK,
+            button_border: Rgb888::new(40, 40, 40),
+        }
+    }
+
+    /// pure black and white plus yellow, for bright sunlight or bad eyes
+    pub fn high_contrast() -> Self {
+        Theme {
+            name: "high contrast".to_string(),
+            background: Rgb888::BLACK,
+            text: Rgb888::WHITE,
+            event_text: Rgb888::CYAN,
+            highlight: Rgb888::YELLOW,
+            nick_background: Rgb888::YELLOW,
+            nick_text: Rgb888::BLACK,
+            bar_background: Rgb888::YELLOW,
+            bar_text: Rgb888::BLACK,
+            bar_dim_text: Rgb888::BLACK,
+            user_list_background: Rgb888::BLACK,
+            user_list_text: Rgb888::YELLOW,
+            selection_background: Rgb888::YELLOW,
+            selection_text: Rgb888::BLACK,
+            button_background: Rgb888::BLACK,
+            button_text: Rgb888::YELLOW,
+            button_border: Rgb888::YELLOW,
+        }
+    }
+
+    /// https://ethanschoonover.com/solarized/ (dark)
+    pub fn solarized() -> Self {
+        let base03 = Rgb888::new(0x00, 0x2b, 0x36);
+        let base02 = Rgb888::new(0x07, 0x36, 0x42);
+        let base01 = Rgb888::new(0x58, 0x6e, 0x75);
+        let base0 = Rgb888::new(0x83, 0x94, 0x96);
+        let base1 = Rgb888::new(0x93, 0xa1, 0xa1);
+        let yellow = Rgb888::new(0xb5, 0x89, 0x00);
+        let blue = Rgb888::new(0x26, 0x8b, 0xd2);
+
+        Theme {
+            name: "solarized".to_string(),
+            background: base03,
+            text: base0,
+            event_text: base01,
+            highlight: yellow,
+            nick_background: blue,
+            nick_text: base03,
+            bar_background: base02,
+            bar_text: base1,
+            bar_dim_text: base01,
+            user_list_background: base02,
+            user_list_text: base0,
+            selection_background: blue,
+            selection_text: base03,
+            button_background: base02,
+            button_text: base1,
+            button_border: base01,
+        }
+    }
+
+    pub fn builtin() -> Vec<Theme> {
+        vec![
+            Theme::dark(),
+            Theme::light(),
+            Theme::high_contrast(),
+            Theme::solarized(),
+        ]
+    }
+
+    /// themes without a name are named after their file
+    pub fn parse(contents: &str, file_name: &str) -> Result<Theme, toml::de::Error> {
+        let mut theme: Theme = toml::from_str(contents)?;
+        if theme.name.is_empty() {
+            theme.name = file_name.to_string();
+        }
+        Ok(theme)
+    }
+
+    /// loads every `.toml` file in `dir`, anything that doesnt parse comes back as an error
+    /// message instead
+    pub fn load_custom(dir: &Path) -> Vec<Result<Theme, String>> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return vec![];
+        };
+
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        paths.sort();
+
+        paths
+            .into_iter()
+            .map(|path| {
+                let file_name = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let contents = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("couldnt read theme {}: {}", path.display(), e))?;
+                Theme::parse(&contents, &file_name)
+                    .map_err(|e| format!("couldnt load theme {}: {}", path.display(), e))
+            })
+            .collect()
+    }
+}
+
+/// colors are written as `#rrggbb` in toml
+mod hex {
+    use embedded_graphics::pixelcolor::Rgb888;
+    use embedded_graphics::prelude::*;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Rgb888, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!(
+            "#{:02x}{:02x}{:02x}",
+            color.r(),
+            color.g(),
+            color.b()
+        ))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rgb888, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        parse(&text)
+            .ok_or_else(|| D::Error::custom(format!("invalid color {:?}, use #rrggbb", text)))
+    }
+
+    fn parse(text: &str) -> Option<Rgb888> {
+        let hex = text.strip_prefix('#')?;
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |range| u8::from_str_radix(&hex[range], 16).ok();
+        Some(Rgb888::new(channel(0..2)?, channel(2..4)?, channel(4..6)?))
+    }
+}
@@ -0,0 +1,58 @@
+use grairc::{
+    irc::types::{IrcChannel, IrcChannelType},
+    state::State,
+};
+
+fn state_with(names: &[&str]) -> State {
+    let mut state = State::default();
+    for name in names {
+        state
+            .channels
+            .push(IrcChannel::new(name, IrcChannelType::Channel));
+    }
+    state
+}
+
+fn names(state: &State) -> Vec<&str> {
+    state.channels.iter().map(|ch| ch.name.as_str()).collect()
+}
+
+#[test]
+fn closing_the_channel_on_screen_shows_the_one_above() {
+    let mut state = state_with(&["#a", "#b", "#c"]);
+    state.select_channel(2);
+    state.close_channel("#b");
+    assert_eq!(names(&state), ["<system>", "#a", "#c"]);
+    assert_eq!(state.current_channel_static().name, "#a");
+
+    // the system channel cant be closed
+    state.close_channel("<system>");
+    assert_eq!(names(&state), ["<system>", "#a", "#c"]);
+
+    state.select_channel(2);
+    state.close_channel("#a");
+    assert_eq!(state.current_channel_static().name, "#c");
+}
+
+#[test]
+fn channels_move_but_the_system_channel_stays_on_top() {
+    let mut state = state_with(&["#a", "#b", "#c"]);
+    assert!(state.move_channel(1, 1));
+    assert_eq!(names(&state), ["<system>", "#b", "#a", "#c"]);
+    assert!(!state.move_channel(1, -1));
+    assert!(!state.move_channel(3, 1));
+    assert!(state.move_channel(3, -2));
+    assert_eq!(names(&state), ["<system>", "#c", "#b", "#a"]);
+}
+
+#[test]
+fn channels_joined_from_the_ui_are_switched_to() {
+    let mut state = state_with(&["#a"]);
+    state.open_channel("#b");
+    assert_eq!(state.current_channel_static().name, "<system>");
+
+    state.focus_on_join = Some("#C".to_string());
+    state.open_channel("#c");
+    assert_eq!(state.current_channel_static().name, "#c");
+    assert_eq!(state.focus_on_join, None);
+}
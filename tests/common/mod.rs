@@ -0,0 +1,239 @@
+//! an in-process irc server that follows a script, for driving `IrcServer` without a network
+
+#![allow(dead_code)]
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io::{Error, ErrorKind, Read, Write},
+    rc::Rc,
+    time::Duration,
+};
+
+use grairc::{
+    config::ChannelConfig,
+    irc::{server::IrcServer, transport::Connector},
+    state::State,
+};
+
+enum Step {
+    /// the client has to send exactly this line next
+    Expect(String),
+    /// sent to the client as soon as every step before it is done
+    Send(String),
+    /// closes the connection once everything before it has been read
+    Disconnect,
+}
+
+#[derive(Default)]
+struct Script {
+    steps: VecDeque<Step>,
+    /// what the client wrote that doesnt end in a newline yet
+    incoming: Vec<u8>,
+    outgoing: VecDeque<u8>,
+    closed: bool,
+    /// the most a single write takes, like a socket with a full send buffer
+    write_limit: Option<usize>,
+    /// every other write would block when theres a limit
+    blocked: bool,
+}
+
+impl Script {
+    /// queues up everything that doesnt have to wait for the client
+    fn advance(&mut self) {
+        loop {
+            match self.steps.front() {
+                Some(Step::Send(line)) => {
+                    self.outgoing.extend(format!("{}\r\n", line).bytes());
+                }
+                Some(Step::Disconnect) => self.closed = true,
+                Some(Step::Expect(_)) | None => return,
+            }
+            self.steps.pop_front();
+        }
+    }
+}
+
+/// one connection to the fake server, built up step by step:
+///
+/// ```ignore
+/// FakeServer::new()
+///     .expect("NICK me")
+///     .send(":irc.example 001 me :Welcome")
+/// ```
+#[derive(Clone, Default)]
+pub struct FakeServer(Rc<RefCell<Script>>);
+
+impl FakeServer {
+    pub fn new() -> Self {
+        FakeServer::default()
+    }
+
+    pub fn expect(self, line: &str) -> Self {
+        self.push(Step::Expect(line.to_string()))
+    }
+
+    pub fn send(self, line: &str) -> Self {
+        self.push(Step::Send(line.to_string()))
+    }
+
+    pub fn disconnect(self) -> Self {
+        self.push(Step::Disconnect)
+    }
+
+    /// takes at most `limit` bytes per write and makes every other write block
+    pub fn slow_writes(self, limit: usize) -> Self {
+        self.0.borrow_mut().write_limit = Some(limit);
+        self
+    }
+
+    /// the usual USER/NICK/001 dance
+    pub fn registration(self, nick: &str) -> Self {
+        self.expect("CAP REQ :multi-prefix")
+            .expect(&format!("USER {} 0 * :{}", nick, nick))
+            .expect(&format!("NICK {}", nick))
+            .expect("CAP END")
+            .send(&format!(
+                ":irc.example 001 {} :Welcome to the fake network {}",
+                nick, nick
+            ))
+    }
+
+    fn push(self, step: Step) -> Self {
+        self.0.borrow_mut().steps.push_back(step);
+        self
+    }
+
+    /// every step ran and the client read everything
+    pub fn finished(&self) -> bool {
+        let script = self.0.borrow();
+        script.steps.is_empty() && script.outgoing.is_empty()
+    }
+
+    pub fn assert_finished(&self) {
+        let script = self.0.borrow();
+        if let Some(Step::Expect(line)) = script.steps.front() {
+            panic!("script still expects {:?}", line);
+        }
+        assert!(script.steps.is_empty(), "script has steps left");
+        assert!(script.outgoing.is_empty(), "client didnt read everything");
+    }
+}
+
+impl Read for FakeServer {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut script = self.0.borrow_mut();
+        script.advance();
+
+        if script.outgoing.is_empty() {
+            if script.closed {
+                return Ok(0);
+            }
+            return Err(ErrorKind::WouldBlock.into());
+        }
+
+        let size = buf.len().min(script.outgoing.len());
+        for (byte, out) in script.outgoing.drain(..size).zip(buf.iter_mut()) {
+            *out = byte;
+        }
+        Ok(size)
+    }
+}
+
+impl Write for FakeServer {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let mut script = self.0.borrow_mut();
+        if script.closed {
+            return Err(ErrorKind::BrokenPipe.into());
+        }
+        let buf = match script.write_limit {
+            Some(limit) => {
+                script.blocked = !script.blocked;
+                if script.blocked {
+                    return Err(ErrorKind::WouldBlock.into());
+                }
+                &buf[..buf.len().min(limit)]
+            }
+            None => buf,
+        };
+
+        script.incoming.extend_from_slice(buf);
+        while let Some(end) = script.incoming.windows(2).position(|w| w == b"\r\n") {
+            let line: Vec<u8> = script.incoming.drain(..end + 2).collect();
+            let line = String::from_utf8(line[..end].to_vec()).expect("client sent invalid utf-8");
+
+            match script.steps.pop_front() {
+                Some(Step::Expect(expected)) => assert_eq!(line, expected),
+                _ => panic!(
+                    "client sent {:?} but the script wasnt expecting anything",
+                    line
+                ),
+            }
+            script.advance();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// hands out the servers in order, one per (re)connect
+pub fn connector(servers: &[FakeServer]) -> Connector<FakeServer> {
+    let mut servers: VecDeque<FakeServer> = servers.iter().cloned().collect();
+    Box::new(move |_hostname, _port| {
+        servers
+            .pop_front()
+            .ok_or_else(|| ErrorKind::ConnectionRefused.into())
+    })
+}
+
+/// connects to the first server and registers as `nick` with `channels` to autojoin
+pub fn connect(servers: &[FakeServer], nick: &str, channels: &[&str]) -> IrcServer<FakeServer> {
+    let mut irc = IrcServer::with_connector("irc.example", 6667, connector(servers))
+        .expect("couldnt connect");
+    let channels: Vec<ChannelConfig> = channels.iter().map(|c| ChannelConfig::new(c)).collect();
+    // scripts send lines back to back, theres a separate test for flood control
+    irc.set_flood_control(100, Duration::ZERO);
+    irc.irc_ident(nick, &channels).expect("couldnt identify");
+    irc
+}
+
+/// runs the handler until every server has gone through its script
+pub fn pump(irc: &mut IrcServer<FakeServer>, state: &mut State, servers: &[FakeServer]) {
+    for _ in 0..1000 {
+        if servers.iter().all(FakeServer::finished) {
+            break;
+        }
+        irc.handler(state);
+    }
+    // one more round for anything sent by the last step
+    irc.handler(state);
+
+    for server in servers {
+        server.assert_finished();
+    }
+}
+
+pub fn channel_users(state: &State, name: &str) -> Vec<String> {
+    state
+        .channels
+        .iter()
+        .find(|c| c.name == name)
+        .unwrap_or_else(|| panic!("no channel named {}", name))
+        .users
+        .clone()
+}
+
+pub fn messages(state: &State, name: &str) -> Vec<String> {
+    state
+        .channels
+        .iter()
+        .find(|c| c.name == name)
+        .unwrap_or_else(|| panic!("no channel named {}", name))
+        .messages
+        .iter()
+        .map(|m| m.content.clone())
+        .collect()
+}
@@ -0,0 +1,159 @@
+use std::time::{Duration, Instant};
+
+use grairc::{
+    completion::{CompletionKind, completions},
+    history::{HISTORY_LENGTH, History},
+    irc::{
+        command::{Command, Input},
+        types::{IrcChannel, IrcChannelType},
+    },
+    state::State,
+};
+
+fn command(text: &str) -> Command {
+    match Input::parse(text) {
+        Ok(Input::Command(command)) => command,
+        other => panic!("{} parsed as {:?}", text, other),
+    }
+}
+
+#[test]
+fn plain_text_is_a_message() {
+    assert_eq!(
+        Input::parse("hello /there"),
+        Ok(Input::Message("hello /there".to_string()))
+    );
+    assert_eq!(
+        Input::parse("//shrug"),
+        Ok(Input::Message("/shrug".to_string()))
+    );
+}
+
+#[test]
+fn commands_become_irc_lines() {
+    let line = |text: &str, channel: Option<&str>| command(text).line(channel);
+
+    assert_eq!(
+        line("/join #rust hunter2", None),
+        Ok("JOIN #rust hunter2".to_string())
+    );
+    assert_eq!(line("/JOIN #rust", None), Ok("JOIN #rust".to_string()));
+    assert_eq!(line("/part", Some("#rust")), Ok("PART #rust".to_string()));
+    assert_eq!(
+        line("/part see you", Some("#rust")),
+        Ok("PART #rust :see you".to_string())
+    );
+    assert_eq!(
+        line("/part #other bye", Some("#rust")),
+        Ok("PART #other :bye".to_string())
+    );
+    assert_eq!(
+        line("/topic new topic here", Some("#rust")),
+        Ok("TOPIC #rust :new topic here".to_string())
+    );
+    assert_eq!(line("/nick bob", None), Ok("NICK bob".to_string()));
+    assert_eq!(
+        line("/quote MODE #rust +i", None),
+        Ok("MODE #rust +i".to_string())
+    );
+    assert!(line("/topic", None).is_err());
+}
+
+#[test]
+fn msg_keeps_the_whole_message() {
+    assert_eq!(
+        command("/msg alice hi there"),
+        Command::Msg {
+            target: "alice".to_string(),
+            text: "hi there".to_string(),
+        }
+    );
+}
+
+#[test]
+fn bad_commands_explain_themselves() {
+    assert_eq!(
+        Input::parse("/join"),
+        Err("usage: /join #channel [key]".to_string())
+    );
+    assert_eq!(
+        Input::parse("/msg alice"),
+        Err("usage: /msg nick message".to_string())
+    );
+    assert!(
+        Input::parse("/dance")
+            .unwrap_err()
+            .starts_with("unknown command /dance")
+    );
+}
+
+#[test]
+fn recent_speakers_are_completed_first() {
+    let now = Instant::now();
+    let mut channel = IrcChannel {
+        selected: true,
+        users: ["@alice", "bob", "me", "+carol", "dave"]
+            .iter()
+            .map(|user| user.to_string())
+            .collect(),
+        ..IrcChannel::new("#grairc", IrcChannelType::Channel)
+    };
+    channel
+        .last_spoke
+        .insert("dave".to_string(), now - Duration::from_secs(60));
+    channel.last_spoke.insert("carol".to_string(), now);
+    let mut state = State::default();
+    state.channels[0].selected = false;
+    state.channels.push(channel);
+    state.nickname = Some("me".to_string());
+
+    let nicks: Vec<String> = completions(&state, CompletionKind::Nicks)
+        .into_iter()
+        .map(|completion| completion.text)
+        .collect();
+    assert_eq!(nicks, ["carol: ", "dave: ", "alice: ", "bob: "]);
+
+    let channels: Vec<String> = completions(&state, CompletionKind::Channels)
+        .into_iter()
+        .map(|completion| completion.text)
+        .collect();
+    assert_eq!(channels, ["#grairc "]);
+}
+
+#[test]
+fn history_keeps_the_newest_lines_once() {
+    let mut history = History::default();
+    history.push("#rust", "first");
+    history.push("#rust", "second");
+    history.push("#RUST", "first");
+    history.push("#rust", "   ");
+    history.push("#other", "elsewhere");
+
+    assert_eq!(history.lines("#rust"), ["first", "second"]);
+    assert_eq!(history.lines("#other"), ["elsewhere"]);
+    assert!(history.raw.is_empty());
+
+    for i in 0..HISTORY_LENGTH + 10 {
+        history.push_raw(&format!("PING {}", i));
+    }
+    assert_eq!(history.raw.len(), HISTORY_LENGTH);
+    assert_eq!(history.raw[0], format!("PING {}", HISTORY_LENGTH + 9));
+}
+
+#[test]
+fn history_is_offered_in_the_picker() {
+    let mut state = State::default();
+    state.history.push("<system>", "/join #rust");
+    state.history.push_raw("WHOIS alice");
+
+    let sent: Vec<String> = completions(&state, CompletionKind::History)
+        .into_iter()
+        .map(|completion| completion.text)
+        .collect();
+    assert_eq!(sent, ["/join #rust"]);
+    let raw: Vec<String> = completions(&state, CompletionKind::RawHistory)
+        .into_iter()
+        .map(|completion| completion.text)
+        .collect();
+    assert_eq!(raw, ["WHOIS alice"]);
+}
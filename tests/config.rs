@@ -0,0 +1,247 @@
+use std::time::Duration;
+
+use grairc::config::{
+    CONFIG_VERSION, ChannelConfig, ChannelOption, Config, ConfigError, DEFAULT_NICK_WIDTH, Setting,
+    validate_channels, validate_hostname, validate_key, validate_nick_width, validate_nickname,
+    validate_ping_interval, validate_ping_timeout, validate_port, validate_send_burst,
+    validate_send_interval, validate_yes_no,
+};
+
+#[test]
+fn nicknames() {
+    assert!(validate_nickname("grairc").is_ok());
+    assert!(validate_nickname("[away]_").is_ok());
+    for nick in ["", "  ", "1up", "-dash", "has space", "a,b", "me!", "#chan"] {
+        assert!(validate_nickname(nick).is_err(), "{:?} was accepted", nick);
+    }
+}
+
+#[test]
+fn hostnames_and_ports() {
+    assert!(validate_hostname("irc.libera.chat").is_ok());
+    assert!(validate_hostname("irc.libera.chat:6697").is_err());
+    assert!(validate_hostname("").is_err());
+
+    assert!(validate_port("6697").is_ok());
+    for port in ["0", "65536", "-1", "irc", ""] {
+        assert!(validate_port(port).is_err(), "{:?} was accepted", port);
+    }
+}
+
+#[test]
+fn channels() {
+    assert!(validate_channels("#grairc\n\n&local\n").is_ok());
+    assert!(validate_channels("").is_ok());
+    assert!(validate_channels("grairc").is_err());
+    assert!(validate_channels("#a,#b").is_err());
+    assert!(validate_channels("#secret key").is_ok());
+    assert!(validate_channels("#secret key extra").is_err());
+}
+
+#[test]
+fn flood_control() {
+    assert!(validate_send_burst("5").is_ok());
+    assert!(validate_send_interval("0").is_ok());
+    assert!(validate_send_interval("2000").is_ok());
+    for burst in ["0", "21", "-1", "lots", ""] {
+        assert!(
+            validate_send_burst(burst).is_err(),
+            "{:?} was accepted",
+            burst
+        );
+    }
+    for interval in ["10001", "-1", "2s", ""] {
+        assert!(
+            validate_send_interval(interval).is_err(),
+            "{:?} was accepted",
+            interval
+        );
+    }
+
+    let mut config = Config::default();
+    Setting::SendBurst.set(&mut config, " 3 ");
+    Setting::SendInterval.set(&mut config, "500");
+    assert_eq!(config.send_burst, 3);
+    assert_eq!(config.send_interval(), Duration::from_millis(500));
+}
+
+#[test]
+fn ping_timing() {
+    assert!(validate_ping_interval("60").is_ok());
+    assert!(validate_ping_timeout("240").is_ok());
+    for interval in ["4", "601", "-1", "1m", ""] {
+        assert!(
+            validate_ping_interval(interval).is_err(),
+            "{:?} was accepted",
+            interval
+        );
+    }
+    for timeout in ["29", "3601", "-1", ""] {
+        assert!(
+            validate_ping_timeout(timeout).is_err(),
+            "{:?} was accepted",
+            timeout
+        );
+    }
+
+    let mut config = Config::default();
+    Setting::PingInterval.set(&mut config, "30");
+    Setting::PingTimeout.set(&mut config, " 120 ");
+    assert_eq!(config.ping_interval(), Duration::from_secs(30));
+    assert_eq!(config.ping_timeout(), Duration::from_secs(120));
+}
+
+#[test]
+fn remembering_channels_is_a_toggle() {
+    assert!(validate_yes_no(" Yes ").is_ok());
+    assert!(validate_yes_no("no").is_ok());
+    assert!(validate_yes_no("maybe").is_err());
+
+    let mut config = Config::default();
+    assert!(config.remember_channels);
+    assert!(Setting::RememberChannels.toggle(&mut config));
+    assert_eq!(Setting::RememberChannels.value(&config), "no");
+    Setting::RememberChannels.set(&mut config, "yes");
+    assert!(config.remember_channels);
+
+    assert!(config.smart_filter);
+    assert!(Setting::SmartFilter.toggle(&mut config));
+    assert_eq!(Setting::SmartFilter.value(&config), "no");
+    Setting::SmartFilter.set(&mut config, "yes");
+
+    assert!(!Setting::Nickname.toggle(&mut config));
+    assert_eq!(config, Config::default());
+}
+
+#[test]
+fn settings_start_from_their_current_value() {
+    let mut config = Config::default();
+    for setting in Setting::ALL {
+        let prompt = setting.prompt(&config);
+        assert!(prompt.cancellable);
+        setting.set(&mut config, &prompt.initial_text);
+    }
+    assert_eq!(config, Config::default());
+
+    Setting::Channels.set(&mut config, " #grairc \n\n#rust hunter2");
+    assert_eq!(Setting::Channels.value(&config), "#grairc #rust");
+    assert_eq!(config.channels[1].key.as_deref(), Some("hunter2"));
+    assert_eq!(
+        Setting::Channels.prompt(&config).initial_text,
+        "#grairc\n#rust hunter2"
+    );
+}
+
+#[test]
+fn editing_the_channel_list_keeps_channel_settings() {
+    let mut config = Config::default();
+    Setting::Channels.set(&mut config, "#grairc\n#rust");
+    config.channel_mut("#RUST").unwrap().hide_joins_parts = true;
+
+    Setting::Channels.set(&mut config, "#rust\n#new");
+    assert_eq!(config.channels.len(), 2);
+    assert!(config.channel("#rust").unwrap().hide_joins_parts);
+    assert_eq!(config.channel("#new"), Some(&ChannelConfig::new("#new")));
+}
+
+#[test]
+fn channel_options() {
+    let mut channel = ChannelConfig::new("#grairc");
+    assert!(ChannelOption::Log.toggle(&mut channel));
+    assert!(!channel.log);
+    assert!(!ChannelOption::Key.toggle(&mut channel));
+
+    ChannelOption::NickWidth.set(&mut channel, "8");
+    assert_eq!(channel.nick_width(), 8);
+    ChannelOption::NickWidth.set(&mut channel, &DEFAULT_NICK_WIDTH.to_string());
+    assert_eq!(channel.nick_width, None);
+
+    ChannelOption::Key.set(&mut channel, "secret");
+    assert_eq!(channel.key.as_deref(), Some("secret"));
+    ChannelOption::Key.set(&mut channel, "");
+    assert_eq!(channel.key, None);
+
+    assert!(validate_nick_width("0").is_err());
+    assert!(validate_nick_width("31").is_err());
+    assert!(validate_key("has space").is_err());
+}
+
+#[test]
+fn unversioned_configs_are_migrated() {
+    let config = Config::parse(
+        r##"
+nickname = "grairc"
+server_hostname = "irc.example"
+server_port = 6697
+autojoin_channels = ["#grairc"]
+"##,
+    )
+    .unwrap();
+    assert_eq!(config.config_version, CONFIG_VERSION);
+    assert_eq!(config.server_port, 6697);
+    assert_eq!(config.theme, "dark");
+    assert_eq!(config.channels, [ChannelConfig::new("#grairc")]);
+}
+
+#[test]
+fn new_fields_have_defaults() {
+    let config = Config::parse(&format!(
+        "config_version = {}\nnickname = \"grairc\"\nserver_hostname = \"irc.example\"\n",
+        CONFIG_VERSION
+    ))
+    .unwrap();
+    assert_eq!(config.server_port, 6667);
+    assert!(config.channels.is_empty());
+    assert_eq!(config.send_burst, 5);
+    assert_eq!(config.send_interval(), Duration::from_secs(2));
+}
+
+#[test]
+fn parse_errors_have_a_position() {
+    let error = Config::parse(&format!(
+        "config_version = {}\nnickname = \"grairc\"\nserver_port = \"six\"\n",
+        CONFIG_VERSION
+    ))
+    .unwrap_err();
+    let ConfigError::Invalid { position, .. } = &error else {
+        panic!("expected a parse error, got {:?}", error);
+    };
+    assert_eq!(*position, Some((3, 15)));
+    assert!(error.to_string().starts_with("line 3, column 15: "));
+
+    let error = Config::parse("nickname = \"unterminated\n").unwrap_err();
+    assert!(matches!(
+        error,
+        ConfigError::Invalid {
+            position: Some((1, _)),
+            ..
+        }
+    ));
+}
+
+#[test]
+fn configs_from_newer_versions_are_refused() {
+    let error = Config::parse(&format!(
+        "config_version = {}\nnickname = \"grairc\"\nserver_hostname = \"irc.example\"\n",
+        CONFIG_VERSION + 1
+    ))
+    .unwrap_err();
+    assert!(matches!(error, ConfigError::TooNew(version) if version == CONFIG_VERSION + 1));
+}
+
+#[test]
+fn saved_configs_load_again() {
+    let config = Config::default();
+    let contents = toml::to_string_pretty(&config).unwrap();
+    assert_eq!(Config::parse(&contents).unwrap(), config);
+}
+
+#[test]
+fn missing_fields_have_no_position() {
+    let error = Config::parse(&format!(
+        "config_version = {}\nnickname = \"grairc\"\n",
+        CONFIG_VERSION
+    ))
+    .unwrap_err();
+    assert_eq!(error.to_string(), "missing field `server_hostname`");
+}
@@ -0,0 +1,129 @@
+use grairc::directory::{Directory, DirectoryEntry, DirectorySort};
+
+fn directory(entries: &[(&str, usize, &str)]) -> Directory {
+    let mut directory = Directory::default();
+    directory.start();
+    for (name, users, topic) in entries {
+        directory.push(DirectoryEntry {
+            name: name.to_string(),
+            users: *users,
+            topic: topic.to_string(),
+        });
+    }
+    directory.finish();
+    directory
+}
+
+fn names(directory: &Directory) -> Vec<&str> {
+    directory
+        .visible()
+        .map(|(_, entry)| entry.name.as_str())
+        .collect()
+}
+
+#[test]
+fn sorts_by_users_then_name() {
+    let mut directory = directory(&[
+        ("#rust", 400, "the rust language"),
+        ("#grairc", 3, "irc on the 3ds"),
+        ("#Alpha", 3, ""),
+        ("#3ds", 120, "homebrew talk"),
+    ]);
+    assert_eq!(names(&directory), ["#rust", "#3ds", "#Alpha", "#grairc"]);
+
+    directory.set_sort(DirectorySort::Name);
+    assert_eq!(names(&directory), ["#3ds", "#Alpha", "#grairc", "#rust"]);
+}
+
+#[test]
+fn filters_names_and_topics_ignoring_case() {
+    let mut directory = directory(&[
+        ("#rust", 400, "the rust language"),
+        ("#grairc", 3, "IRC on the 3DS"),
+        ("#3ds", 120, "homebrew talk"),
+    ]);
+    directory.set_filter(" 3ds ");
+    assert_eq!(names(&directory), ["#3ds", "#grairc"]);
+
+    directory.set_filter("nothing like this");
+    assert!(names(&directory).is_empty());
+    directory.move_selection(1);
+    assert_eq!(directory.selected(), None);
+}
+
+#[test]
+fn selection_follows_whats_shown() {
+    let mut directory = directory(&[("#b", 1, ""), ("#a", 2, ""), ("#c", 3, "")]);
+    directory.move_selection(1);
+    assert_eq!(directory.selected().as_deref(), Some("#c"));
+    directory.move_selection(5);
+    assert_eq!(directory.selected().as_deref(), Some("#b"));
+    directory.move_selection(-1);
+    assert_eq!(directory.selected().as_deref(), Some("#a"));
+
+    // the highlight stays on its channel when the order changes
+    directory.set_sort(DirectorySort::Name);
+    assert_eq!(directory.selected().as_deref(), Some("#a"));
+    directory.move_selection(1);
+    assert_eq!(directory.selected().as_deref(), Some("#b"));
+
+    directory.set_filter("#c");
+    assert_eq!(directory.selected(), None);
+}
+
+#[test]
+fn selection_stays_put_while_the_list_comes_in() {
+    let mut directory = Directory::default();
+    directory.start();
+    directory.push(DirectoryEntry {
+        name: "#small".to_string(),
+        users: 2,
+        topic: String::new(),
+    });
+    directory.select(0);
+    assert!(directory.is_selected(0));
+
+    // a busier channel goes below it until the list is done, then sorts in above it
+    directory.push(DirectoryEntry {
+        name: "#big".to_string(),
+        users: 500,
+        topic: String::new(),
+    });
+    assert_eq!(names(&directory), ["#small", "#big"]);
+    assert_eq!(directory.selected_position(), Some(0));
+    directory.finish();
+    assert_eq!(names(&directory), ["#big", "#small"]);
+    assert!(directory.is_selected(0));
+    assert!(!directory.is_selected(1));
+    assert_eq!(directory.selected().as_deref(), Some("#small"));
+    assert_eq!(directory.selected_position(), Some(1));
+}
+
+#[test]
+fn filtering_applies_to_entries_as_they_come_in() {
+    let mut directory = Directory::default();
+    directory.set_filter("RUST");
+    directory.start();
+    for (name, users) in [
+        ("#rust", 10),
+        ("#python", 50),
+        ("#rust-gamedev", 30),
+        ("#Rustaceans", 30),
+    ] {
+        directory.push(DirectoryEntry {
+            name: name.to_string(),
+            users,
+            topic: String::new(),
+        });
+    }
+    assert_eq!(names(&directory), ["#rust", "#rust-gamedev", "#Rustaceans"]);
+    directory.finish();
+    assert_eq!(names(&directory), ["#rust-gamedev", "#Rustaceans", "#rust"]);
+    assert_eq!(directory.filter, "rust");
+
+    directory.set_filter("");
+    assert_eq!(
+        names(&directory),
+        ["#python", "#rust-gamedev", "#Rustaceans", "#rust"]
+    );
+}
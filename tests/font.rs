@@ -0,0 +1,41 @@
+use grairc::gfx::font::{char_width, strip_formatting, text_width};
+
+#[test]
+fn ascii_uses_the_main_font() {
+    assert_eq!(text_width("hello"), 30);
+    assert_eq!(char_width(' '), 6);
+}
+
+#[test]
+fn other_scripts_have_glyphs() {
+    for c in ['é', 'Ł', 'д', 'λ', '你', 'こ'] {
+        assert!(char_width(c) > 0, "{} has no width", c);
+    }
+}
+
+#[test]
+fn missing_glyphs_still_take_up_space() {
+    // drawn as a replacement box instead of disappearing
+    assert_eq!(char_width('😀'), 6);
+    assert_eq!(char_width('\u{2}'), 6);
+}
+
+#[test]
+fn formatting_codes_are_stripped() {
+    assert_eq!(strip_formatting("plain text"), "plain text");
+    assert_eq!(
+        strip_formatting("\x02bold\x0F \x1Ditalic\x1D \x1Funder\x1F \x16rev\x16"),
+        "bold italic under rev"
+    );
+}
+
+#[test]
+fn color_codes_take_their_numbers_with_them() {
+    assert_eq!(strip_formatting("\x034red\x03"), "red");
+    assert_eq!(strip_formatting("\x0304,12red on blue"), "red on blue");
+    // only two digits belong to the color
+    assert_eq!(strip_formatting("\x03123 apples"), "3 apples");
+    // a comma thats not followed by a background is part of the text
+    assert_eq!(strip_formatting("\x034, then"), ", then");
+    assert_eq!(strip_formatting("\x03,5 stays"), ",5 stays");
+}
@@ -0,0 +1,1004 @@
+mod common;
+
+use std::{io::ErrorKind, time::Duration};
+
+use common::{FakeServer, channel_users, connect, messages, pump};
+use grairc::{
+    config::{ChannelConfig, Config},
+    irc::types::Membership,
+    state::State,
+};
+
+#[test]
+fn registers_and_joins_autojoin_channels() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(":me!me@host JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me alice bob");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert!(irc.registered);
+    assert_eq!(state.nickname.as_deref(), Some("me"));
+    assert!(messages(&state, "<system>").contains(&"Welcome to the fake network me".to_string()));
+    assert_eq!(channel_users(&state, "#grairc"), ["me", "alice", "bob"]);
+}
+
+#[test]
+fn names_keep_every_prefix() {
+    let server = FakeServer::new()
+        .registration("me")
+        .send(":irc.example CAP * ACK :multi-prefix")
+        .expect("JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me @+alice bob")
+        .send(":bob!bob@host MODE #grairc -o alice");
+    let servers = [server];
+    let mut state = unfiltered("#grairc");
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(channel_users(&state, "#grairc"), ["me", "+alice", "bob"]);
+    assert!(
+        messages(&state, "<system>")
+            .iter()
+            .all(|m| !m.contains("multi-prefix"))
+    );
+}
+
+#[test]
+fn channels_are_only_joined_after_the_welcome() {
+    let server = FakeServer::new()
+        .expect("CAP REQ :multi-prefix")
+        .expect("USER me 0 * :me")
+        .expect("NICK me")
+        .expect("CAP END");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert!(!irc.registered);
+    assert_eq!(state.channels.len(), 1);
+}
+
+#[test]
+fn names_replies_replace_the_user_list() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me alice")
+        .send(":irc.example 353 me = #grairc :me @op +voiced");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(channel_users(&state, "#grairc"), ["me", "@op", "+voiced"]);
+}
+
+#[test]
+fn joins_and_parts_update_the_user_list() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me")
+        .send(":alice!alice@host JOIN #grairc")
+        .send(":bob!bob@host JOIN :#grairc")
+        .send(":alice!alice@host PART #grairc :bye");
+    let servers = [server];
+    let mut state = unfiltered("#grairc");
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(channel_users(&state, "#grairc"), ["me", "bob"]);
+    assert_eq!(messages(&state, "#grairc"), ["2 joined, alice left"]);
+}
+
+/// a state with the smart filter turned off for `channel`
+fn unfiltered(channel: &str) -> State {
+    let channel = ChannelConfig {
+        smart_filter: false,
+        ..ChannelConfig::new(channel)
+    };
+    State {
+        config: Some(Config {
+            channels: vec![channel],
+            ..Config::default()
+        }),
+        ..State::default()
+    }
+}
+
+#[test]
+fn membership_runs_are_summarized() {
+    let join = |nick: &str| Membership::Join(nick.to_string());
+    let part = |nick: &str| Membership::Part(nick.to_string());
+
+    assert_eq!(Membership::summarize(&[join("alice")]), "-> alice joined");
+    assert_eq!(
+        Membership::summarize(&[Membership::Quit {
+            nick: "bob".to_string(),
+            reason: "ping timeout".to_string(),
+        }]),
+        "<- bob quit (ping timeout)"
+    );
+    assert_eq!(
+        Membership::summarize(&[
+            join("a"),
+            join("b"),
+            join("c"),
+            part("d"),
+            part("e"),
+            Membership::Nick {
+                old: "alice".to_string(),
+                new: "bob".to_string(),
+            },
+        ]),
+        "3 joined, 2 left, alice is now bob"
+    );
+}
+
+#[test]
+fn smart_filter_only_shows_people_who_spoke() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me alice bob")
+        .send(":alice!alice@host PRIVMSG #grairc :brb")
+        .send(":bob!bob@host QUIT :ping timeout")
+        .send(":alice!alice@host QUIT :restarting")
+        .send(":carol!carol@host JOIN #grairc");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(channel_users(&state, "#grairc"), ["me", "carol"]);
+    assert_eq!(
+        messages(&state, "#grairc"),
+        ["brb", "<- alice quit (restarting)"]
+    );
+}
+
+#[test]
+fn channels_without_settings_follow_the_global_smart_filter() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me bob")
+        .send(":bob!bob@host QUIT :ping timeout");
+    let servers = [server];
+    let mut state = State {
+        config: Some(Config {
+            channels: vec![],
+            smart_filter: false,
+            ..Config::default()
+        }),
+        ..State::default()
+    };
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(messages(&state, "#grairc"), ["<- bob quit (ping timeout)"]);
+}
+
+#[test]
+fn quits_inside_privmsgs_are_just_text() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me alice bob")
+        .send(":bob!bob@host PRIVMSG #grairc :x :alice!alice@host QUIT :bye");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(channel_users(&state, "#grairc"), ["me", "alice", "bob"]);
+    assert_eq!(
+        messages(&state, "#grairc"),
+        ["x :alice!alice@host QUIT :bye"]
+    );
+}
+
+#[test]
+fn nick_changes_rename_users_everywhere() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #a")
+        .expect("JOIN #b")
+        .send(":irc.example 353 me = #a :me @alice")
+        .send(":irc.example 353 me = #b :me alice")
+        .send(":alice!alice@host NICK :bob");
+    let servers = [server];
+    let mut state = unfiltered("#a");
+    let mut irc = connect(&servers, "me", &["#a", "#b"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(channel_users(&state, "#a"), ["me", "@bob"]);
+    assert_eq!(channel_users(&state, "#b"), ["me", "bob"]);
+    assert_eq!(messages(&state, "#a"), ["alice is now bob"]);
+    assert!(messages(&state, "#b").is_empty());
+}
+
+#[test]
+fn privmsgs_end_up_in_their_channel() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me alice")
+        .send(":alice!alice@host PRIVMSG #grairc :hello there")
+        .expect("PRIVMSG #grairc :hi alice");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+
+    for _ in 0..10 {
+        irc.handler(&mut state);
+    }
+    irc.irc_send("hi alice", "#grairc", &mut state).unwrap();
+    pump(&mut irc, &mut state, &servers);
+
+    let channel = state.channels.iter().find(|c| c.name == "#grairc").unwrap();
+    let lines: Vec<(Option<&str>, &str)> = channel
+        .messages
+        .iter()
+        .map(|m| (m.nick.as_deref(), m.content.as_str()))
+        .collect();
+    assert_eq!(
+        lines,
+        [(Some("alice"), "hello there"), (Some("me"), "hi alice")]
+    );
+}
+
+#[test]
+fn numerics_inside_privmsgs_are_just_text() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me alice")
+        .send(":alice!alice@host PRIVMSG #grairc :lol :srv 001 evil :hi")
+        .send(":alice!alice@host PRIVMSG #grairc :lol :srv 433 me me :taken");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert!(irc.registered);
+    assert_eq!(state.nickname.as_deref(), Some("me"));
+    assert_eq!(
+        messages(&state, "#grairc"),
+        ["lol :srv 001 evil :hi", "lol :srv 433 me me :taken"]
+    );
+}
+
+#[test]
+fn answers_pings() {
+    let server = FakeServer::new()
+        .registration("me")
+        .send("PING :irc.example")
+        .expect("PONG :irc.example");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+
+    pump(&mut irc, &mut state, &servers);
+}
+
+#[test]
+fn pongs_echo_the_exact_token() {
+    // the fake server only sees lines ending in crlf, so these also check the line ending
+    let server = FakeServer::new()
+        .registration("me")
+        .send("PING :PINGPONG-PING")
+        .expect("PONG :PINGPONG-PING")
+        .send("PING 1234567890")
+        .expect("PONG :1234567890")
+        .send(":irc.example PING :irc.example")
+        .expect("PONG :irc.example")
+        .send("PING :two words")
+        .expect("PONG :two words");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    // pings dont show up anywhere
+    assert_eq!(
+        messages(&state, "<system>"),
+        ["Welcome to the fake network me"]
+    );
+}
+
+#[test]
+fn handles_lines_split_across_reads() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(&format!(
+            ":irc.example 353 me = #grairc :me {}",
+            "a ".repeat(300)
+        ));
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(channel_users(&state, "#grairc").len(), 301);
+}
+
+#[test]
+fn reconnects_and_rejoins_after_a_disconnect() {
+    let first = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me")
+        // joined by hand, not in the autojoin list
+        .send(":irc.example 353 me = #other :me")
+        .disconnect();
+    let second = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .expect("JOIN #other")
+        .send(":irc.example 353 me = #other :me alice");
+    let servers = [first, second];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert!(irc.is_connected());
+    assert!(irc.registered);
+    assert!(
+        messages(&state, "<system>")
+            .iter()
+            .any(|m| m.starts_with("disconnected from the server"))
+    );
+    assert_eq!(channel_users(&state, "#other"), ["me", "alice"]);
+}
+
+#[test]
+fn keeps_trying_to_reconnect_when_the_server_is_gone() {
+    let server = FakeServer::new().registration("me").disconnect();
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+
+    pump(&mut irc, &mut state, &servers);
+    irc.handler(&mut state);
+
+    assert!(!irc.is_connected());
+    assert!(
+        messages(&state, "<system>")
+            .iter()
+            .any(|m| m.starts_with("reconnecting failed"))
+    );
+}
+
+#[test]
+fn appends_a_number_when_the_nick_is_taken() {
+    let server = FakeServer::new()
+        .expect("CAP REQ :multi-prefix")
+        .expect("USER me 0 * :me")
+        .expect("NICK me")
+        .expect("CAP END")
+        .send(":irc.example 433 * me :Nickname is already in use")
+        .expect("NICK me1")
+        .send(":irc.example 433 * me1 :Nickname is already in use")
+        .expect("NICK me2")
+        .send(":irc.example 001 me2 :Welcome to the fake network me2")
+        .expect("JOIN #grairc");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(irc.nick, "me2");
+    assert_eq!(state.nickname.as_deref(), Some("me2"));
+}
+
+#[test]
+fn nick_collisions_after_registration_dont_change_the_nick() {
+    let server = FakeServer::new()
+        .registration("me")
+        .send(":irc.example 433 me taken :Nickname is already in use");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(irc.nick, "me");
+    assert!(messages(&state, "<system>").contains(&"taken is already in use".to_string()));
+}
+
+#[test]
+fn nick_changes_once_the_server_accepts_them() {
+    let server = FakeServer::new().registration("me");
+    let servers = [server.clone()];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+    pump(&mut irc, &mut state, &servers);
+
+    server
+        .clone()
+        .expect("NICK you")
+        .send(":me!me@host NICK :you");
+    irc.set_nick("you").unwrap();
+    assert_eq!(irc.nick, "me");
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(irc.nick, "you");
+    assert_eq!(state.nickname.as_deref(), Some("you"));
+}
+
+#[test]
+fn nick_changes_inside_privmsgs_are_just_text() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me alice")
+        .send(":alice!alice@host PRIVMSG #grairc :hey :me!me@host NICK pwned");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(irc.nick, "me");
+    assert_eq!(state.nickname.as_deref(), Some("me"));
+    assert_eq!(messages(&state, "#grairc"), ["hey :me!me@host NICK pwned"]);
+}
+
+#[test]
+fn new_autojoin_channels_are_joined_right_away() {
+    let server = FakeServer::new().registration("me").expect("JOIN #grairc");
+    let servers = [server.clone()];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+    pump(&mut irc, &mut state, &servers);
+
+    server.clone().expect("JOIN #rust");
+    irc.set_channels(&[ChannelConfig::new("#grairc"), ChannelConfig::new("#rust")])
+        .unwrap();
+    pump(&mut irc, &mut state, &servers);
+}
+
+#[test]
+fn switching_servers_reconnects_and_closes_the_old_channels() {
+    let first = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me alice");
+    let second = FakeServer::new();
+    let servers = [first.clone(), second.clone()];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+    pump(&mut irc, &mut state, &servers);
+
+    first.clone().expect("QUIT :changing servers");
+    second.clone().registration("you");
+    irc.set_server("irc.other.example", 6697, &mut state);
+    irc.set_nick("you").unwrap();
+    irc.set_channels(&[]).unwrap();
+    pump(&mut irc, &mut state, &servers);
+
+    assert!(irc.registered);
+    assert_eq!(state.nickname.as_deref(), Some("you"));
+    assert_eq!(state.channels.len(), 1);
+}
+
+#[test]
+fn channel_keys_are_sent_with_the_join() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #secret hunter2")
+        .expect("JOIN #open");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+    let secret = ChannelConfig {
+        key: Some("hunter2".to_string()),
+        ..ChannelConfig::new("#secret")
+    };
+    irc.set_channels(&[secret, ChannelConfig::new("#open")])
+        .unwrap();
+
+    pump(&mut irc, &mut state, &servers);
+}
+
+#[test]
+fn joins_and_parts_can_be_hidden_per_channel() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #quiet")
+        .send(":irc.example 353 me = #quiet :me")
+        .send(":alice!alice@host JOIN #quiet")
+        .send(":bob!bob@host JOIN #quiet")
+        .send(":alice!alice@host PART #quiet :bye");
+    let servers = [server];
+    let quiet = ChannelConfig {
+        hide_joins_parts: true,
+        ..ChannelConfig::new("#Quiet")
+    };
+    let mut state = State {
+        config: Some(Config {
+            channels: vec![quiet],
+            ..Config::default()
+        }),
+        ..State::default()
+    };
+    let mut irc = connect(&servers, "me", &["#quiet"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(channel_users(&state, "#quiet"), ["me", "bob"]);
+    assert!(messages(&state, "#quiet").is_empty());
+}
+
+#[test]
+fn long_messages_are_split_to_fit_with_our_prefix() {
+    // what everyone else sees in front of our text, known after the JOIN echo
+    let budget = 510 - ":me!~me@host.example PRIVMSG #grairc :".len();
+    let long = "a".repeat(budget + 20);
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(":me!~me@host.example JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me")
+        .expect(&format!("PRIVMSG #grairc :{}", "a".repeat(budget)))
+        .expect(&format!("PRIVMSG #grairc :{}", "a".repeat(20)))
+        .expect("PRIVMSG #grairc :second line");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+
+    for _ in 0..10 {
+        irc.handler(&mut state);
+    }
+    assert_eq!(irc.message_budget("#grairc"), budget);
+    irc.irc_send(&format!("{}\nsecond line\n", long), "#grairc", &mut state)
+        .unwrap();
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(messages(&state, "#grairc").len(), 3);
+}
+
+#[test]
+fn raw_commands_cant_smuggle_in_more_lines() {
+    let server = FakeServer::new().registration("me");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+    pump(&mut irc, &mut state, &servers);
+
+    let error = irc.irc_raw_send("PRIVMSG #a :hi\r\nQUIT :bye").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert!(irc.is_connected());
+}
+
+#[test]
+fn flood_control_holds_lines_back_but_not_pongs() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("PRIVMSG #x :one")
+        .send("PING :token")
+        .expect("PONG :token");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+    irc.set_flood_control(1, Duration::from_secs(3600));
+
+    for _ in 0..10 {
+        irc.handler(&mut state);
+    }
+    irc.irc_send("one\ntwo\nthree", "#x", &mut state).unwrap();
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(irc.queued(), 2);
+    assert_eq!(state.queued_lines, 2);
+}
+
+#[test]
+fn pongs_dont_use_up_the_burst() {
+    let server = FakeServer::new()
+        .registration("me")
+        .send("PING :token")
+        .expect("PONG :token")
+        .expect("PRIVMSG #x :one")
+        .expect("PRIVMSG #x :two");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+    irc.set_flood_control(2, Duration::from_secs(3600));
+
+    for _ in 0..10 {
+        irc.handler(&mut state);
+    }
+    irc.irc_send("one\ntwo\nthree", "#x", &mut state).unwrap();
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(irc.queued(), 1);
+}
+
+#[test]
+fn pongs_go_out_in_the_order_they_were_asked_for() {
+    // the socket blocking keeps the later pongs waiting behind the first one
+    let server = FakeServer::new()
+        .slow_writes(5)
+        .registration("me")
+        .send("PING :first")
+        .send("PING :second")
+        .send("PING :third")
+        .expect("PONG :first")
+        .expect("PONG :second")
+        .expect("PONG :third");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+
+    pump(&mut irc, &mut state, &servers);
+}
+
+#[test]
+fn queued_lines_go_out_as_the_limit_allows() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("PRIVMSG #x :one")
+        .expect("PRIVMSG #x :two")
+        .expect("PRIVMSG #x :three");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+    irc.set_flood_control(1, Duration::from_millis(10));
+
+    for _ in 0..10 {
+        irc.handler(&mut state);
+    }
+    irc.irc_send("one\ntwo\nthree", "#x", &mut state).unwrap();
+    for _ in 0..100 {
+        if servers[0].finished() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+        irc.handler(&mut state);
+    }
+
+    servers[0].assert_finished();
+    assert_eq!(irc.queued(), 0);
+}
+
+#[test]
+fn partial_writes_are_finished_later() {
+    let server = FakeServer::new()
+        .slow_writes(7)
+        .registration("me")
+        .expect("JOIN #grairc")
+        .expect("JOIN #rust");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &["#grairc", "#rust"]);
+
+    pump(&mut irc, &mut state, &servers);
+
+    assert!(irc.registered);
+    assert_eq!(irc.queued(), 0);
+}
+
+#[test]
+fn lag_is_measured_with_our_own_pings() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("PING :grairc-1")
+        .send(":irc.example PONG irc.example :grairc-1")
+        // the next check goes out as soon as the first is answered
+        .expect("PING :grairc-2");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+    irc.set_ping_timing(Duration::ZERO, Duration::from_secs(3600));
+
+    for _ in 0..100 {
+        if servers[0].finished() {
+            break;
+        }
+        irc.handler(&mut state);
+    }
+    irc.set_ping_timing(Duration::from_secs(3600), Duration::from_secs(3600));
+    pump(&mut irc, &mut state, &servers);
+
+    assert!(state.lag.is_some());
+}
+
+#[test]
+fn pongs_inside_privmsgs_dont_fake_the_lag() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("PING :grairc-1")
+        .send(":alice!alice@host PRIVMSG me :x :irc.example PONG irc.example :grairc-1");
+    let servers = [server];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+    irc.set_ping_timing(Duration::ZERO, Duration::from_secs(3600));
+
+    for _ in 0..100 {
+        if servers[0].finished() {
+            break;
+        }
+        irc.handler(&mut state);
+    }
+    // a PING :grairc-2 would mean the fake answer was taken, the script doesnt expect one
+    pump(&mut irc, &mut state, &servers);
+}
+
+#[test]
+fn quiet_connections_time_out_and_reconnect() {
+    let first = FakeServer::new().registration("me");
+    let second = FakeServer::new().registration("me");
+    let servers = [first, second];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+    for _ in 0..1000 {
+        if irc.registered {
+            break;
+        }
+        irc.handler(&mut state);
+    }
+    assert!(irc.registered);
+
+    // only shortened once registered, a slow test machine shouldnt time out the registration
+    irc.set_ping_timing(Duration::from_secs(3600), Duration::from_millis(20));
+    std::thread::sleep(Duration::from_millis(30));
+    irc.handler(&mut state);
+    assert!(!irc.is_connected());
+    assert!(
+        messages(&state, "<system>")
+            .iter()
+            .any(|message| message.contains("nothing from the server"))
+    );
+
+    irc.set_ping_timing(Duration::from_secs(3600), Duration::from_secs(3600));
+    pump(&mut irc, &mut state, &servers);
+    assert!(irc.registered);
+}
+
+#[test]
+fn whois_replies_are_collected_per_nick() {
+    let server = FakeServer::new().registration("me");
+    let servers = [server.clone()];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+    pump(&mut irc, &mut state, &servers);
+
+    server
+        .clone()
+        .expect("WHOIS alice alice")
+        .send(":irc.example 311 me alice ~alice host.example * :Alice Example")
+        .send(":irc.example 319 me alice :@#grairc +#rust")
+        .send(":irc.example 312 me alice irc.example :the example server")
+        .send(":irc.example 301 me alice :gone fishing")
+        .send(":irc.example 671 me alice :is using a secure connection")
+        .send(":irc.example 317 me alice 245 1700000000 :seconds idle, signon time")
+        .send(":irc.example 330 me alice alice_account :is logged in as")
+        .send(":irc.example 318 me alice :End of /WHOIS list.")
+        // nobody asked about bob so this is ignored
+        .send(":irc.example 311 me bob ~bob host.example * :Bob");
+    state.start_lookup("alice");
+    irc.irc_raw_send("WHOIS alice alice").unwrap();
+    pump(&mut irc, &mut state, &servers);
+
+    assert!(state.get_user_info("bob").is_none());
+    let info = state.get_user_info("alice").unwrap();
+    assert!(info.complete);
+    assert_eq!(
+        info.lines(),
+        [
+            "alice (~alice@host.example)",
+            "name: Alice Example",
+            "account: alice_account",
+            "away: gone fishing",
+            "channels: @#grairc +#rust",
+            "server: irc.example (the example server)",
+            "idle: 4m 5s",
+            "connected since: 2023-11-14 22:13 utc",
+            "using a secure connection",
+        ]
+    );
+}
+
+#[test]
+fn whois_falls_back_to_whowas_for_people_who_left() {
+    let server = FakeServer::new().registration("me");
+    let servers = [server.clone()];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+    pump(&mut irc, &mut state, &servers);
+
+    server
+        .clone()
+        .expect("WHOIS carol carol")
+        .send(":irc.example 401 me carol :No such nick/channel")
+        .send(":irc.example 318 me carol :End of /WHOIS list.")
+        .expect("WHOWAS carol")
+        .send(":irc.example 314 me carol ~carol old.example * :Carol")
+        .send(":irc.example 312 me carol irc.example :Sat Oct 18 2026")
+        .send(":irc.example 369 me carol :End of WHOWAS");
+    state.start_lookup("carol");
+    irc.irc_raw_send("WHOIS carol carol").unwrap();
+    pump(&mut irc, &mut state, &servers);
+
+    let info = state.get_user_info("carol").unwrap();
+    assert!(info.offline);
+    assert!(info.error.is_none());
+    assert_eq!(
+        info.lines()[..3],
+        [
+            "carol (~carol@old.example)",
+            "not online, this is from when they were",
+            "name: Carol",
+        ]
+    );
+
+    server
+        .clone()
+        .expect("WHOIS dave dave")
+        .send(":irc.example 401 me dave :No such nick/channel")
+        .send(":irc.example 318 me dave :End of /WHOIS list.")
+        .expect("WHOWAS dave")
+        .send(":irc.example 406 me dave :There was no such nickname")
+        .send(":irc.example 369 me dave :End of WHOWAS");
+    state.start_lookup("dave");
+    irc.irc_raw_send("WHOIS dave dave").unwrap();
+    pump(&mut irc, &mut state, &servers);
+
+    let info = state.get_user_info("dave").unwrap();
+    assert!(info.complete);
+    assert_eq!(info.lines(), ["dave", "There was no such nickname"]);
+}
+
+#[test]
+fn list_replies_fill_the_channel_browser() {
+    let server = FakeServer::new().registration("me");
+    let servers = [server.clone()];
+    let mut state = State::default();
+    let mut irc = connect(&servers, "me", &[]);
+    pump(&mut irc, &mut state, &servers);
+
+    server
+        .clone()
+        .expect("LIST")
+        .send(":irc.example 321 me Channel :Users  Name")
+        .send(":irc.example 322 me #grairc 3 :irc on the 3ds")
+        .send(":irc.example 322 me #rust 400 :[+nt] the rust language")
+        .send(":irc.example 322 me #quiet 1")
+        .send(":irc.example 323 me :End of /LIST");
+    state.update_directory(|directory| directory.start());
+    irc.irc_raw_send("LIST").unwrap();
+    pump(&mut irc, &mut state, &servers);
+
+    assert!(!state.directory.listing);
+    let entries: Vec<(&str, usize, &str)> = state
+        .directory
+        .visible()
+        .map(|(_, entry)| (entry.name.as_str(), entry.users, entry.topic.as_str()))
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            ("#rust", 400, "[+nt] the rust language"),
+            ("#grairc", 3, "irc on the 3ds"),
+            ("#quiet", 1, ""),
+        ]
+    );
+}
+
+#[test]
+fn our_own_part_closes_the_channel() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(":me!me@host JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me alice bob")
+        .send(":alice!alice@host PART #grairc");
+    let servers = [server.clone()];
+    let mut state = unfiltered("#grairc");
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+    pump(&mut irc, &mut state, &servers);
+
+    // a part without a reason still counts
+    assert_eq!(channel_users(&state, "#grairc"), ["me", "bob"]);
+    state.select_channel(1);
+    server
+        .clone()
+        .expect("PART #grairc")
+        .send(":me!me@host PART #grairc");
+    irc.irc_raw_send("PART #grairc").unwrap();
+    pump(&mut irc, &mut state, &servers);
+
+    assert_eq!(state.channels.len(), 1);
+    assert_eq!(state.current_channel_static().name, "<system>");
+}
+
+#[test]
+fn kicks_grey_out_the_channel_until_its_rejoined() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(":me!me@host JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me @alice bob")
+        .send(":alice!alice@host KICK #grairc bob :spamming")
+        .send(":alice!alice@host KICK #grairc me");
+    let servers = [server.clone()];
+    let mut state = unfiltered("#grairc");
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+    pump(&mut irc, &mut state, &servers);
+
+    let channel = &state.channels[1];
+    assert!(!channel.joined);
+    assert!(channel.users.is_empty());
+    assert_eq!(
+        messages(&state, "#grairc")[1..],
+        [
+            "<- bob got kicked by alice (spamming)",
+            "<- you got kicked by alice"
+        ]
+    );
+
+    server
+        .clone()
+        .expect("JOIN #grairc")
+        .send(":me!me@host JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me @alice");
+    irc.irc_raw_send("JOIN #grairc").unwrap();
+    pump(&mut irc, &mut state, &servers);
+
+    assert!(state.channels[1].joined);
+    assert_eq!(channel_users(&state, "#grairc"), ["me", "@alice"]);
+}
+
+#[test]
+fn channel_modes_and_ban_lists_are_tracked() {
+    let server = FakeServer::new()
+        .registration("me")
+        .expect("JOIN #grairc")
+        .send(":me!me@host JOIN #grairc")
+        .send(":irc.example 353 me = #grairc :me @alice bob")
+        .send(":irc.example 324 me #grairc +ntl 50")
+        .send(":irc.example 329 me #grairc 1700000000")
+        .send(":alice!alice@host MODE #grairc +o-l me")
+        .send(":irc.example 367 me #grairc *!*@spam.example alice 1700000000")
+        .send(":irc.example 367 me #grairc troll!*@* alice 1700000001")
+        .send(":irc.example 368 me #grairc :End of Channel Ban List")
+        // our own user modes arent a channel thing
+        .send(":me MODE me :+i");
+    let servers = [server];
+    let mut state = unfiltered("#grairc");
+    let mut irc = connect(&servers, "me", &["#grairc"]);
+    pump(&mut irc, &mut state, &servers);
+
+    let channel = &state.channels[1];
+    assert_eq!(channel.mode_string(), "+nt");
+    assert_eq!(channel.users, ["@me", "@alice", "bob"]);
+    assert_eq!(channel.mode_lists[&'b'], ["*!*@spam.example", "troll!*@*"]);
+    assert_eq!(
+        messages(&state, "#grairc").last().unwrap(),
+        "alice sets +o-l me"
+    );
+    assert_eq!(messages(&state, "<system>").last().unwrap(), "+i");
+}
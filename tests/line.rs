@@ -0,0 +1,41 @@
+use grairc::irc::line::{split_message, validate_line};
+
+#[test]
+fn every_line_break_starts_a_new_message() {
+    assert_eq!(
+        split_message("one\r\ntwo\n\nthree\rfour  ", 100),
+        ["one", "two", "three", "four"]
+    );
+    assert!(split_message("\n \n", 100).is_empty());
+}
+
+#[test]
+fn long_lines_split_between_words() {
+    assert_eq!(
+        split_message("the quick brown fox jumps over the lazy dog", 16),
+        ["the quick brown", "fox jumps over", "the lazy dog"]
+    );
+    // no space near the end, so the word gets cut
+    assert_eq!(
+        split_message("a supercalifragilistic", 10),
+        ["a supercal", "ifragilist", "ic"]
+    );
+}
+
+#[test]
+fn splits_never_land_inside_a_character() {
+    let text = "é".repeat(300) + &"漢字".repeat(100);
+    let pieces = split_message(&text, 101);
+
+    assert!(pieces.iter().all(|piece| piece.len() <= 101));
+    assert_eq!(pieces.concat(), text);
+}
+
+#[test]
+fn line_breaks_and_long_lines_are_refused() {
+    assert!(validate_line("PRIVMSG #a :hi").is_ok());
+    assert!(validate_line("PRIVMSG #a :hi\r\nQUIT :bye").is_err());
+    assert!(validate_line("PRIVMSG #a :hi\nQUIT").is_err());
+    assert!(validate_line(&"a".repeat(510)).is_ok());
+    assert!(validate_line(&"a".repeat(511)).is_err());
+}
@@ -0,0 +1,125 @@
+use grairc::irc::{
+    command::Moderation,
+    mode::{ModeChange, ban_mask, list_needs, parse_modes, prefix_at_least},
+    types::{IrcChannel, IrcChannelType, display_user},
+};
+
+fn change(adding: bool, mode: char, param: Option<&str>) -> ModeChange {
+    ModeChange {
+        adding,
+        mode,
+        param: param.map(|param| param.to_string()),
+    }
+}
+
+#[test]
+fn params_go_to_the_modes_that_take_them() {
+    assert_eq!(
+        parse_modes("+ntlk-o+b", &["50", "hunter2", "alice", "*!*@spam.example"]),
+        [
+            change(true, 'n', None),
+            change(true, 't', None),
+            change(true, 'l', Some("50")),
+            change(true, 'k', Some("hunter2")),
+            change(false, 'o', Some("alice")),
+            change(true, 'b', Some("*!*@spam.example")),
+        ]
+    );
+    // the limit has no param when its taken off, the key still does
+    assert_eq!(
+        parse_modes("-lk", &["*"]),
+        [change(false, 'l', None), change(false, 'k', Some("*"))]
+    );
+    // asking for the ban list isnt a change
+    assert!(parse_modes("+b", &[]).is_empty());
+}
+
+#[test]
+fn modes_update_the_channel() {
+    let mut channel = IrcChannel {
+        users: vec![
+            "@alice".to_string(),
+            "bob".to_string(),
+            "+carol".to_string(),
+        ],
+        ..IrcChannel::new("#grairc", IrcChannelType::Channel)
+    };
+    channel.mode_lists.insert('b', vec![]);
+    let modes = parse_modes("+ovlb-v", &["bob", "alice", "50", "*!*@spam", "carol"]);
+    for change in &modes {
+        channel.apply_mode(change);
+    }
+
+    // every prefix is kept, highest first
+    assert_eq!(channel.users, ["@+alice", "@bob", "carol"]);
+    assert_eq!(channel.prefix_of("alice"), Some('@'));
+    assert_eq!(display_user(&channel.users[0]), "@alice");
+    assert_eq!(channel.mode_string(), "+l 50");
+    assert_eq!(channel.mode_lists[&'b'], ["*!*@spam"]);
+
+    for change in &parse_modes("-ob", &["alice", "*!*@spam"]) {
+        channel.apply_mode(change);
+    }
+    assert_eq!(channel.prefixes_of("alice"), "+");
+    assert_eq!(channel.prefixes_of("carol"), "");
+    assert!(channel.mode_lists[&'b'].is_empty());
+}
+
+#[test]
+fn op_tools_depend_on_our_prefix() {
+    assert!(prefix_at_least(Some('~'), '@'));
+    assert!(prefix_at_least(Some('@'), '%'));
+    assert!(!prefix_at_least(Some('+'), '%'));
+    assert!(!prefix_at_least(None, '%'));
+
+    assert!(Moderation::available(None, "").is_empty());
+    assert!(Moderation::available(Some('+'), "").is_empty());
+    assert_eq!(
+        Moderation::available(Some('%'), "+"),
+        [
+            Moderation::Devoice,
+            Moderation::Kick,
+            Moderation::Ban,
+            Moderation::KickBan
+        ]
+    );
+    assert_eq!(
+        Moderation::available(Some('@'), "@")[..2],
+        [Moderation::Deop, Moderation::Voice]
+    );
+    // a voiced op gets both taken away
+    assert_eq!(
+        Moderation::available(Some('@'), "@+")[..2],
+        [Moderation::Deop, Moderation::Devoice]
+    );
+    // halfops cant touch ops, and nobody can touch the owner but another owner
+    assert!(Moderation::available(Some('%'), "@+").is_empty());
+    assert!(Moderation::available(Some('@'), "~").is_empty());
+    assert_eq!(Moderation::available(Some('~'), "~@").len(), 5);
+}
+
+#[test]
+fn halfops_can_only_take_off_bans() {
+    assert!(prefix_at_least(Some('%'), list_needs('b')));
+    assert!(!prefix_at_least(Some('%'), list_needs('e')));
+    assert!(!prefix_at_least(Some('%'), list_needs('I')));
+    assert!(prefix_at_least(Some('@'), list_needs('I')));
+    assert!(!prefix_at_least(Some('+'), list_needs('b')));
+}
+
+#[test]
+fn kickbans_ban_before_kicking() {
+    let mask = ban_mask("spammer", Some("spam.example"));
+    assert_eq!(
+        Moderation::KickBan.lines("#grairc", "spammer", &mask, Some("bye")),
+        [
+            "MODE #grairc +b *!*@spam.example",
+            "KICK #grairc spammer :bye"
+        ]
+    );
+    assert_eq!(
+        Moderation::Kick.lines("#grairc", "spammer", &ban_mask("spammer", None), None),
+        ["KICK #grairc spammer"]
+    );
+    assert_eq!(ban_mask("spammer", None), "spammer!*@*");
+}
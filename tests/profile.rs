@@ -0,0 +1,227 @@
+use std::path::{Path, PathBuf};
+
+use grairc::{
+    app::{App, Prompt, Prompter},
+    config::CONFIG_VERSION,
+    history::History,
+    irc::types::IrcMessage,
+    logs,
+    profile::{Profile, validate_profile_name},
+    state::Panel,
+};
+use time::Time;
+
+/// a fresh directory in the system temp dir, removed when dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("grairc-{}-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+fn names(profiles: &[Profile]) -> Vec<&str> {
+    profiles
+        .iter()
+        .map(|profile| profile.name.as_str())
+        .collect()
+}
+
+#[test]
+fn create_duplicate_and_delete() {
+    let root = TempDir::new("profiles");
+
+    let alice = Profile::create(&root.0, "alice").unwrap();
+    assert!(alice.logs_dir().is_dir());
+    std::fs::write(alice.config_path(), "nickname = \"alice\"\n").unwrap();
+    Profile::create(&root.0, "Bob").unwrap();
+    assert!(Profile::create(&root.0, "alice").is_err());
+
+    let copy = alice.duplicate("carol").unwrap();
+    assert_eq!(
+        std::fs::read_to_string(copy.config_path()).unwrap(),
+        "nickname = \"alice\"\n"
+    );
+    assert_eq!(names(&Profile::list(&root.0)), ["alice", "Bob", "carol"]);
+
+    alice.delete().unwrap();
+    assert_eq!(names(&Profile::list(&root.0)), ["Bob", "carol"]);
+}
+
+#[test]
+fn the_old_config_becomes_the_default_profile() {
+    let dir = TempDir::new("legacy");
+    let root = dir.0.join("profiles");
+    let legacy = dir.0.join("config.toml");
+    std::fs::write(&legacy, "nickname = \"old\"\n").unwrap();
+
+    let profile = Profile::migrate_legacy(&root, &legacy).unwrap().unwrap();
+    assert_eq!(profile.name, "default");
+    assert!(!legacy.exists());
+    assert_eq!(
+        std::fs::read_to_string(profile.config_path()).unwrap(),
+        "nickname = \"old\"\n"
+    );
+
+    // only ever happens once
+    std::fs::write(&legacy, "nickname = \"old\"\n").unwrap();
+    assert_eq!(Profile::migrate_legacy(&root, &legacy).unwrap(), None);
+}
+
+#[test]
+fn profile_names() {
+    assert!(validate_profile_name("alice").is_ok());
+    assert!(validate_profile_name("work laptop").is_ok());
+    for name in ["", "  ", ".", "..", ".hidden", "a/b", "a\\b", "what?"] {
+        assert!(
+            validate_profile_name(name).is_err(),
+            "{:?} was accepted",
+            name
+        );
+    }
+}
+
+#[test]
+fn logs_are_appended_per_channel() {
+    let dir = TempDir::new("logs");
+    let time = Time::from_hms(9, 5, 3).unwrap();
+    let message = IrcMessage {
+        nick: Some("alice".to_string()),
+        content: "hi".to_string(),
+    };
+    let event = IrcMessage {
+        nick: None,
+        content: "-> bob joined".to_string(),
+    };
+
+    logs::append(&dir.0, "#grairc", time, &message).unwrap();
+    logs::append(&dir.0, "#grairc", time, &event).unwrap();
+    logs::append(&dir.0, "<system>", time, &event).unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(dir.0.join("#grairc.log")).unwrap(),
+        "[09:05:03] <alice> hi\n[09:05:03] * -> bob joined\n"
+    );
+    assert_eq!(
+        logs::log_path(&dir.0, "<system>"),
+        dir.0.join("_system_.log")
+    );
+}
+
+#[test]
+fn history_survives_a_restart() {
+    let root = TempDir::new("history");
+    let profile = Profile::create(&root.0, "alice").unwrap();
+    assert_eq!(
+        History::load(&profile.history_path()),
+        Ok(History::default())
+    );
+
+    let mut history = History::default();
+    history.push("#rust", "hello");
+    history.push_raw("MODE #rust +i");
+    history.save(&profile.history_path()).unwrap();
+
+    let loaded = History::load(&profile.history_path()).unwrap();
+    assert_eq!(loaded, history);
+    assert_eq!(loaded.lines("#Rust"), ["hello"]);
+}
+
+#[test]
+fn passwords_arent_saved_in_the_history() {
+    let root = TempDir::new("secret-history");
+    let profile = Profile::create(&root.0, "alice").unwrap();
+
+    let mut history = History::default();
+    history.push("#rust", "hello");
+    history.push("#rust", "/msg NickServ IDENTIFY hunter2");
+    history.push("#rust", "/ns identify hunter2");
+    history.push("#rust", "/quote OPER alice hunter2");
+    history.push("NickServ", "identify hunter2");
+    history.push_raw("MODE #rust +i");
+    history.push_raw("PASS hunter2");
+    history.push_raw("privmsg nickserv :identify hunter2");
+    history.push_raw("AUTHENTICATE aHVudGVyMg==");
+    history.save(&profile.history_path()).unwrap();
+
+    // theyre still there to recall until grairc closes
+    assert_eq!(history.lines("#rust").len(), 4);
+    let loaded = History::load(&profile.history_path()).unwrap();
+    assert_eq!(loaded.lines("#rust"), ["hello"]);
+    assert!(loaded.lines("nickserv").is_empty());
+    assert_eq!(loaded.raw, ["MODE #rust +i"]);
+}
+
+/// answers prompts in order, any prompt past the end is a test failure
+struct ScriptedPrompter(Vec<&'static str>);
+
+impl Prompter for ScriptedPrompter {
+    fn prompt(&mut self, prompt: &Prompt) -> Option<String> {
+        assert!(!self.0.is_empty(), "unexpected prompt: {}", prompt.hint);
+        Some(self.0.remove(0).to_string())
+    }
+}
+
+fn open_profile(app: &mut App, root: &Path) {
+    app.state.profile = Some(Profile::new(root, "alice"));
+}
+
+#[test]
+fn configs_from_newer_versions_are_left_alone() {
+    let root = TempDir::new("too-new");
+    let profile = Profile::create(&root.0, "alice").unwrap();
+    let contents = format!(
+        "config_version = {}\nnickname = \"future\"\nserver_hostname = \"irc.example\"\n",
+        CONFIG_VERSION + 1
+    );
+    std::fs::write(profile.config_path(), &contents).unwrap();
+
+    let mut app = App::default();
+    for _ in 0..2 {
+        open_profile(&mut app, &root.0);
+        assert!(!app.load_config(&mut ScriptedPrompter(vec![])));
+        assert!(app.state.profile.is_none());
+        assert_eq!(app.state.panel, Panel::Profiles);
+        assert_eq!(
+            std::fs::read_to_string(profile.config_path()).unwrap(),
+            contents
+        );
+    }
+}
+
+#[test]
+fn broken_configs_are_shown_before_the_wizard_runs() {
+    let root = TempDir::new("broken");
+    let profile = Profile::create(&root.0, "alice").unwrap();
+    std::fs::write(profile.config_path(), "nickname = ").unwrap();
+
+    // back to the picker with the error, the wizard only runs when its opened again
+    let mut app = App::default();
+    open_profile(&mut app, &root.0);
+    assert!(!app.load_config(&mut ScriptedPrompter(vec![])));
+    assert_eq!(app.state.panel, Panel::Profiles);
+    assert!(
+        app.state.channels[0]
+            .messages
+            .iter()
+            .any(|message| message.content.starts_with("couldnt load"))
+    );
+    assert_eq!(
+        std::fs::read_to_string(profile.config_path().with_extension("toml.bak")).unwrap(),
+        "nickname = "
+    );
+
+    open_profile(&mut app, &root.0);
+    let answers = vec!["me", "irc.example", "6667", "#grairc"];
+    assert!(app.load_config(&mut ScriptedPrompter(answers)));
+    assert_eq!(app.state.config.as_ref().unwrap().nickname, "me");
+}
@@ -0,0 +1,521 @@
+//! golden image tests for the renderers
+//!
+//! each test draws into an in-memory framebuffer and compares it against the png with the same
+//! name in `tests/snapshots`. with `GRAIRC_BLESS=1` set the current output gets written as the
+//! new golden image, otherwise a mismatch (or a missing snapshot) writes `<name>.actual.png`
+//! next to it so the two can be compared
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use grairc::{
+    config::Config,
+    directory::DirectoryEntry,
+    gfx::{
+        memory::{MemoryDisplay, MemoryTargets},
+        renderers::{
+            Renderer, render_bar, render_bottom_screen, render_channels, render_main_screen,
+            render_messages, render_user_list, widgets,
+        },
+        wrap::WrapCache,
+    },
+    irc::types::{IrcChannel, IrcChannelType, IrcMessage, Membership},
+    profile::Profile,
+    state::{BatteryState, Panel, State},
+};
+use time::Time;
+
+type TopScreen = MemoryDisplay<400, 240>;
+type BottomScreen = MemoryDisplay<320, 240>;
+
+fn snapshot_path(name: &str, suffix: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{}{}.png", name, suffix))
+}
+
+fn write_png<const W: u32, const H: u32>(display: &MemoryDisplay<W, H>, path: &PathBuf) {
+    let file = File::create(path).expect("couldnt create snapshot");
+    let mut encoder = png::Encoder::new(BufWriter::new(file), W, H);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&display.to_rgb_bytes()))
+        .expect("couldnt write snapshot");
+}
+
+fn read_png(path: &PathBuf) -> (u32, u32, Vec<u8>) {
+    let decoder = png::Decoder::new(File::open(path).expect("couldnt open snapshot"));
+    let mut reader = decoder.read_info().expect("couldnt read snapshot");
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .expect("couldnt decode snapshot");
+    assert_eq!(
+        (info.color_type, info.bit_depth),
+        (png::ColorType::Rgb, png::BitDepth::Eight),
+        "snapshots have to be 8 bit rgb"
+    );
+    buffer.truncate(info.buffer_size());
+    (info.width, info.height, buffer)
+}
+
+fn assert_snapshot<const W: u32, const H: u32>(name: &str, display: &MemoryDisplay<W, H>) {
+    let path = snapshot_path(name, "");
+    if std::env::var_os("GRAIRC_BLESS").is_some() {
+        write_png(display, &path);
+        return;
+    }
+    if !path.exists() {
+        let actual_path = snapshot_path(name, ".actual");
+        write_png(display, &actual_path);
+        panic!(
+            "{} has no snapshot, rerun with GRAIRC_BLESS=1 to add it and check {}",
+            name,
+            actual_path.display()
+        );
+    }
+
+    let (width, height, expected) = read_png(&path);
+    let actual = display.to_rgb_bytes();
+    let differing = expected
+        .chunks(3)
+        .zip(actual.chunks(3))
+        .filter(|(expected, actual)| expected != actual)
+        .count();
+
+    if (width, height) != (W, H) || differing > 0 {
+        let actual_path = snapshot_path(name, ".actual");
+        write_png(display, &actual_path);
+        panic!(
+            "{} doesnt match its snapshot ({} pixels differ), see {}",
+            name,
+            differing,
+            actual_path.display()
+        );
+    }
+}
+
+fn message(nick: &str, content: &str) -> IrcMessage {
+    IrcMessage {
+        nick: Some(nick.to_string()),
+        content: content.to_string(),
+    }
+}
+
+fn channel(name: &str, users: &[&str], messages: Vec<IrcMessage>) -> IrcChannel {
+    IrcChannel {
+        users: users.iter().map(|user| user.to_string()).collect(),
+        messages,
+        ..IrcChannel::new(name, IrcChannelType::Channel)
+    }
+}
+
+/// a state with `channel` selected
+fn state_with(channel: IrcChannel) -> State {
+    let mut state = State::default();
+    state.channels.push(channel);
+    state.select_channel(1);
+    state
+}
+
+#[test]
+fn messages_with_long_nicks() {
+    let mut state = state_with(channel(
+        "#grairc",
+        &[],
+        vec![
+            message("me", "short nick"),
+            message("exactly12chr", "nick that fills the whole column"),
+            message("averyveryverylongnickname", "nick longer than the column"),
+            IrcMessage {
+                nick: None,
+                content: "-> someone joined".to_string(),
+            },
+        ],
+    ));
+    state.channels[1].users = vec!["me".to_string()];
+
+    let mut display = TopScreen::default();
+    render_messages(&mut display, &state).unwrap();
+    assert_snapshot("messages_long_nicks", &display);
+}
+
+#[test]
+fn messages_wrap_edge_cases() {
+    let state = state_with(channel(
+        "#grairc",
+        &[],
+        vec![
+            message("alice", &"x".repeat(36)),
+            message("alice", &"x".repeat(37)),
+            message("alice", &"y".repeat(120)),
+            message(
+                "bob",
+                "a message with plenty of words in it that has to wrap somewhere in the middle",
+            ),
+            message("carol", ""),
+            message("dave", "trailing spaces   "),
+        ],
+    ));
+
+    let mut display = TopScreen::default();
+    render_messages(&mut display, &state).unwrap();
+    assert_snapshot("messages_wrap", &display);
+}
+
+#[test]
+fn messages_in_other_scripts() {
+    let state = state_with(channel(
+        "#grairc",
+        &[],
+        vec![
+            message("zoë", "café, naïve, Łódź, Ærøskøbing"),
+            message("дмитрий", "привет всем"),
+            message("ελένη", "καλημέρα"),
+            message("小明", "你好，世界 こんにちは"),
+            message("emoji", "no glyph for these 😀🎉 so they get boxes"),
+        ],
+    ));
+
+    let mut display = TopScreen::default();
+    render_messages(&mut display, &state).unwrap();
+    assert_snapshot("messages_unicode", &display);
+}
+
+#[test]
+fn messages_with_formatting() {
+    let state = state_with(channel(
+        "#grairc",
+        &[],
+        vec![
+            message(
+                "bold",
+                "\x02bold\x02 and \x1Ditalic\x1D and \x1Funderlined\x1F",
+            ),
+            message("color", "\x0304red\x03, \x034,12red on blue\x0F and back"),
+            message(
+                "reverse",
+                "\x16reversed\x16, a comma after a color\x033, stays",
+            ),
+            message("wrap", &"\x02formatted\x0F ".repeat(12)),
+        ],
+    ));
+
+    let mut display = TopScreen::default();
+    render_messages(&mut display, &state).unwrap();
+    assert_snapshot("messages_formatted", &display);
+}
+
+#[test]
+fn messages_scrolled_to_the_newest() {
+    let messages = (0..40)
+        .map(|i| message("counter", &format!("message number {}", i)))
+        .collect();
+    let state = state_with(channel("#grairc", &[], messages));
+
+    let mut display = TopScreen::default();
+    render_messages(&mut display, &state).unwrap();
+    assert_snapshot("messages_overflow", &display);
+}
+
+#[test]
+fn messages_in_an_empty_channel() {
+    let state = state_with(channel("#empty", &[], vec![]));
+
+    let mut display = TopScreen::default();
+    render_messages(&mut display, &state).unwrap();
+    assert_snapshot("messages_empty", &display);
+}
+
+#[test]
+fn bar() {
+    let mut state = State {
+        config: Some(Config {
+            nickname: "grairc".to_string(),
+            server_hostname: "irc.example".to_string(),
+            server_port: 6667,
+            channels: vec![],
+            ..Config::default()
+        }),
+        ..State::default()
+    };
+    state.set_clock(Time::from_hms(13, 37, 42).unwrap());
+    state.set_battery_state(BatteryState::Medium);
+
+    let mut display = TopScreen::default();
+    render_bar(&mut display, &state).unwrap();
+    assert_snapshot("bar", &display);
+
+    // the nick the server gave us wins over the configured one
+    state.set_nickname("grairc1");
+    state.set_battery_state(BatteryState::Charging);
+    let mut display = TopScreen::default();
+    render_bar(&mut display, &state).unwrap();
+    assert_snapshot("bar_collided_nick", &display);
+
+    state.set_queued_lines(3);
+    let mut display = TopScreen::default();
+    render_bar(&mut display, &state).unwrap();
+    assert_snapshot("bar_queued", &display);
+
+    state.set_lag(Some(Duration::from_millis(6340)));
+    let mut display = TopScreen::default();
+    render_bar(&mut display, &state).unwrap();
+    assert_snapshot("bar_lagging", &display);
+}
+
+#[test]
+fn user_list_with_long_nicks() {
+    let state = state_with(channel(
+        "#grairc",
+        &["@op", "+voiced", "averyveryverylongnickname", "me"],
+        vec![],
+    ));
+
+    let mut display = TopScreen::default();
+    render_user_list(&mut display, &state).unwrap();
+    assert_snapshot("user_list_long_nicks", &display);
+}
+
+#[test]
+fn user_list_overflowing() {
+    let users: Vec<String> = (0..40).map(|i| format!("user{}", i)).collect();
+    let users: Vec<&str> = users.iter().map(String::as_str).collect();
+    let state = state_with(channel("#busy", &users, vec![]));
+
+    let mut display = TopScreen::default();
+    render_user_list(&mut display, &state).unwrap();
+    assert_snapshot("user_list_overflow", &display);
+}
+
+#[test]
+fn user_list_in_an_empty_channel() {
+    let state = state_with(channel("#empty", &[], vec![]));
+
+    let mut display = TopScreen::default();
+    render_user_list(&mut display, &state).unwrap();
+    assert_snapshot("user_list_empty", &display);
+}
+
+#[test]
+fn channels() {
+    let mut state = State::default();
+    for name in ["#grairc", "#a-channel-with-a-really-long-name", "#rust"] {
+        state.channels.push(channel(name, &[], vec![]));
+    }
+    state.select_channel(2);
+
+    let mut display = BottomScreen::default();
+    render_channels(&mut display, &state).unwrap();
+    assert_snapshot("channels", &display);
+}
+
+#[test]
+fn channels_scrolled() {
+    let mut state = State::default();
+    for i in 0..30 {
+        state
+            .channels
+            .push(channel(&format!("#channel{}", i), &[], vec![]));
+    }
+    state.select_channel(20);
+    state.channel_scroll = 200;
+
+    let mut display = BottomScreen::default();
+    render_channels(&mut display, &state).unwrap();
+    assert_snapshot("channels_scrolled", &display);
+}
+
+#[test]
+fn settings_panel() {
+    let mut state = State::default();
+    state.set_panel(Panel::Settings);
+    state.set_theme("solarized");
+
+    let mut display = BottomScreen::default();
+    render_bottom_screen(&mut display, &state).unwrap();
+    assert_snapshot("settings_panel", &display);
+}
+
+#[test]
+fn settings_panel_scrolled() {
+    let mut state = State::default();
+    state.set_panel(Panel::Settings);
+    widgets::scroll_settings(&mut state, 100);
+
+    let mut display = BottomScreen::default();
+    render_bottom_screen(&mut display, &state).unwrap();
+    assert_snapshot("settings_panel_scrolled", &display);
+}
+
+#[test]
+fn light_theme() {
+    let mut state = state_with(channel(
+        "#grairc",
+        &["alice", "bob"],
+        vec![message("alice", "hi grairc"), message("bob", "hello")],
+    ));
+    state.nickname = Some("grairc".to_string());
+    state.set_theme("light");
+
+    let mut display = TopScreen::default();
+    render_main_screen(&mut display, &state).unwrap();
+    assert_snapshot("light_theme", &display);
+}
+
+#[test]
+fn profile_picker() {
+    let mut state = State::default();
+    state.set_profiles(
+        ["alice", "bob", "shared"]
+            .iter()
+            .map(|name| Profile::new(Path::new("/profiles"), name))
+            .collect(),
+    );
+    state.select_profile(1);
+    state.set_panel(Panel::Profiles);
+
+    let mut display = BottomScreen::default();
+    render_bottom_screen(&mut display, &state).unwrap();
+    assert_snapshot("profile_picker", &display);
+}
+
+#[test]
+fn completion_picker() {
+    let mut state = state_with(channel(
+        "#grairc",
+        &["@alice", "bob", "grairc", "+carol"],
+        vec![message("carol", "hi")],
+    ));
+    state.nickname = Some("grairc".to_string());
+    state.set_panel(Panel::Compose);
+    state.move_completion_selection(1);
+
+    let mut display = BottomScreen::default();
+    render_bottom_screen(&mut display, &state).unwrap();
+    assert_snapshot("completion_picker", &display);
+}
+
+#[test]
+fn user_panel() {
+    let mut state = state_with(channel("#grairc", &["@alice", "bob"], vec![]));
+    state.set_panel(Panel::User);
+    state.start_lookup("alice");
+    state.select_user(Some("alice"));
+    state.update_user_info("alice", |info| {
+        for (numeric, params) in [
+            ("311", &["~alice", "host.example", "*", "Alice Example"][..]),
+            ("319", &["@#grairc"]),
+            ("317", &["245", "1700000000", "seconds idle, signon time"]),
+        ] {
+            info.apply(numeric, params);
+        }
+    });
+
+    let mut display = BottomScreen::default();
+    render_bottom_screen(&mut display, &state).unwrap();
+    assert_snapshot("user_panel", &display);
+
+    let mut display = TopScreen::default();
+    render_user_list(&mut display, &state).unwrap();
+    assert_snapshot("user_list_selected", &display);
+}
+
+#[test]
+fn channel_browser() {
+    let mut state = State::default();
+    state.set_panel(Panel::Directory);
+    state.update_directory(|directory| {
+        directory.start();
+        for (name, users, topic) in [
+            ("#rust", 400, "the rust language"),
+            ("#grairc", 3, "irc on the 3ds"),
+            ("#3ds", 120, "homebrew talk"),
+        ] {
+            directory.push(DirectoryEntry {
+                name: name.to_string(),
+                users,
+                topic: topic.to_string(),
+            });
+        }
+        directory.set_filter("r");
+        directory.move_selection(1);
+    });
+
+    let mut display = BottomScreen::default();
+    render_bottom_screen(&mut display, &state).unwrap();
+    assert_snapshot("channel_browser", &display);
+}
+
+#[test]
+fn moderation_panel() {
+    let mut state = state_with(channel("#grairc", &["@grairc", "bob"], vec![]));
+    state.nickname = Some("grairc".to_string());
+    state.channels[1].modes.insert('n', None);
+    state.channels[1].modes.insert('t', None);
+    state.set_panel(Panel::Moderation);
+
+    let mut display = BottomScreen::default();
+    render_bottom_screen(&mut display, &state).unwrap();
+    assert_snapshot("moderation_panel", &display);
+
+    // kick, ban and the rest show up under whats known about someone
+    state.start_lookup("bob");
+    state.select_user(Some("bob"));
+    state.set_panel(Panel::User);
+    let mut display = BottomScreen::default();
+    render_bottom_screen(&mut display, &state).unwrap();
+    assert_snapshot("user_panel_op_tools", &display);
+}
+
+#[test]
+fn mode_list_read_only() {
+    // halfops can see the ban exceptions but only ops can remove them
+    let mut state = state_with(channel("#grairc", &["%grairc", "bob"], vec![]));
+    state.nickname = Some("grairc".to_string());
+    state.open_mode_list('e');
+    state.current_channel().mode_lists.insert(
+        'e',
+        vec!["*!*@friend.example".to_string(), "bob!*@*".to_string()],
+    );
+    state.move_mask_selection(1);
+    assert!(!state.can_edit_mode_list());
+
+    let mut display = BottomScreen::default();
+    render_bottom_screen(&mut display, &state).unwrap();
+    assert_snapshot("mode_list_read_only", &display);
+}
+
+/// the renderer only builds whats dirty and keeps messages wrapped between frames, it still
+/// has to end up with what drawing everything from scratch gives
+#[test]
+fn renderer_matches_a_full_redraw() {
+    let mut state = state_with(channel(
+        "#grairc",
+        &["alice"],
+        vec![message("alice", &"wrapped ".repeat(20))],
+    ));
+    let mut targets = MemoryTargets::default();
+    let mut renderer = Renderer::default();
+    renderer.render(&mut targets, &mut state).unwrap();
+
+    state.push_message("#grairc", message("alice", "and another one"));
+    renderer.render(&mut targets, &mut state).unwrap();
+    state.push_membership("#grairc", Membership::Part("alice".to_string()));
+    renderer.render(&mut targets, &mut state).unwrap();
+    // folded into the part, which changes a message thats already wrapped
+    state.push_membership("#grairc", Membership::Join("alice".to_string()));
+    renderer.render(&mut targets, &mut state).unwrap();
+
+    // from scratch means without anything wrapped already
+    state.current_channel().wrapped = WrapCache::default();
+    let mut display = TopScreen::default();
+    render_main_screen(&mut display, &state).unwrap();
+    assert!(targets.top.pixels == display.pixels);
+}
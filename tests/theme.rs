@@ -0,0 +1,36 @@
+use embedded_graphics::pixelcolor::Rgb888;
+use grairc::theme::Theme;
+
+#[test]
+fn missing_colors_come_from_the_dark_theme() {
+    let theme = Theme::parse("text = \"#ff8000\"\n", "orange").unwrap();
+    assert_eq!(theme.name, "orange");
+    assert_eq!(theme.text, Rgb888::new(0xff, 0x80, 0x00));
+    assert_eq!(theme.background, Theme::dark().background);
+}
+
+#[test]
+fn name_in_the_file_wins() {
+    let theme = Theme::parse("name = \"sunset\"\n", "file").unwrap();
+    assert_eq!(theme.name, "sunset");
+}
+
+#[test]
+fn invalid_colors_are_rejected() {
+    for color in ["ff8000", "#ff80", "#gg0000", "#ff80000"] {
+        let toml = format!("text = \"{}\"\n", color);
+        assert!(
+            Theme::parse(&toml, "bad").is_err(),
+            "{} was accepted",
+            color
+        );
+    }
+}
+
+#[test]
+fn builtin_themes_round_trip() {
+    for theme in Theme::builtin() {
+        let toml = toml::to_string(&theme).unwrap();
+        assert_eq!(Theme::parse(&toml, "").unwrap(), theme);
+    }
+}
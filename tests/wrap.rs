@@ -0,0 +1,60 @@
+use grairc::gfx::wrap::wrap;
+
+/// every char is one unit wide, so widths are just char counts
+fn wrap_chars(text: &str, width: i32) -> Vec<&str> {
+    wrap(text, width, |_| 1)
+}
+
+#[test]
+fn short_text_stays_on_one_line() {
+    assert_eq!(wrap_chars("hello there", 20), ["hello there"]);
+    assert_eq!(wrap_chars("exactly10!", 10), ["exactly10!"]);
+}
+
+#[test]
+fn breaks_between_words() {
+    assert_eq!(
+        wrap_chars("the quick brown fox jumps over the lazy dog", 10),
+        ["the quick", "brown fox", "jumps over", "the lazy", "dog"]
+    );
+}
+
+#[test]
+fn no_characters_are_lost_at_breaks() {
+    let text = "a".repeat(25);
+    let lines = wrap_chars(&text, 10);
+    assert_eq!(lines, ["a".repeat(10), "a".repeat(10), "a".repeat(5)]);
+}
+
+#[test]
+fn only_overlong_words_get_hard_broken() {
+    assert_eq!(
+        wrap_chars("see https://example.com/a/very/long/path ok", 12),
+        ["see", "https://exam", "ple.com/a/ve", "ry/long/path", "ok"]
+    );
+}
+
+#[test]
+fn whitespace_at_breaks_is_dropped() {
+    assert_eq!(wrap_chars("one    two", 5), ["one", "two"]);
+    assert_eq!(wrap_chars("trailing   ", 20), ["trailing"]);
+}
+
+#[test]
+fn leading_whitespace_is_kept() {
+    assert_eq!(wrap_chars("  indented text", 20), ["  indented text"]);
+}
+
+#[test]
+fn newlines_start_new_lines() {
+    assert_eq!(wrap_chars("one\ntwo three", 20), ["one", "two three"]);
+    assert_eq!(wrap_chars("", 20), [""]);
+}
+
+#[test]
+fn measures_with_the_given_widths() {
+    // wide chars take up two units, like they would with a proportional font
+    let width = |c: char| if c.is_ascii() { 1 } else { 2 };
+    assert_eq!(wrap("ab 日本語 cd", 6, width), ["ab", "日本語", "cd"]);
+    assert_eq!(wrap("日本語日本語", 5, width), ["日本", "語日", "本語"]);
+}